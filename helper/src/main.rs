@@ -84,10 +84,6 @@ fn validate_pacman(args: &[String]) -> Result<()> {
         }
     }
 
-    if op == "-Syu" && !pkgs.is_empty() {
-        return Err(anyhow!("-Syu does not accept packages"));
-    }
-
     if pkgs.len() > 200 {
         return Err(anyhow!("too many packages"));
     }
@@ -96,7 +92,10 @@ fn validate_pacman(args: &[String]) -> Result<()> {
 }
 
 fn is_allowed_flag(flag: &str) -> bool {
-    matches!(flag, "--noconfirm" | "--needed" | "--noprogressbar")
+    matches!(
+        flag,
+        "--noconfirm" | "--needed" | "--noprogressbar" | "--asdeps" | "--asexplicit"
+    )
 }
 
 fn is_safe_pkg(name: &str) -> bool {
@@ -1,5 +1,6 @@
 use libadwaita as adw;
 use adw::prelude::*;
+use gtk::gio;
 
 use crate::ui;
 
@@ -13,8 +14,19 @@ impl AuroraApp {
             .application_id("io.github.ahmoodio.aurora")
             .build();
 
-        app.connect_startup(|_| {
+        app.connect_startup(|app| {
             adw::init();
+
+            // Closing the windows instead of quitting outright lets them save their state.
+            let quit = gio::SimpleAction::new("quit", None);
+            let app_for_quit = app.clone();
+            quit.connect_activate(move |_, _| {
+                for window in app_for_quit.windows() {
+                    window.close();
+                }
+            });
+            app.add_action(&quit);
+            app.set_accels_for_action("app.quit", &["<Primary>q"]);
         });
 
         app.connect_activate(|app| {
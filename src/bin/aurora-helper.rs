@@ -1,6 +1,7 @@
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
 use std::process::Command;
 
 use anyhow::{anyhow, Result};
@@ -24,6 +25,9 @@ fn run() -> Result<()> {
     match target.as_str() {
         "pacman" => run_pacman(args),
         "clear-pacman-lock" => clear_pacman_lock(),
+        "clean-cache" => clean_package_cache(args),
+        "refresh-mirrors" => refresh_mirrors(),
+        "read-pacnew" => read_pacnew(args),
         _ => Err(anyhow!("unsupported target: {target}")),
     }
 }
@@ -31,7 +35,7 @@ fn run() -> Result<()> {
 fn ensure_root() -> Result<()> {
     let uid = unsafe { libc::geteuid() };
     if uid != 0 {
-        return Err(anyhow!("must be run as root via pkexec"));
+        return Err(anyhow!("must be run as root via pkexec or sudo"));
     }
     Ok(())
 }
@@ -47,7 +51,11 @@ fn run_pacman(mut args: Vec<String>) -> Result<()> {
     // pacman does not need it for normal package names, so drop it to simplify validation.
     args.retain(|arg| arg != "--");
 
-    validate_pacman(&args)?;
+    // The prefix lets Aurora tell a refused command apart from a pacman failure.
+    if let Err(err) = validate_pacman(&args) {
+        eprintln!("aurora-helper: validation: {err}");
+        std::process::exit(1);
+    }
 
     let status = Command::new("pacman")
         .args(&args)
@@ -61,16 +69,166 @@ fn clear_pacman_lock() -> Result<()> {
     ensure_no_package_manager_running()?;
 
     let lock_path = "/var/lib/pacman/db.lck";
-    if !Path::new(lock_path).exists() {
+    let Ok(metadata) = fs::metadata(lock_path) else {
         println!("No pacman lock file present.");
         return Ok(());
+    };
+
+    // libalpm keeps the lock file open for as long as it holds the database lock, which
+    // also catches a package manager the name-based check above does not know about.
+    let holders = lock_holders(Path::new(lock_path));
+    if !holders.is_empty() {
+        return Err(anyhow!("pacman lock is held open by: {}", holders.join(", ")));
     }
 
+    let age = metadata.modified().ok().and_then(|modified| modified.elapsed().ok());
     fs::remove_file(lock_path)?;
-    println!("Removed stale pacman lock: {lock_path}");
+    match age {
+        Some(age) => println!(
+            "Removed stale pacman lock: {lock_path} (created {} ago, not held open by any process)",
+            describe_age(age.as_secs())
+        ),
+        None => println!("Removed stale pacman lock: {lock_path}"),
+    }
+    Ok(())
+}
+
+fn lock_holders(lock_path: &Path) -> Vec<String> {
+    let Ok(procs) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    procs
+        .flatten()
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            let holds_lock = fs::read_dir(entry.path().join("fd"))
+                .ok()?
+                .flatten()
+                .any(|fd| fs::read_link(fd.path()).is_ok_and(|target| target == lock_path));
+            if !holds_lock {
+                return None;
+            }
+            let name = fs::read_to_string(entry.path().join("comm")).unwrap_or_default();
+            Some(format!("{} ({pid})", name.trim()))
+        })
+        .collect()
+}
+
+fn describe_age(secs: u64) -> String {
+    let (value, unit) = match secs {
+        0..=59 => (secs, "second"),
+        60..=3599 => (secs / 60, "minute"),
+        3600..=86_399 => (secs / 3600, "hour"),
+        _ => (secs / 86_400, "day"),
+    };
+    format!("{value} {unit}{}", if value == 1 { "" } else { "s" })
+}
+
+fn refresh_mirrors() -> Result<()> {
+    if !Path::new("/usr/bin/reflector").exists() {
+        return Err(anyhow!("reflector is not installed"));
+    }
+    ensure_no_package_manager_running()?;
+
+    let mirrorlist = "/etc/pacman.d/mirrorlist";
+    let backup = format!("{mirrorlist}.aurora-backup");
+    fs::copy(mirrorlist, &backup)?;
+    let status = Command::new("reflector")
+        .args(["--latest", "20", "--protocol", "https", "--sort", "rate"])
+        .args(["--save", mirrorlist])
+        .status()?;
+    if !status.success() {
+        fs::copy(&backup, mirrorlist)?;
+        return Err(anyhow!("reflector failed; the previous mirror list was restored"));
+    }
+    println!("Refreshed {mirrorlist}; the previous list is saved as {backup}");
+    Ok(())
+}
+
+fn clean_package_cache(args: Vec<String>) -> Result<()> {
+    let keep: u32 = match args.as_slice() {
+        [keep] => keep
+            .parse()
+            .map_err(|_| anyhow!("invalid number of versions to keep: {keep}"))?,
+        _ => return Err(anyhow!("usage: clean-cache <versions-to-keep>")),
+    };
+    if !(1..=10).contains(&keep) {
+        return Err(anyhow!("versions to keep must be between 1 and 10"));
+    }
+
+    ensure_no_package_manager_running()?;
+
+    let cache_dir = Path::new("/var/cache/pacman/pkg");
+    let before = dir_size(cache_dir);
+    let status = if Path::new("/usr/bin/paccache").exists() {
+        Command::new("paccache")
+            .arg(format!("-rk{keep}"))
+            .env("LC_ALL", "C")
+            .status()?
+    } else {
+        println!("paccache not found (pacman-contrib); falling back to pacman -Sc");
+        Command::new("pacman")
+            .args(["-Sc", "--noconfirm"])
+            .env("LC_ALL", "C")
+            .status()?
+    };
+    let reclaimed = before.saturating_sub(dir_size(cache_dir));
+    println!(
+        "Reclaimed {:.1} MiB from {}",
+        reclaimed as f64 / (1024.0 * 1024.0),
+        cache_dir.display()
+    );
+
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+fn read_pacnew(args: Vec<String>) -> Result<()> {
+    let [path] = args.as_slice() else {
+        return Err(anyhow!("usage: read-pacnew /etc/<file>.pacnew"));
+    };
+
+    let pacnew = validate_pacnew_path(path)?;
+    let data = fs::read(&pacnew)?;
+    std::io::stdout().write_all(&data)?;
     Ok(())
 }
 
+fn validate_pacnew_path(path: &str) -> Result<PathBuf> {
+    if path.len() > 4096 || path.contains('\0') {
+        return Err(anyhow!("invalid path"));
+    }
+    let candidate = Path::new(path);
+    if !candidate.is_absolute()
+        || !path.ends_with(".pacnew")
+        || candidate
+            .components()
+            .any(|component| matches!(component, Component::ParentDir))
+    {
+        return Err(anyhow!("not an absolute .pacnew path: {path}"));
+    }
+
+    let canon = candidate.canonicalize()?;
+    if !canon.starts_with("/etc")
+        || !canon.to_string_lossy().ends_with(".pacnew")
+        || !canon.is_file()
+    {
+        return Err(anyhow!("path is not a .pacnew file in /etc: {path}"));
+    }
+    Ok(canon)
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.metadata().ok())
+                .filter(|meta| meta.is_file())
+                .map(|meta| meta.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
 fn ensure_no_package_manager_running() -> Result<()> {
     let mut running = Vec::new();
     let candidates = ["pacman", "yay", "paru", "pamac", "pkcon", "packagekitd"];
@@ -101,7 +259,7 @@ fn validate_pacman(args: &[String]) -> Result<()> {
     }
 
     let op = &args[0];
-    let allowed_ops = ["-S", "-Syu", "-Rns", "-U", "-D"];
+    let allowed_ops = ["-S", "-Syu", "-Rns", "-U", "-D", "-Fy"];
     if !allowed_ops.contains(&op.as_str()) {
         return Err(anyhow!("operation not allowed: {op}"));
     }
@@ -172,12 +330,12 @@ fn validate_pacman(args: &[String]) -> Result<()> {
         }
     }
 
-    if op == "-U" && pkg_files.is_empty() {
-        return Err(anyhow!("no package files supplied"));
+    if op == "-Fy" && !pkgs.is_empty() {
+        return Err(anyhow!("files database sync does not take packages"));
     }
 
-    if op == "-Syu" && !pkgs.is_empty() {
-        return Err(anyhow!("-Syu does not accept packages"));
+    if op == "-U" && pkg_files.is_empty() {
+        return Err(anyhow!("no package files supplied"));
     }
 
     if pkgs.len() > 200 {
@@ -0,0 +1,207 @@
+use std::io::BufRead;
+use std::process::Command;
+use std::sync::{mpsc, Arc, Mutex};
+
+use crate::core::cache::load_settings;
+use crate::core::context::AppContext;
+use crate::core::models::{
+    ActionKind, PackageSource, PackageSummary, SearchMode, TransactionAction, TransactionQueue,
+};
+use crate::core::runner::LogEvent;
+use crate::core::transactions::{plan_transactions, probe_sudo_wiring};
+use crate::core::updates::{collect_updates_checked, update_error_note};
+
+const USAGE: &str = "Usage:
+  aurora                     Start the graphical interface
+  aurora search <query>      Search repo, AUR and Flatpak packages
+  aurora updates             List available updates
+  aurora install <package>   Install a package, resolving its source
+  aurora --notify-updates    Send a desktop notification if updates are available";
+
+// Returns None when the arguments are not a CLI subcommand so the GUI starts instead.
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = args.first()?;
+    let rest = &args[1..];
+    let code = match command.as_str() {
+        "search" => search(rest),
+        "updates" => updates(),
+        "install" => install(rest),
+        "--notify-updates" => notify_updates(),
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            0
+        }
+        _ => return None,
+    };
+    Some(code)
+}
+
+fn context() -> AppContext {
+    AppContext::new(Arc::new(Mutex::new(load_settings())))
+}
+
+fn search(args: &[String]) -> i32 {
+    let query = args.join(" ");
+    if query.trim().is_empty() {
+        eprintln!("{USAGE}");
+        return 2;
+    }
+    let ctx = context();
+    let mode = SearchMode::NameDescription;
+    let mut results = ctx.pacman.search(&query, mode).unwrap_or_default();
+    if !ctx.settings.lock().unwrap().offline {
+        results.extend(ctx.aur.search(&query, mode).unwrap_or_default());
+    }
+    results.extend(ctx.flatpak.search(&query, mode).unwrap_or_default());
+    for pkg in &results {
+        println!("{}/{} {}", source_label(pkg.source), pkg.name, pkg.version);
+        if !pkg.summary.is_empty() {
+            println!("    {}", pkg.summary);
+        }
+    }
+    if results.is_empty() {
+        eprintln!("No packages found for \"{query}\"");
+        return 1;
+    }
+    0
+}
+
+fn updates() -> i32 {
+    let ctx = context();
+    let (items, errors) = collect_updates_checked(&ctx);
+    for item in &items {
+        let rebuild = if item.rebuild { " [rebuild]" } else { "" };
+        println!("{}/{}{}", source_label(item.action.source), item.display, rebuild);
+    }
+    for note in errors.iter().filter_map(update_error_note) {
+        eprintln!("warning: {note}");
+    }
+    if items.is_empty() {
+        println!("System is up to date");
+    }
+    0
+}
+
+// Meant to be run from a systemd user timer, so it never touches GTK.
+fn notify_updates() -> i32 {
+    let ctx = context();
+    let (items, errors) = collect_updates_checked(&ctx);
+    for note in errors.iter().filter_map(update_error_note) {
+        eprintln!("warning: {note}");
+    }
+    if items.is_empty() {
+        return 0;
+    }
+    let rebuilds = items.iter().filter(|item| item.rebuild).count();
+    let title = match items.len() {
+        1 => String::from("1 update available"),
+        count => format!("{count} updates available"),
+    };
+    let mut body = String::from("Open Aurora to review and apply them.");
+    if rebuilds > 0 {
+        body = format!("{rebuilds} of them are AUR rebuilds. {body}");
+    }
+    let status = Command::new("notify-send")
+        .args([
+            "--app-name=Aurora",
+            "--icon=io.github.ahmoodio.aurora",
+            "--category=system",
+            &title,
+            &body,
+        ])
+        .status();
+    match status {
+        Ok(status) if status.success() => 0,
+        Ok(status) => {
+            eprintln!("notify-send failed ({})", status.code().unwrap_or(1));
+            1
+        }
+        Err(err) => {
+            eprintln!("Failed to run notify-send: {err}");
+            1
+        }
+    }
+}
+
+fn install(args: &[String]) -> i32 {
+    let [name] = args else {
+        eprintln!("{USAGE}");
+        return 2;
+    };
+    let ctx = context();
+    let Some(pkg) = resolve_package(&ctx, name) else {
+        eprintln!("Package \"{name}\" was not found in the repos, the AUR or Flatpak remotes");
+        return 1;
+    };
+
+    let mut queue = TransactionQueue::default();
+    queue.push(TransactionAction {
+        name: pkg.name.clone(),
+        source: pkg.source,
+        kind: ActionKind::Install,
+        origin: pkg.origin.clone(),
+        delete_data: false,
+        unused_runtimes: false,
+    });
+    let settings = ctx.settings.lock().unwrap().clone();
+    probe_sudo_wiring(settings.aur_helper);
+    let plan = plan_transactions(&queue, &settings);
+
+    // One reader for the whole run; lines go to whichever command is running at the time.
+    let input: Arc<Mutex<Option<mpsc::Sender<String>>>> = Arc::default();
+    let forward = input.clone();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+            if let Some(input_tx) = &*forward.lock().unwrap() {
+                let _ = input_tx.send(line);
+            }
+        }
+    });
+
+    for cmd in plan.commands {
+        println!("$ {}", cmd.display_line());
+        let (tx, rx) = mpsc::channel();
+        let (input_tx, input_rx) = mpsc::channel();
+        *input.lock().unwrap() = Some(input_tx);
+        if let Err(err) = ctx.runner.run_streaming(cmd, tx, Some(input_rx)) {
+            eprintln!("Failed to start command: {err}");
+            return 1;
+        }
+        let mut code = 1;
+        for event in rx {
+            match event {
+                LogEvent::Line(line) => println!("{line}"),
+                LogEvent::Finished(status) => code = status,
+            }
+        }
+        if code != 0 {
+            eprintln!("Command failed ({code})");
+            return code;
+        }
+    }
+    0
+}
+
+fn resolve_package(ctx: &AppContext, name: &str) -> Option<PackageSummary> {
+    let mode = SearchMode::Exact;
+    let aur = if ctx.settings.lock().unwrap().offline {
+        Vec::new()
+    } else {
+        ctx.aur.search(name, mode).unwrap_or_default()
+    };
+    ctx.pacman
+        .search(name, mode)
+        .unwrap_or_default()
+        .into_iter()
+        .chain(aur)
+        .chain(ctx.flatpak.search(name, mode).unwrap_or_default())
+        .next()
+}
+
+fn source_label(source: PackageSource) -> &'static str {
+    match source {
+        PackageSource::Repo => "repo",
+        PackageSource::Aur => "aur",
+        PackageSource::Flatpak => "flatpak",
+    }
+}
@@ -0,0 +1,175 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnsiColor {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl AnsiColor {
+    // Bright variants (90-97) map onto the same eight colors.
+    fn from_code(code: u32) -> Option<AnsiColor> {
+        let base = match code {
+            30..=37 => code - 30,
+            90..=97 => code - 90,
+            _ => return None,
+        };
+        Some(match base {
+            0 => AnsiColor::Black,
+            1 => AnsiColor::Red,
+            2 => AnsiColor::Green,
+            3 => AnsiColor::Yellow,
+            4 => AnsiColor::Blue,
+            5 => AnsiColor::Magenta,
+            6 => AnsiColor::Cyan,
+            _ => AnsiColor::White,
+        })
+    }
+
+    pub fn tag_name(self) -> &'static str {
+        match self {
+            AnsiColor::Black => "ansi-black",
+            AnsiColor::Red => "ansi-red",
+            AnsiColor::Green => "ansi-green",
+            AnsiColor::Yellow => "ansi-yellow",
+            AnsiColor::Blue => "ansi-blue",
+            AnsiColor::Magenta => "ansi-magenta",
+            AnsiColor::Cyan => "ansi-cyan",
+            AnsiColor::White => "ansi-white",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnsiSpan {
+    pub text: String,
+    pub color: Option<AnsiColor>,
+    pub bold: bool,
+}
+
+// Splits a line into styled spans. SGR sequences (`ESC[...m`) set the foreground color and
+// bold; every other escape sequence is dropped.
+pub fn parse_ansi(line: &str) -> Vec<AnsiSpan> {
+    let mut spans: Vec<AnsiSpan> = Vec::new();
+    let mut color = None;
+    let mut bold = false;
+    let mut text = String::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                let mut params = String::new();
+                let mut terminator = None;
+                for next in chars.by_ref() {
+                    if ('\u{40}'..='\u{7e}').contains(&next) {
+                        terminator = Some(next);
+                        break;
+                    }
+                    params.push(next);
+                }
+                if terminator != Some('m') {
+                    continue;
+                }
+                if !text.is_empty() {
+                    spans.push(AnsiSpan {
+                        text: std::mem::take(&mut text),
+                        color,
+                        bold,
+                    });
+                }
+                for param in params.split(';') {
+                    let code = if param.is_empty() { Some(0) } else { param.parse().ok() };
+                    match code {
+                        Some(0) => {
+                            color = None;
+                            bold = false;
+                        }
+                        Some(1) => bold = true,
+                        Some(22) => bold = false,
+                        Some(39) => color = None,
+                        Some(code) => {
+                            if let Some(next) = AnsiColor::from_code(code) {
+                                color = Some(next);
+                            }
+                        }
+                        None => {}
+                    }
+                }
+            }
+            // OSC sequences (window titles, hyperlinks) end with BEL or ESC \.
+            Some(']') => {
+                while let Some(next) = chars.next() {
+                    if next == '\u{7}' {
+                        break;
+                    }
+                    if next == '\u{1b}' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if !text.is_empty() {
+        spans.push(AnsiSpan { text, color, bold });
+    }
+    spans
+}
+
+pub fn strip_ansi(line: &str) -> String {
+    if !line.contains('\u{1b}') {
+        return line.to_string();
+    }
+    parse_ansi(line).into_iter().map(|span| span.text).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_color_cursor_and_title_sequences() {
+        assert_eq!(
+            strip_ansi("\u{1b}[1;32m==>\u{1b}[0m \u{1b}[1mMaking package\u{1b}[m"),
+            "==> Making package"
+        );
+        assert_eq!(strip_ansi("\u{1b}[2K\u{1b}[1Gdownloading..."), "downloading...");
+        assert_eq!(strip_ansi("\u{1b}]0;yay\u{7}done"), "done");
+        assert_eq!(strip_ansi("plain [0;32m text"), "plain [0;32m text");
+    }
+
+    #[test]
+    fn maps_foreground_colors_to_spans() {
+        let spans = parse_ansi("\u{1b}[31merror:\u{1b}[0m failed \u{1b}[1;93mwarn");
+        assert_eq!(
+            spans,
+            [
+                AnsiSpan {
+                    text: String::from("error:"),
+                    color: Some(AnsiColor::Red),
+                    bold: false,
+                },
+                AnsiSpan {
+                    text: String::from(" failed "),
+                    color: None,
+                    bold: false,
+                },
+                AnsiSpan {
+                    text: String::from("warn"),
+                    color: Some(AnsiColor::Yellow),
+                    bold: true,
+                },
+            ]
+        );
+    }
+}
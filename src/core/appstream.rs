@@ -1,24 +1,64 @@
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
 use serde_json::Value;
 use url::Url;
 
 use crate::core::cache::{ensure_cache_dirs, screenshots_dir};
+use crate::core::models::{PackageSource, PackageSummary};
+
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(20);
 
 #[derive(Debug, Clone)]
 pub struct AppStreamComponent {
     pub id: String,
+    pub name: Option<String>,
+    pub pkgname: Option<String>,
     pub summary: Option<String>,
+    pub categories: Vec<String>,
     pub icon_name: Option<String>,
     pub screenshots: Vec<String>,
 }
 
+impl AppStreamComponent {
+    pub fn to_package_summary(&self) -> PackageSummary {
+        let (name, source) = match &self.pkgname {
+            Some(pkgname) => (pkgname.clone(), PackageSource::Repo),
+            None => (
+                self.id.trim_end_matches(".desktop").to_string(),
+                PackageSource::Flatpak,
+            ),
+        };
+        PackageSummary {
+            name,
+            summary: self
+                .summary
+                .clone()
+                .or_else(|| self.name.clone())
+                .unwrap_or_default(),
+            version: String::from("-"),
+            source,
+            installed: false,
+            origin: None,
+            popularity: None,
+            group_size: None,
+            install_date: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
-pub struct AppStreamClient;
+pub struct AppStreamClient {
+    category_cache: Arc<Mutex<HashMap<String, Vec<AppStreamComponent>>>>,
+}
 
 impl AppStreamClient {
     pub fn search_component(&self, name: &str) -> Option<AppStreamComponent> {
@@ -51,11 +91,87 @@ impl AppStreamClient {
         None
     }
 
+    // AppStream only describes applications, so any component naming a package marks that
+    // package as one.
+    pub fn app_package_names(&self, query: &str) -> HashSet<String> {
+        let output = match Command::new("appstreamcli")
+            .args(["search", query, "--format=json"])
+            .output()
+        {
+            Ok(output) if output.status.success() => output,
+            _ => return HashSet::new(),
+        };
+        serde_json::from_str::<Value>(&String::from_utf8_lossy(&output.stdout))
+            .map(|v| Self::components_from_json(&v))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|comp| comp.pkgname)
+            .collect()
+    }
+
+    pub fn components_in_category(&self, category: &str) -> Vec<AppStreamComponent> {
+        if let Some(cached) = self.category_cache.lock().unwrap().get(category) {
+            return cached.clone();
+        }
+
+        let output = match Command::new("appstreamcli")
+            .args(["search", category, "--format=json"])
+            .output()
+        {
+            Ok(output) if output.status.success() => output,
+            _ => return Vec::new(),
+        };
+        let text = String::from_utf8_lossy(&output.stdout).to_string();
+        let components: Vec<AppStreamComponent> = serde_json::from_str::<Value>(&text)
+            .map(|v| Self::components_from_json(&v))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|comp| {
+                comp.categories
+                    .iter()
+                    .any(|candidate| candidate.eq_ignore_ascii_case(category))
+            })
+            .collect();
+
+        if !components.is_empty() {
+            self.category_cache
+                .lock()
+                .unwrap()
+                .insert(category.to_string(), components.clone());
+        }
+        components
+    }
+
     fn component_from_json(v: &Value) -> Option<AppStreamComponent> {
         let comps = v.get("components").and_then(|c| c.as_array())?;
-        let comp = comps.first()?;
+        Self::parse_component(comps.first()?)
+    }
+
+    fn components_from_json(v: &Value) -> Vec<AppStreamComponent> {
+        v.get("components")
+            .and_then(|c| c.as_array())
+            .map(|comps| comps.iter().filter_map(Self::parse_component).collect())
+            .unwrap_or_default()
+    }
+
+    fn parse_component(comp: &Value) -> Option<AppStreamComponent> {
         let id = comp.get("id").and_then(|v| v.as_str())?.to_string();
+        let name = comp.get("name").and_then(|v| v.as_str()).map(|s| s.to_string());
+        let pkgname = comp
+            .get("pkgname")
+            .or_else(|| comp.get("package"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
         let summary = comp.get("summary").and_then(|v| v.as_str()).map(|s| s.to_string());
+        let categories = comp
+            .get("categories")
+            .and_then(|v| v.as_array())
+            .map(|cats| {
+                cats.iter()
+                    .filter_map(|cat| cat.as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
 
         let mut icon_name = None;
         if let Some(icons) = comp.get("icons").and_then(|v| v.as_array()) {
@@ -84,7 +200,10 @@ impl AppStreamClient {
 
         Some(AppStreamComponent {
             id,
+            name,
+            pkgname,
             summary,
+            categories,
             icon_name,
             screenshots,
         })
@@ -96,7 +215,10 @@ impl AppStreamClient {
             if line.contains('.') && (line.contains(".desktop") || line.contains(".metainfo")) {
                 return Some(AppStreamComponent {
                     id: line.to_string(),
+                    name: None,
+                    pkgname: None,
                     summary: None,
+                    categories: Vec::new(),
                     icon_name: None,
                     screenshots: Vec::new(),
                 });
@@ -137,10 +259,81 @@ impl AppStreamClient {
         if path.exists() {
             return Ok(());
         }
-        let response = ureq::get(url).call()?;
-        let mut reader = response.into_reader();
-        let mut file = fs::File::create(path)?;
-        let _ = std::io::copy(&mut reader, &mut file)?;
-        Ok(())
+        let agent = ureq::AgentBuilder::new()
+            .timeout(DOWNLOAD_TIMEOUT)
+            .build();
+        let mut last_err = anyhow!("download failed");
+        for attempt in 0..DOWNLOAD_ATTEMPTS {
+            if attempt > 0 {
+                thread::sleep(Duration::from_millis(500 * 2u64.pow(attempt - 1)));
+            }
+            match agent.get(url).call() {
+                Ok(response) => match write_atomically(&path, &mut response.into_reader()) {
+                    Ok(()) => return Ok(()),
+                    Err(err) => last_err = err,
+                },
+                // Client errors will not go away by asking again.
+                Err(ureq::Error::Status(code, _)) if (400..500).contains(&code) => {
+                    return Err(anyhow!("server returned {code}"));
+                }
+                Err(err) => last_err = err.into(),
+            }
+        }
+        Err(last_err)
+    }
+}
+
+// Downloads land in a sibling ".part" file first so an interrupted transfer never
+// leaves a truncated image where ensure_cached would pick it up.
+fn write_atomically(path: &Path, reader: &mut impl Read) -> Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+    let result = fs::File::create(&partial)
+        .and_then(|mut file| std::io::copy(reader, &mut file).map(|_| ()))
+        .and_then(|_| fs::rename(&partial, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    Ok(result?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset"))
+        }
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aurora-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn completed_download_is_renamed_into_place() {
+        let dir = scratch_dir("download-ok");
+        let path = dir.join("shot.png");
+        write_atomically(&path, &mut &b"image data"[..]).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"image data");
+        assert!(!dir.join("shot.png.part").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failed_download_leaves_no_file_behind() {
+        let dir = scratch_dir("download-fail");
+        let path = dir.join("shot.png");
+        assert!(write_atomically(&path, &mut FailingReader).is_err());
+        assert!(!path.exists());
+        assert!(!dir.join("shot.png.part").exists());
+        let _ = fs::remove_dir_all(&dir);
     }
 }
@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+
+use crate::core::models::{AurMetadata, PackageDetails, PackageSource};
+
+const AUR_INFO_URL: &str = "https://aur.archlinux.org/rpc/v5/info";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AurInfo {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(rename = "URL", default)]
+    pub url: Option<String>,
+    // Null for orphaned packages.
+    #[serde(default)]
+    pub maintainer: Option<String>,
+    #[serde(default)]
+    pub num_votes: u32,
+    #[serde(default)]
+    pub popularity: f64,
+    // Unix time the package was flagged, null when it is not flagged.
+    #[serde(default)]
+    pub out_of_date: Option<i64>,
+    #[serde(default)]
+    pub last_modified: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    results: Vec<AurInfo>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl AurInfo {
+    pub fn metadata(&self) -> AurMetadata {
+        AurMetadata {
+            votes: Some(self.num_votes),
+            popularity: Some(self.popularity),
+            maintainer: self.maintainer.clone(),
+            out_of_date: self.out_of_date.map(format_date),
+            last_modified: self.last_modified.map(format_date),
+        }
+    }
+
+    pub fn into_details(self) -> PackageDetails {
+        let aur = self.metadata();
+        let description = self.description.unwrap_or_default();
+        PackageDetails {
+            name: self.name,
+            summary: description.clone(),
+            description,
+            version: self.version,
+            source: PackageSource::Aur,
+            installed: false,
+            size: None,
+            home: self.url.filter(|url| !url.is_empty()),
+            screenshots: Vec::new(),
+            icon_name: None,
+            aur,
+            permissions: Vec::new(),
+        }
+    }
+}
+
+// Ok(None) when the AUR has no package by that name.
+pub fn fetch_info(name: &str) -> Result<Option<AurInfo>> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(10))
+        .build();
+    let body = agent
+        .get(AUR_INFO_URL)
+        .query("arg[]", name)
+        .call()?
+        .into_string()?;
+    Ok(parse_info(&body)?
+        .into_iter()
+        .find(|info| info.name == name))
+}
+
+pub fn parse_info(body: &str) -> Result<Vec<AurInfo>> {
+    let response: Response = serde_json::from_str(body)?;
+    if let Some(error) = response.error {
+        bail!("AUR RPC error: {error}");
+    }
+    Ok(response.results)
+}
+
+// YYYY-MM-DD in UTC; the day is all the AUR web interface shows as well.
+pub fn format_date(unix: i64) -> String {
+    let days = unix.div_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_info_results_into_details() {
+        let body = r#"{"resultcount":1,"results":[{"ID":1,"Name":"yay-bin","PackageBase":"yay-bin",
+            "Version":"12.3.5-1","Description":"Yet another yogurt","URL":"https://github.com/Jguer/yay",
+            "NumVotes":412,"Popularity":8.25,"OutOfDate":1700000000,"Maintainer":null,
+            "FirstSubmitted":1500000000,"LastModified":1717243200}],"type":"multiinfo","version":5}"#;
+        let info = parse_info(body).unwrap().remove(0);
+        assert_eq!(info.num_votes, 412);
+        assert!(info.maintainer.is_none());
+
+        let details = info.into_details();
+        assert_eq!(details.name, "yay-bin");
+        assert_eq!(details.version, "12.3.5-1");
+        assert_eq!(details.home.as_deref(), Some("https://github.com/Jguer/yay"));
+        assert_eq!(details.aur.votes, Some(412));
+        assert_eq!(details.aur.out_of_date.as_deref(), Some("2023-11-14"));
+        assert_eq!(details.aur.last_modified.as_deref(), Some("2024-06-01"));
+    }
+
+    #[test]
+    fn rpc_errors_are_reported() {
+        let body = r#"{"error":"Incorrect request type specified.","resultcount":0,"results":[],"type":"error","version":5}"#;
+        assert!(parse_info(body).is_err());
+        assert_eq!(format_date(0), "1970-01-01");
+    }
+}
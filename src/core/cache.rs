@@ -1,10 +1,19 @@
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{BufWriter, Write};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, OnceLock};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use directories::ProjectDirs;
 
-use crate::core::models::Settings;
+use crate::core::models::{
+    CuratedApp, CuratedApps, CustomPalette, PackageSource, Settings, TransactionAction,
+};
+
+const LOG_FILE_MAX_BYTES: u64 = 5 * 1024 * 1024;
+const LOG_FILE_ROTATIONS: usize = 3;
 
 pub fn project_dirs() -> ProjectDirs {
     ProjectDirs::from("io", "github.ahmoodio", "Aurora").expect("Project dirs")
@@ -18,20 +27,76 @@ pub fn screenshots_dir() -> PathBuf {
     cache_dir().join("screenshots")
 }
 
+pub fn logs_dir() -> PathBuf {
+    cache_dir().join("logs")
+}
+
+pub fn log_file_path() -> PathBuf {
+    logs_dir().join("aurora.log")
+}
+
 pub fn config_dir() -> PathBuf {
     project_dirs().config_dir().to_path_buf()
 }
 
+// `AURORA_HELPER` lets packagers and testers point at a helper outside the usual locations.
+pub fn helper_path() -> String {
+    let exe = std::env::current_exe().ok();
+    resolve_helper_path(
+        std::env::var_os("AURORA_HELPER"),
+        exe.as_deref().and_then(Path::parent),
+    )
+}
+
+fn resolve_helper_path(env_override: Option<OsString>, exe_dir: Option<&Path>) -> String {
+    if let Some(path) = env_override.filter(|path| !path.is_empty()) {
+        return path.to_string_lossy().to_string();
+    }
+    if let Some(dir) = exe_dir {
+        let candidate = dir.join("aurora-helper");
+        if candidate.exists() {
+            return candidate.to_string_lossy().to_string();
+        }
+    }
+    "/usr/bin/aurora-helper".to_string()
+}
+
 pub fn load_settings() -> Settings {
     let path = config_dir().join("settings.json");
     if let Ok(data) = fs::read_to_string(path) {
-        if let Ok(settings) = serde_json::from_str(&data) {
+        if let Ok((settings, _)) = settings_from_json(&data) {
             return settings;
         }
     }
     Settings::default()
 }
 
+// Applies each known field on top of the defaults and keeps the default for any field
+// that does not deserialize. Returns the names of the fields that were ignored.
+pub fn settings_from_json(data: &str) -> Result<(Settings, Vec<String>)> {
+    let input: serde_json::Value = serde_json::from_str(data)?;
+    let fields = input
+        .as_object()
+        .ok_or_else(|| anyhow!("settings file must contain a JSON object"))?;
+
+    let mut merged = serde_json::to_value(Settings::default())?;
+    let mut ignored = Vec::new();
+    for (key, value) in fields {
+        if merged.get(key).is_none() {
+            ignored.push(key.clone());
+            continue;
+        }
+        let mut candidate = merged.clone();
+        candidate[key] = value.clone();
+        if serde_json::from_value::<Settings>(candidate.clone()).is_ok() {
+            merged = candidate;
+        } else {
+            ignored.push(key.clone());
+        }
+    }
+    Ok((serde_json::from_value(merged)?, ignored))
+}
+
 pub fn save_settings(settings: &Settings) -> Result<()> {
     let dir = config_dir();
     fs::create_dir_all(&dir)?;
@@ -41,6 +106,137 @@ pub fn save_settings(settings: &Settings) -> Result<()> {
     Ok(())
 }
 
+pub fn load_last_transaction() -> Vec<TransactionAction> {
+    let path = cache_dir().join("last_transaction.json");
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_last_transaction(actions: &[TransactionAction]) -> Result<()> {
+    fs::create_dir_all(cache_dir())?;
+    let data = serde_json::to_string_pretty(actions)?;
+    fs::write(cache_dir().join("last_transaction.json"), data)?;
+    Ok(())
+}
+
+pub fn load_favorites() -> Vec<CuratedApp> {
+    let path = config_dir().join("favorites.json");
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_favorites(favorites: &[CuratedApp]) -> Result<()> {
+    let dir = config_dir();
+    fs::create_dir_all(&dir)?;
+    let data = serde_json::to_string_pretty(favorites)?;
+    fs::write(dir.join("favorites.json"), data)?;
+    Ok(())
+}
+
+pub type FavoriteKeys = HashSet<(String, PackageSource)>;
+
+// Read once per render and handed to every card, rather than re-read per card.
+pub fn favorite_keys() -> FavoriteKeys {
+    load_favorites()
+        .into_iter()
+        .map(|fav| (fav.name, fav.source))
+        .collect()
+}
+
+pub fn set_favorite(name: &str, source: PackageSource, favorite: bool) -> Result<()> {
+    let mut favorites = load_favorites();
+    favorites.retain(|fav| !(fav.name == name && fav.source == source));
+    if favorite {
+        favorites.push(CuratedApp {
+            name: name.to_string(),
+            source,
+        });
+    }
+    save_favorites(&favorites)
+}
+
+pub fn custom_theme_path() -> PathBuf {
+    config_dir().join("theme.json")
+}
+
+pub fn load_custom_palette() -> Result<CustomPalette> {
+    let data = fs::read_to_string(custom_theme_path())?;
+    Ok(serde_json::from_str(&data)?)
+}
+
+pub fn save_custom_palette(palette: &CustomPalette) -> Result<()> {
+    fs::create_dir_all(config_dir())?;
+    let data = serde_json::to_string_pretty(palette)?;
+    fs::write(custom_theme_path(), data)?;
+    Ok(())
+}
+
+// Lines go to a writer thread that keeps the file open, so a busy transaction does not open
+// and stat the log for every line on the main loop.
+pub fn append_log_file(line: &str) -> Result<()> {
+    static WRITER: OnceLock<mpsc::Sender<String>> = OnceLock::new();
+    let writer = WRITER.get_or_init(|| {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || write_log_lines(rx));
+        tx
+    });
+    writer
+        .send(line.to_string())
+        .map_err(|_| anyhow!("log writer stopped"))
+}
+
+// Writes whatever has queued up, then flushes once the queue is drained.
+fn write_log_lines(lines: mpsc::Receiver<String>) {
+    let mut file: Option<(BufWriter<fs::File>, u64)> = None;
+    while let Ok(first) = lines.recv() {
+        for line in std::iter::once(first).chain(lines.try_iter()) {
+            if file.is_none() {
+                file = open_log_file().ok();
+            }
+            if file.as_ref().is_some_and(|(_, size)| *size >= LOG_FILE_MAX_BYTES) {
+                if let Some((mut writer, _)) = file.take() {
+                    let _ = writer.flush();
+                }
+                let _ = rotate_log_files();
+                file = open_log_file().ok();
+            }
+            if let Some((writer, size)) = &mut file {
+                if writeln!(writer, "{line}").is_ok() {
+                    *size += line.len() as u64 + 1;
+                }
+            }
+        }
+        if let Some((writer, _)) = &mut file {
+            let _ = writer.flush();
+        }
+    }
+}
+
+fn open_log_file() -> Result<(BufWriter<fs::File>, u64)> {
+    fs::create_dir_all(logs_dir())?;
+    let file = fs::OpenOptions::new().create(true).append(true).open(log_file_path())?;
+    let size = file.metadata()?.len();
+    Ok((BufWriter::new(file), size))
+}
+
+fn rotate_log_files() -> Result<()> {
+    let path = log_file_path();
+    let rotated = |index: usize| logs_dir().join(format!("aurora.log.{index}"));
+    let _ = fs::remove_file(rotated(LOG_FILE_ROTATIONS));
+    for index in (1..LOG_FILE_ROTATIONS).rev() {
+        let from = rotated(index);
+        if from.exists() {
+            fs::rename(&from, rotated(index + 1))?;
+        }
+    }
+    fs::rename(&path, rotated(1))?;
+    Ok(())
+}
+
 pub fn ensure_cache_dirs() -> Result<()> {
     fs::create_dir_all(screenshots_dir())?;
     Ok(())
@@ -55,6 +251,22 @@ pub fn clear_screenshots_cache() -> Result<()> {
     Ok(())
 }
 
+pub fn load_curated_apps() -> CuratedApps {
+    let candidates = [
+        PathBuf::from("assets/featured.json"),
+        PathBuf::from("/usr/share/aurora/assets/featured.json"),
+    ];
+
+    for path in candidates {
+        if let Ok(data) = fs::read_to_string(&path) {
+            if let Ok(apps) = serde_json::from_str(&data) {
+                return apps;
+            }
+        }
+    }
+    serde_json::from_str(include_str!("../../assets/featured.json")).unwrap_or_default()
+}
+
 pub fn find_logo_path() -> Option<PathBuf> {
     let candidates = [
         PathBuf::from("assets/logo.svg"),
@@ -73,3 +285,39 @@ pub fn find_logo_path() -> Option<PathBuf> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::{AurHelperKind, ThemeMode};
+
+    #[test]
+    fn invalid_fields_fall_back_to_defaults() {
+        let data = r#"{"aur_helper": "Paru", "theme": "NotATheme", "log_limit": "many", "bogus": 1}"#;
+        let (settings, mut ignored) = settings_from_json(data).unwrap();
+        ignored.sort();
+        assert_eq!(settings.aur_helper, AurHelperKind::Paru);
+        assert_eq!(settings.theme, ThemeMode::System);
+        assert_eq!(settings.log_limit, Settings::default().log_limit);
+        assert_eq!(ignored, ["bogus", "log_limit", "theme"]);
+    }
+
+    #[test]
+    fn non_object_settings_are_rejected() {
+        assert!(settings_from_json("[1, 2, 3]").is_err());
+    }
+
+    #[test]
+    fn helper_env_override_takes_precedence() {
+        let exe_dir = std::env::temp_dir();
+        let path = resolve_helper_path(Some(OsString::from("/opt/aurora/helper")), Some(&exe_dir));
+        assert_eq!(path, "/opt/aurora/helper");
+    }
+
+    #[test]
+    fn helper_falls_back_to_system_path() {
+        let exe_dir = std::env::temp_dir().join("aurora-helper-test-missing");
+        assert_eq!(resolve_helper_path(None, Some(&exe_dir)), "/usr/bin/aurora-helper");
+        assert_eq!(resolve_helper_path(None, None), "/usr/bin/aurora-helper");
+    }
+}
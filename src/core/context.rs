@@ -0,0 +1,37 @@
+use std::sync::{Arc, Mutex};
+
+use crate::core::appstream::AppStreamClient;
+use crate::core::models::{Settings, TransactionQueue};
+use crate::core::providers::aur::Aur;
+use crate::core::providers::flatpak::Flatpak;
+use crate::core::providers::pacman::Pacman;
+use crate::core::providers::{AurProvider, FlatpakProvider, PacmanProvider};
+use crate::core::runner::CommandRunner;
+
+// Shared by the GUI and the command-line subcommands, so it holds no widgets.
+#[derive(Clone)]
+pub struct AppContext {
+    pub pacman: Arc<dyn PacmanProvider>,
+    pub aur: Arc<dyn AurProvider>,
+    pub flatpak: Arc<dyn FlatpakProvider>,
+    pub appstream: Arc<AppStreamClient>,
+    pub settings: Arc<Mutex<Settings>>,
+    pub queue: Arc<Mutex<TransactionQueue>>,
+    pub runner: Arc<CommandRunner>,
+    pub transaction_in_progress: Arc<Mutex<bool>>,
+}
+
+impl AppContext {
+    pub fn new(settings: Arc<Mutex<Settings>>) -> Self {
+        Self {
+            pacman: Arc::new(Pacman::new(settings.clone())),
+            aur: Arc::new(Aur::new(settings.clone())),
+            flatpak: Arc::new(Flatpak::new(settings.clone())),
+            appstream: Arc::new(AppStreamClient::default()),
+            settings,
+            queue: Arc::new(Mutex::new(TransactionQueue::default())),
+            runner: Arc::new(CommandRunner),
+            transaction_in_progress: Arc::new(Mutex::new(false)),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+impl DiffLine {
+    pub fn prefixed(&self) -> String {
+        match self {
+            DiffLine::Same(line) => format!("  {line}"),
+            DiffLine::Removed(line) => format!("- {line}"),
+            DiffLine::Added(line) => format!("+ {line}"),
+        }
+    }
+}
+
+// Inputs larger than this fall back to "everything removed, everything added" instead of
+// building a quadratic LCS table.
+const MAX_TABLE_CELLS: usize = 4_000_000;
+
+pub fn line_diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut result: Vec<DiffLine> = old[..prefix]
+        .iter()
+        .map(|line| DiffLine::Same(line.to_string()))
+        .collect();
+
+    if (old_mid.len() + 1) * (new_mid.len() + 1) > MAX_TABLE_CELLS {
+        result.extend(old_mid.iter().map(|line| DiffLine::Removed(line.to_string())));
+        result.extend(new_mid.iter().map(|line| DiffLine::Added(line.to_string())));
+    } else {
+        result.extend(lcs_diff(old_mid, new_mid));
+    }
+
+    result.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| DiffLine::Same(line.to_string())),
+    );
+    result
+}
+
+fn lcs_diff(old: &[&str], new: &[&str]) -> Vec<DiffLine> {
+    let width = new.len() + 1;
+    let mut table = vec![0usize; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            table[i * width + j] = if old[i] == new[j] {
+                table[(i + 1) * width + j + 1] + 1
+            } else {
+                table[(i + 1) * width + j].max(table[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            result.push(DiffLine::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if table[(i + 1) * width + j] >= table[i * width + j + 1] {
+            result.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            result.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    result.extend(old[i..].iter().map(|line| DiffLine::Removed(line.to_string())));
+    result.extend(new[j..].iter().map(|line| DiffLine::Added(line.to_string())));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_line_shows_as_removed_then_added() {
+        let diff = line_diff("a\nb\nc\n", "a\nB\nc\nd\n");
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Same("a".into()),
+                DiffLine::Removed("b".into()),
+                DiffLine::Added("B".into()),
+                DiffLine::Same("c".into()),
+                DiffLine::Added("d".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_files_have_no_changes() {
+        let diff = line_diff("x\ny\n", "x\ny\n");
+        assert!(diff.iter().all(|line| matches!(line, DiffLine::Same(_))));
+    }
+}
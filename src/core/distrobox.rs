@@ -0,0 +1,71 @@
+use anyhow::Result;
+
+use crate::core::models::Settings;
+use crate::core::runner::{command_exists, CommandRunner, CommandSpec};
+use crate::core::transactions::noconfirm_args;
+
+pub fn is_available() -> bool {
+    command_exists("distrobox")
+}
+
+pub fn list_containers(runner: &CommandRunner) -> Result<Vec<String>> {
+    let output = runner.run_capture(&CommandSpec::new(
+        "distrobox",
+        vec!["list".to_string(), "--no-color".to_string()],
+    ))?;
+    Ok(parse_containers(&output))
+}
+
+// `distrobox list` prints a table: "ID | NAME | STATUS | IMAGE".
+fn parse_containers(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("ID"))
+        .skip(1)
+        .filter_map(|line| line.split('|').nth(1))
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+// The container has its own AUR helper and sudo, so none of the host's pkexec/helper
+// plumbing applies inside it.
+pub fn install_command(container: &str, package: &str, settings: &Settings) -> CommandSpec {
+    let mut args = vec![
+        "enter".to_string(),
+        container.to_string(),
+        "--".to_string(),
+        settings.aur_helper.as_str().to_string(),
+        "-S".to_string(),
+    ];
+    args.extend(noconfirm_args(settings));
+    args.push(package.to_string());
+    CommandSpec::new("distrobox", args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_container_names_from_table() {
+        let output = "\
+ID           | NAME                 | STATUS             | IMAGE
+4f1c9a7e2b3d | arch-test            | Up 2 hours         | quay.io/toolbx/arch-toolbox:latest
+9d8e7f6a5b4c | my box               | Exited (0) 1 day   | docker.io/library/archlinux:latest
+";
+        assert_eq!(parse_containers(output), ["arch-test", "my box"]);
+        assert!(parse_containers("").is_empty());
+    }
+
+    #[test]
+    fn install_runs_the_aur_helper_inside_the_container() {
+        let settings = Settings::default();
+        let cmd = install_command("arch-test", "yay-bin", &settings);
+        assert_eq!(cmd.program, "distrobox");
+        assert_eq!(
+            cmd.args,
+            ["enter", "arch-test", "--", "yay", "-S", "--noconfirm", "yay-bin"]
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt;
+use std::io;
+use std::process::Output;
+
+pub type Result<T> = std::result::Result<T, ProviderError>;
+
+#[derive(Debug)]
+pub enum ProviderError {
+    // The program could not be found on PATH. Empty when the name is not known.
+    NotInstalled(String),
+    CommandFailed {
+        program: String,
+        code: Option<i32>,
+        stderr: String,
+    },
+    ParseError(String),
+    // `pacman -F` needs the files database, which is only downloaded by `pacman -Fy`.
+    FilesDatabaseMissing,
+    TimedOut {
+        program: String,
+        secs: u64,
+    },
+    Io(io::Error),
+}
+
+impl ProviderError {
+    pub fn spawn(program: &str, err: io::Error) -> Self {
+        match ProviderError::from(err) {
+            ProviderError::NotInstalled(_) => ProviderError::NotInstalled(program.to_string()),
+            other => other,
+        }
+    }
+
+    pub fn command_failed(program: &str, output: &Output) -> Self {
+        ProviderError::CommandFailed {
+            program: program.to_string(),
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+    }
+
+    pub fn is_not_installed(&self) -> bool {
+        matches!(self, ProviderError::NotInstalled(_))
+    }
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderError::NotInstalled(program) if program.is_empty() => {
+                write!(f, "a required command is not installed")
+            }
+            ProviderError::NotInstalled(program) => write!(f, "{program} is not installed"),
+            ProviderError::CommandFailed {
+                program,
+                code,
+                stderr,
+            } => {
+                match code {
+                    Some(code) => write!(f, "{program} failed with exit code {code}")?,
+                    None => write!(f, "{program} was terminated by a signal")?,
+                }
+                if !stderr.is_empty() {
+                    write!(f, ": {stderr}")?;
+                }
+                Ok(())
+            }
+            ProviderError::ParseError(message) => write!(f, "could not parse output: {message}"),
+            ProviderError::FilesDatabaseMissing => write!(
+                f,
+                "the files database has not been downloaded yet; run `sudo pacman -Fy` to sync it"
+            ),
+            ProviderError::TimedOut { program, secs } => {
+                write!(f, "{program} did not finish within {secs}s and was stopped")
+            }
+            ProviderError::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProviderError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ProviderError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::NotFound {
+            ProviderError::NotInstalled(String::new())
+        } else {
+            ProviderError::Io(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_programs_are_not_installed() {
+        let err = ProviderError::spawn("yay", io::Error::from(io::ErrorKind::NotFound));
+        assert!(err.is_not_installed());
+        assert_eq!(err.to_string(), "yay is not installed");
+
+        let err = ProviderError::from(io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(matches!(err, ProviderError::Io(_)));
+    }
+
+    #[test]
+    fn command_failures_include_stderr() {
+        let err = ProviderError::CommandFailed {
+            program: String::from("pacman"),
+            code: Some(1),
+            stderr: String::from("error: target not found: nope"),
+        };
+        assert_eq!(
+            err.to_string(),
+            "pacman failed with exit code 1: error: target not found: nope"
+        );
+    }
+}
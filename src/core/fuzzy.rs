@@ -0,0 +1,58 @@
+// Subsequence match: every query character must appear in order. Consecutive runs and
+// matches at the start of a name segment score higher, and shorter names win ties.
+// Both arguments must already be lowercase.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    if let Some(position) = candidate.find(query) {
+        return Some(1000 - position as i64 - candidate.len() as i64);
+    }
+
+    let mut score = 0i64;
+    let mut query_chars = query.chars().peekable();
+    let mut previous: Option<char> = None;
+    let mut last_match: Option<usize> = None;
+    for (index, c) in candidate.chars().enumerate() {
+        let Some(&wanted) = query_chars.peek() else {
+            break;
+        };
+        if c == wanted {
+            score += 10;
+            if last_match.is_some_and(|last| last + 1 == index) {
+                score += 15;
+            }
+            if previous.is_none_or(|p| matches!(p, '-' | '_' | '.' | ' ')) {
+                score += 10;
+            }
+            if let Some(last) = last_match {
+                score -= (index - last - 1) as i64;
+            }
+            last_match = Some(index);
+            query_chars.next();
+        }
+        previous = Some(c);
+    }
+    query_chars
+        .peek()
+        .is_none()
+        .then(|| score - candidate.len() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_subsequences_and_ranks_closer_names_first() {
+        assert!(fuzzy_score("frfx", "firefox").is_some());
+        assert!(fuzzy_score("frfx", "thunderbird").is_none());
+        assert!(fuzzy_score("xf", "firefox").is_none());
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+
+        let score = |candidate| fuzzy_score("frfx", candidate).unwrap();
+        assert!(score("firefox") > score("firefox-ublock-origin"));
+        assert!(fuzzy_score("fire", "firefox") > fuzzy_score("frfx", "firefox"));
+        assert!(fuzzy_score("fox", "firefox") < fuzzy_score("fox", "fox-utils"));
+    }
+}
@@ -0,0 +1,219 @@
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Result};
+
+use crate::core::models::EscalationMethod;
+use crate::core::runner::{CommandRunner, CommandSpec};
+use crate::core::transactions::read_pacnew_command;
+
+pub const PACMAN_LOCK: &str = "/var/lib/pacman/db.lck";
+
+// None when there is no lock. A lock left behind by a crashed pacman keeps its old mtime,
+// while one from a running transaction is at most as old as that transaction.
+pub fn pacman_lock_age() -> Option<Duration> {
+    let modified = fs::metadata(PACMAN_LOCK).ok()?.modified().ok()?;
+    Some(SystemTime::now().duration_since(modified).unwrap_or_default())
+}
+
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    let (value, unit) = match secs {
+        0..=59 => (secs, "second"),
+        60..=3599 => (secs / 60, "minute"),
+        3600..=86_399 => (secs / 3600, "hour"),
+        _ => (secs / 86_400, "day"),
+    };
+    if value == 1 {
+        format!("1 {unit}")
+    } else {
+        format!("{value} {unit}s")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthCategory {
+    ConfigFiles,
+    FailedUnits,
+    ForeignPackages,
+    PendingUpdates,
+}
+
+impl HealthCategory {
+    pub fn all() -> &'static [HealthCategory] {
+        static CATEGORIES: [HealthCategory; 4] = [
+            HealthCategory::ConfigFiles,
+            HealthCategory::FailedUnits,
+            HealthCategory::ForeignPackages,
+            HealthCategory::PendingUpdates,
+        ];
+        &CATEGORIES
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HealthCategory::ConfigFiles => "Unmerged Config Files",
+            HealthCategory::FailedUnits => "Failed Services",
+            HealthCategory::ForeignPackages => "Foreign Packages",
+            HealthCategory::PendingUpdates => "Pending Updates",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            HealthCategory::ConfigFiles => {
+                ".pacnew and .pacsave files in /etc that still need to be merged"
+            }
+            HealthCategory::FailedUnits => "systemd units currently in a failed state",
+            HealthCategory::ForeignPackages => {
+                "Installed packages not found in any sync repository (AUR or manual installs)"
+            }
+            HealthCategory::PendingUpdates => "Repo packages with a newer version in the sync database",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HealthItem {
+    pub title: String,
+    pub detail: String,
+}
+
+pub fn check(runner: &CommandRunner, category: HealthCategory) -> Result<Vec<HealthItem>> {
+    match category {
+        HealthCategory::ConfigFiles => config_leftovers(runner),
+        HealthCategory::FailedUnits => failed_units(runner),
+        HealthCategory::ForeignPackages => foreign_packages(runner),
+        HealthCategory::PendingUpdates => pending_updates(runner),
+    }
+}
+
+fn config_leftovers(runner: &CommandRunner) -> Result<Vec<HealthItem>> {
+    // Prune unreadable directories so find does not exit non-zero on permission errors.
+    let spec = CommandSpec::new(
+        "find",
+        [
+            "/etc", "!", "-readable", "-prune", "-o", "(", "-name", "*.pacnew", "-o", "-name",
+            "*.pacsave", ")", "-print",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect(),
+    );
+    let output = runner.run_capture(&spec)?;
+    let mut items: Vec<HealthItem> = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|path| {
+            let original = path
+                .trim_end_matches(".pacnew")
+                .trim_end_matches(".pacsave");
+            HealthItem {
+                title: path.to_string(),
+                detail: format!("Compare with {original}"),
+            }
+        })
+        .collect();
+    items.sort_by(|a, b| a.title.cmp(&b.title));
+    Ok(items)
+}
+
+// Returns (installed config, .pacnew contents). A .pacnew we cannot read directly goes
+// through the privileged helper, which only accepts .pacnew paths under /etc. The installed
+// config is never read through it, so root-only files stay unreadable here.
+pub fn read_pacnew_pair(
+    runner: &CommandRunner,
+    pacnew: &str,
+    escalation: EscalationMethod,
+) -> Result<(String, String)> {
+    let original_path = pacnew
+        .strip_suffix(".pacnew")
+        .ok_or_else(|| anyhow!("not a .pacnew file: {pacnew}"))?;
+    let original = fs::read_to_string(original_path)
+        .map_err(|err| anyhow!("cannot read original {original_path}: {err}"))?;
+    let new = match fs::read_to_string(pacnew) {
+        Ok(text) => text,
+        Err(_) => runner.run_capture(&read_pacnew_command(pacnew, escalation))?,
+    };
+    Ok((original, new))
+}
+
+fn failed_units(runner: &CommandRunner) -> Result<Vec<HealthItem>> {
+    let spec = CommandSpec::new(
+        "systemctl",
+        vec![
+            "--failed".to_string(),
+            "--no-legend".to_string(),
+            "--plain".to_string(),
+        ],
+    );
+    let output = runner.run_capture(&spec)?;
+    Ok(parse_failed_units(&output))
+}
+
+fn parse_failed_units(output: &str) -> Vec<HealthItem> {
+    let mut items = Vec::new();
+    for line in output.lines() {
+        let mut parts = line.split_whitespace();
+        let Some(unit) = parts.next() else {
+            continue;
+        };
+        let load = parts.next().unwrap_or("");
+        let active = parts.next().unwrap_or("");
+        let sub = parts.next().unwrap_or("");
+        let description = parts.collect::<Vec<_>>().join(" ");
+        items.push(HealthItem {
+            title: unit.to_string(),
+            detail: format!("{description} ({load}, {active}, {sub})"),
+        });
+    }
+    items
+}
+
+fn foreign_packages(runner: &CommandRunner) -> Result<Vec<HealthItem>> {
+    let spec = CommandSpec::new("pacman", vec!["-Qm".to_string()]);
+    let output = runner.run_capture(&spec)?;
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.next()?;
+            let version = parts.next().unwrap_or("");
+            Some(HealthItem {
+                title: name.to_string(),
+                detail: format!("Installed version {version}"),
+            })
+        })
+        .collect())
+}
+
+fn pending_updates(runner: &CommandRunner) -> Result<Vec<HealthItem>> {
+    let spec = CommandSpec::new("pacman", vec!["-Qu".to_string()]);
+    // pacman -Qu exits with 1 when there is nothing to upgrade.
+    let output = runner.run_capture(&spec).unwrap_or_default();
+    Ok(output
+        .lines()
+        .filter(|line| !line.contains("[ignored]"))
+        .filter_map(|line| {
+            let (name, change) = line.trim().split_once(' ')?;
+            Some(HealthItem {
+                title: name.to_string(),
+                detail: change.to_string(),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_ages_read_naturally() {
+        assert_eq!(format_age(Duration::from_secs(1)), "1 second");
+        assert_eq!(format_age(Duration::from_secs(150)), "2 minutes");
+        assert_eq!(format_age(Duration::from_secs(3600)), "1 hour");
+        assert_eq!(format_age(Duration::from_secs(3 * 86_400 + 5)), "3 days");
+    }
+}
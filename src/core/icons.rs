@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+// Resolved icons by package name, misses included, so a package is only looked up once.
+fn icon_cache() -> &'static Mutex<HashMap<String, Option<String>>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Option<String>>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+pub fn cached_package_icon(name: &str) -> Option<Option<String>> {
+    icon_cache().lock().unwrap().get(name).cloned()
+}
+
+// One `pacman -Ql` covers every name that is not cached yet. pacman exits non-zero when any
+// name is not installed but still lists the others, so the status is not checked.
+pub fn resolve_package_icons(names: &[String]) -> HashMap<String, Option<String>> {
+    let missing: Vec<&String> = {
+        let cache = icon_cache().lock().unwrap();
+        names.iter().filter(|name| !cache.contains_key(*name)).collect()
+    };
+    if !missing.is_empty() {
+        let output = Command::new("pacman")
+            .arg("-Ql")
+            .arg("--")
+            .args(&missing)
+            .env("LC_ALL", "C")
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+            .unwrap_or_default();
+        let files = group_file_lists(&output);
+        let mut cache = icon_cache().lock().unwrap();
+        for name in missing {
+            let icon = files.get(name.as_str()).and_then(|files| icon_from_files(files));
+            cache.insert(name.clone(), icon);
+        }
+    }
+    let cache = icon_cache().lock().unwrap();
+    names
+        .iter()
+        .map(|name| (name.clone(), cache.get(name).cloned().flatten()))
+        .collect()
+}
+
+// `pacman -Ql` prints "<package> <path>" per line.
+fn group_file_lists(output: &str) -> HashMap<&str, Vec<&str>> {
+    let mut files: HashMap<&str, Vec<&str>> = HashMap::new();
+    for line in output.lines() {
+        if let Some((name, path)) = line.split_once(' ') {
+            files.entry(name).or_default().push(path);
+        }
+    }
+    files
+}
+
+fn icon_from_files(files: &[&str]) -> Option<String> {
+    let desktop_icon = files
+        .iter()
+        .filter(|path| path.starts_with("/usr/share/applications/") && path.ends_with(".desktop"))
+        .find_map(|path| desktop_entry_icon(Path::new(path)));
+    if desktop_icon.is_some() {
+        return desktop_icon;
+    }
+
+    files
+        .iter()
+        .filter(|path| path.ends_with(".svg") || path.ends_with(".png"))
+        .find_map(|path| {
+            if path.starts_with("/usr/share/icons/") {
+                Path::new(path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+            } else if path.starts_with("/usr/share/pixmaps/") {
+                Some(path.to_string())
+            } else {
+                None
+            }
+        })
+}
+
+fn desktop_entry_icon(path: &Path) -> Option<String> {
+    let data = fs::read_to_string(path).ok()?;
+    let mut in_entry = false;
+    for line in data.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_entry {
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("Icon=") {
+            let value = value.trim();
+            if !value.is_empty() {
+                return Some(value.to_string());
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_lists_are_grouped_by_package() {
+        let output = "firefox /usr/share/icons/hicolor/48x48/apps/firefox.png\n\
+                      mesa /usr/lib/libGL.so\n\
+                      firefox /usr/share/pixmaps/firefox.png\n";
+        let files = group_file_lists(output);
+        assert_eq!(files["mesa"], ["/usr/lib/libGL.so"]);
+        assert_eq!(icon_from_files(&files["firefox"]).as_deref(), Some("firefox"));
+        assert_eq!(icon_from_files(&files["mesa"]), None);
+    }
+}
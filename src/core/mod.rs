@@ -1,9 +1,24 @@
+pub mod ansi;
 pub mod appstream;
+pub mod aur_rpc;
 pub mod cache;
+pub mod context;
+pub mod diff;
+pub mod distrobox;
+pub mod error;
+pub mod fuzzy;
+pub mod health;
+pub mod icons;
 pub mod models;
+pub mod news;
+pub mod package_list;
 pub mod providers;
 pub mod runner;
+pub mod self_update;
+pub mod size;
 pub mod transactions;
+pub mod updates;
+pub mod version;
 
 pub use appstream::*;
 pub use cache::*;
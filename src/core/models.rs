@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -15,6 +17,14 @@ pub struct PackageSummary {
     pub source: PackageSource,
     pub installed: bool,
     pub origin: Option<String>,
+    #[serde(default)]
+    pub popularity: Option<f64>,
+    // Set to the member count when the entry is a package group such as base-devel.
+    #[serde(default)]
+    pub group_size: Option<usize>,
+    // Unix time; only looked up for the Installed page.
+    #[serde(default)]
+    pub install_date: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +39,45 @@ pub struct PackageDetails {
     pub home: Option<String>,
     pub screenshots: Vec<String>,
     pub icon_name: Option<String>,
+    pub aur: AurMetadata,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AurMetadata {
+    pub votes: Option<u32>,
+    pub popularity: Option<f64>,
+    pub maintainer: Option<String>,
+    pub out_of_date: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CuratedApp {
+    pub name: String,
+    pub source: PackageSource,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CuratedApps {
+    pub featured: Vec<CuratedApp>,
+    pub popular: Vec<CuratedApp>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlatpakRemote {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnusedRuntime {
+    pub id: String,
+    pub branch: String,
+    // Installed size in bytes, when `flatpak list` reported one.
+    pub size: Option<u64>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -36,14 +85,21 @@ pub enum ActionKind {
     Install,
     Remove,
     Upgrade,
+    Reinstall,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TransactionAction {
     pub name: String,
     pub source: PackageSource,
     pub kind: ActionKind,
     pub origin: Option<String>,
+    // Flatpak removals only: also wipe the app's data under ~/.var/app.
+    #[serde(default)]
+    pub delete_data: bool,
+    // Flatpak removals only: `flatpak uninstall --unused`. The name is just a label then.
+    #[serde(default)]
+    pub unused_runtimes: bool,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -66,10 +122,39 @@ impl TransactionQueue {
         true
     }
 
+    pub fn remove(&mut self, action: &TransactionAction) -> bool {
+        let before = self.actions.len();
+        self.actions.retain(|existing| existing != action);
+        self.actions.len() != before
+    }
+
     pub fn clear(&mut self) {
         self.actions.clear();
     }
 
+    pub fn has_repo_installs(&self) -> bool {
+        self.actions
+            .iter()
+            .any(|action| action.source == PackageSource::Repo && action.kind == ActionKind::Install)
+    }
+
+    pub fn removal_names(&self) -> Vec<String> {
+        self.actions
+            .iter()
+            .filter(|action| {
+                action.kind == ActionKind::Remove && action.source != PackageSource::Flatpak
+            })
+            .map(|action| action.name.clone())
+            .collect()
+    }
+
+    pub fn has_system_upgrade(&self) -> bool {
+        self.actions.iter().any(|action| {
+            action.kind == ActionKind::Upgrade && action.source != PackageSource::Flatpak
+        })
+    }
+
+
     pub fn len(&self) -> usize {
         self.actions.len()
     }
@@ -94,6 +179,47 @@ impl AurHelperKind {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EscalationMethod {
+    Pkexec,
+    Sudo,
+}
+
+impl EscalationMethod {
+    pub fn all() -> &'static [EscalationMethod] {
+        static METHODS: [EscalationMethod; 2] = [EscalationMethod::Pkexec, EscalationMethod::Sudo];
+        &METHODS
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EscalationMethod::Pkexec => "pkexec (polkit)",
+            EscalationMethod::Sudo => "sudo",
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EscalationMethod::Pkexec => "pkexec",
+            EscalationMethod::Sudo => "sudo",
+        }
+    }
+
+    pub fn to_index(self) -> u32 {
+        Self::all()
+            .iter()
+            .position(|candidate| *candidate == self)
+            .unwrap_or(0) as u32
+    }
+
+    pub fn from_index(index: u32) -> EscalationMethod {
+        Self::all()
+            .get(index as usize)
+            .copied()
+            .unwrap_or(EscalationMethod::Pkexec)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ThemeMode {
     System,
@@ -103,11 +229,12 @@ pub enum ThemeMode {
     Emerald,
     Sunset,
     Graphite,
+    Custom,
 }
 
 impl ThemeMode {
     pub fn all() -> &'static [ThemeMode] {
-        static THEMES: [ThemeMode; 7] = [
+        static THEMES: [ThemeMode; 8] = [
             ThemeMode::System,
             ThemeMode::Light,
             ThemeMode::Dark,
@@ -115,6 +242,7 @@ impl ThemeMode {
             ThemeMode::Emerald,
             ThemeMode::Sunset,
             ThemeMode::Graphite,
+            ThemeMode::Custom,
         ];
         &THEMES
     }
@@ -128,6 +256,7 @@ impl ThemeMode {
             ThemeMode::Emerald => "Emerald",
             ThemeMode::Sunset => "Sunset",
             ThemeMode::Graphite => "Graphite",
+            ThemeMode::Custom => "Custom",
         }
     }
 
@@ -146,6 +275,22 @@ impl ThemeMode {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomPalette {
+    pub base: ThemeMode,
+    pub colors: BTreeMap<String, String>,
+}
+
+impl Default for CustomPalette {
+    fn default() -> Self {
+        Self {
+            base: ThemeMode::Dark,
+            colors: BTreeMap::new(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TerminalMode {
     Integrated,
@@ -186,15 +331,27 @@ pub enum TerminalEmulator {
     Kitty,
     Konsole,
     Alacritty,
+    GnomeTerminal,
+    Ptyxis,
+    Xfce4Terminal,
+    Console,
+    Foot,
+    Xterm,
 }
 
 impl TerminalEmulator {
     pub fn all() -> &'static [TerminalEmulator] {
-        static EMULATORS: [TerminalEmulator; 4] = [
+        static EMULATORS: [TerminalEmulator; 10] = [
             TerminalEmulator::Auto,
             TerminalEmulator::Kitty,
             TerminalEmulator::Konsole,
             TerminalEmulator::Alacritty,
+            TerminalEmulator::GnomeTerminal,
+            TerminalEmulator::Ptyxis,
+            TerminalEmulator::Xfce4Terminal,
+            TerminalEmulator::Console,
+            TerminalEmulator::Foot,
+            TerminalEmulator::Xterm,
         ];
         &EMULATORS
     }
@@ -205,6 +362,12 @@ impl TerminalEmulator {
             TerminalEmulator::Kitty => "Kitty",
             TerminalEmulator::Konsole => "Konsole",
             TerminalEmulator::Alacritty => "Alacritty",
+            TerminalEmulator::GnomeTerminal => "GNOME Terminal",
+            TerminalEmulator::Ptyxis => "Ptyxis",
+            TerminalEmulator::Xfce4Terminal => "Xfce Terminal",
+            TerminalEmulator::Console => "GNOME Console",
+            TerminalEmulator::Foot => "foot",
+            TerminalEmulator::Xterm => "xterm",
         }
     }
 
@@ -223,24 +386,212 @@ impl TerminalEmulator {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchSort {
+    Name,
+    InstalledFirst,
+    Source,
+    Popularity,
+}
+
+impl SearchSort {
+    pub fn all() -> &'static [SearchSort] {
+        static SORTS: [SearchSort; 4] = [
+            SearchSort::Name,
+            SearchSort::InstalledFirst,
+            SearchSort::Source,
+            SearchSort::Popularity,
+        ];
+        &SORTS
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchSort::Name => "Name",
+            SearchSort::InstalledFirst => "Installed First",
+            SearchSort::Source => "Source",
+            SearchSort::Popularity => "Popularity",
+        }
+    }
+
+    pub fn to_index(self) -> u32 {
+        Self::all()
+            .iter()
+            .position(|candidate| *candidate == self)
+            .unwrap_or(0) as u32
+    }
+
+    pub fn from_index(index: u32) -> SearchSort {
+        Self::all()
+            .get(index as usize)
+            .copied()
+            .unwrap_or(SearchSort::Name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchMode {
+    Name,
+    NameDescription,
+    Exact,
+    // Looks up the repo packages that ship a file instead of matching package names.
+    File,
+}
+
+impl SearchMode {
+    pub fn all() -> &'static [SearchMode] {
+        static MODES: [SearchMode; 4] = [
+            SearchMode::Name,
+            SearchMode::NameDescription,
+            SearchMode::Exact,
+            SearchMode::File,
+        ];
+        &MODES
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchMode::Name => "Name",
+            SearchMode::NameDescription => "Name + Description",
+            SearchMode::Exact => "Exact Name",
+            SearchMode::File => "Provides File",
+        }
+    }
+
+    pub fn to_index(self) -> u32 {
+        Self::all()
+            .iter()
+            .position(|candidate| *candidate == self)
+            .unwrap_or(0) as u32
+    }
+
+    pub fn from_index(index: u32) -> SearchMode {
+        Self::all()
+            .get(index as usize)
+            .copied()
+            .unwrap_or(SearchMode::NameDescription)
+    }
+
+    pub fn matches_name(self, name: &str, query: &str) -> bool {
+        match self {
+            SearchMode::Name => {
+                let name = name.to_lowercase();
+                query
+                    .split_whitespace()
+                    .all(|term| name.contains(&term.to_lowercase()))
+            }
+            SearchMode::NameDescription | SearchMode::File => true,
+            SearchMode::Exact => name == query.trim(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Settings {
     pub aur_helper: AurHelperKind,
+    pub escalation: EscalationMethod,
     pub allow_noconfirm: bool,
+    pub skip_up_to_date: bool,
+    pub separate_aur_upgrade: bool,
+    pub enable_aur: bool,
+    pub enable_flatpak: bool,
+    pub fuzzy_search: bool,
+    pub review_dependency_tree: bool,
+    // Extra variables for AUR and PKGBUILD builds, or every unescalated queued command with
+    // `env_all_commands`. Names are checked by `transactions::validate_env_name`.
+    pub build_env: BTreeMap<String, String>,
+    pub env_all_commands: bool,
+    // Filter dropdown positions restored on launch. Source filters index the page's full
+    // list of sources, so hiding AUR or Flatpak does not shift them.
+    pub search_mode: u32,
+    pub search_source_filter: u32,
+    pub search_state_filter: u32,
+    pub search_sort: u32,
+    pub updates_source_filter: u32,
+    pub installed_source_filter: u32,
+    pub installed_reason_filter: u32,
     pub theme: ThemeMode,
     pub terminal_mode: TerminalMode,
     pub terminal_emulator: TerminalEmulator,
+    pub log_limit: usize,
+    pub log_to_file: bool,
+    pub log_colors: bool,
+    pub dry_run: bool,
+    pub offline: bool,
+    pub cache_keep_versions: u32,
+    pub update_check_minutes: u32,
+    pub prompt_timeout_secs: u32,
+    pub prompt_timeout_response: String,
+    pub removal_warning_threshold: u32,
+    pub command_timeout_secs: u32,
+    pub news_last_seen: i64,
+    pub news_acknowledged: i64,
+    pub window_width: i32,
+    pub window_height: i32,
+    pub window_maximized: bool,
+    pub queue_panel_docked: bool,
+}
+
+impl Settings {
+    // A hand-edited or stale size from a larger monitor should not open an unusable window.
+    pub fn window_size(&self) -> (i32, i32) {
+        (
+            self.window_width.clamp(640, 7680),
+            self.window_height.clamp(480, 4320),
+        )
+    }
+
+    pub fn source_enabled(&self, source: PackageSource) -> bool {
+        match source {
+            PackageSource::Repo => true,
+            PackageSource::Aur => self.enable_aur,
+            PackageSource::Flatpak => self.enable_flatpak,
+        }
+    }
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
             aur_helper: AurHelperKind::Yay,
+            escalation: EscalationMethod::Pkexec,
             allow_noconfirm: false,
+            skip_up_to_date: true,
+            separate_aur_upgrade: false,
+            enable_aur: true,
+            enable_flatpak: true,
+            fuzzy_search: false,
+            review_dependency_tree: false,
+            build_env: BTreeMap::new(),
+            env_all_commands: false,
+            search_mode: SearchMode::NameDescription.to_index(),
+            search_source_filter: 0,
+            search_state_filter: 0,
+            search_sort: SearchSort::Name.to_index(),
+            updates_source_filter: 0,
+            installed_source_filter: 0,
+            installed_reason_filter: 0,
             theme: ThemeMode::System,
             terminal_mode: TerminalMode::Integrated,
             terminal_emulator: TerminalEmulator::Auto,
+            log_limit: 1000,
+            log_to_file: false,
+            log_colors: true,
+            dry_run: false,
+            offline: false,
+            cache_keep_versions: 2,
+            update_check_minutes: 30,
+            prompt_timeout_secs: 0,
+            prompt_timeout_response: String::from("n"),
+            removal_warning_threshold: 5,
+            command_timeout_secs: 60,
+            news_last_seen: 0,
+            news_acknowledged: 0,
+            window_width: 1200,
+            window_height: 800,
+            window_maximized: false,
+            queue_panel_docked: false,
         }
     }
 }
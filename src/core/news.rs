@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use anyhow::Result;
+
+const NEWS_FEED_URL: &str = "https://archlinux.org/feeds/news/";
+const PACMAN_LOG: &str = "/var/log/pacman.log";
+
+#[derive(Debug, Clone)]
+pub struct NewsItem {
+    pub title: String,
+    pub link: String,
+    pub published: i64,
+    pub summary: String,
+}
+
+pub fn fetch_news() -> Result<Vec<NewsItem>> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(10))
+        .build();
+    let body = agent.get(NEWS_FEED_URL).call()?.into_string()?;
+    Ok(parse_feed(&body))
+}
+
+pub fn unread_news(items: Vec<NewsItem>, last_seen: i64) -> Vec<NewsItem> {
+    items
+        .into_iter()
+        .filter(|item| item.published > last_seen)
+        .collect()
+}
+
+// News that must be acknowledged before a system upgrade: posted since the last upgrade and
+// not acknowledged yet.
+pub fn blocking_news(items: &[NewsItem], acknowledged: i64, last_upgrade: i64) -> Vec<NewsItem> {
+    items
+        .iter()
+        .filter(|item| item.published > acknowledged.max(last_upgrade))
+        .cloned()
+        .collect()
+}
+
+pub fn last_system_upgrade() -> Option<i64> {
+    let log = std::fs::read_to_string(PACMAN_LOG).ok()?;
+    log.lines()
+        .rev()
+        .find(|line| line.contains("starting full system upgrade"))
+        .and_then(parse_log_time)
+}
+
+// pacman.log lines start with "[2024-05-05T12:34:56+0200]", or "[2019-01-01 12:34]" in
+// logs written by pacman before 5.2.
+fn parse_log_time(line: &str) -> Option<i64> {
+    let stamp = line.strip_prefix('[')?.split(']').next()?;
+    let (date, rest) = stamp.split_at_checked(10)?;
+    let mut date = date.split('-');
+    let year: i64 = date.next()?.parse().ok()?;
+    let month: i64 = date.next()?.parse().ok()?;
+    let day: i64 = date.next()?.parse().ok()?;
+    let rest = rest.get(1..)?;
+    let (time, offset) = match rest.find(['+', '-']) {
+        Some(index) => (&rest[..index], parse_offset(&rest[index..])?),
+        None => (rest, 0),
+    };
+    let mut time = time.split(':');
+    let hour: i64 = time.next()?.parse().ok()?;
+    let minute: i64 = time.next()?.parse().ok()?;
+    let second: i64 = time.next().unwrap_or("0").parse().ok()?;
+    Some(unix_time(year, month, day, hour * 3600 + minute * 60 + second) - offset)
+}
+
+fn parse_offset(zone: &str) -> Option<i64> {
+    if zone.len() != 5 {
+        return None;
+    }
+    let sign = if zone.starts_with('-') { -1 } else { 1 };
+    let hours: i64 = zone[1..3].parse().ok()?;
+    let minutes: i64 = zone[3..5].parse().ok()?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+fn parse_feed(xml: &str) -> Vec<NewsItem> {
+    let mut items = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find("<item>") {
+        let after = &rest[start + "<item>".len()..];
+        let Some(end) = after.find("</item>") else {
+            break;
+        };
+        let block = &after[..end];
+        rest = &after[end + "</item>".len()..];
+
+        let title = tag_text(block, "title").unwrap_or_default();
+        let link = tag_text(block, "link").unwrap_or_default();
+        let summary = tag_text(block, "description")
+            .map(|html| strip_tags(&html))
+            .unwrap_or_default();
+        let published = tag_text(block, "pubDate")
+            .and_then(|date| parse_rfc2822(&date))
+            .unwrap_or(0);
+        if title.is_empty() {
+            continue;
+        }
+        items.push(NewsItem {
+            title,
+            link,
+            published,
+            summary,
+        });
+    }
+    items
+}
+
+fn tag_text(block: &str, tag: &str) -> Option<String> {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    let start = block.find(&open)? + open.len();
+    let end = start + block[start..].find(&close)?;
+    let raw = block[start..end].trim();
+    let raw = raw
+        .strip_prefix("<![CDATA[")
+        .and_then(|text| text.strip_suffix("]]>"))
+        .unwrap_or(raw);
+    Some(
+        raw.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&#39;", "'")
+            .replace("&amp;", "&"),
+    )
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                text.push(' ');
+            }
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Parses dates like "Sun, 05 May 2024 12:34:56 +0000" into a unix timestamp.
+fn parse_rfc2822(date: &str) -> Option<i64> {
+    let date = date.split_once(',').map(|(_, rest)| rest).unwrap_or(date);
+    let mut parts = date.split_whitespace();
+    let day: i64 = parts.next()?.parse().ok()?;
+    let month = match parts.next()? {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    };
+    let year: i64 = parts.next()?.parse().ok()?;
+    let mut time = parts.next()?.split(':');
+    let hour: i64 = time.next()?.parse().ok()?;
+    let minute: i64 = time.next()?.parse().ok()?;
+    let second: i64 = time.next().unwrap_or("0").parse().ok()?;
+    let offset = match parts.next() {
+        Some(zone) if zone.starts_with(['+', '-']) => parse_offset(zone)?,
+        _ => 0,
+    };
+    Some(unix_time(year, month, day, hour * 3600 + minute * 60 + second) - offset)
+}
+
+fn unix_time(year: i64, month: i64, day: i64, seconds: i64) -> i64 {
+    // Days since the unix epoch for a proleptic Gregorian date.
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    days * 86_400 + seconds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_upgrade_times_from_both_pacman_log_formats() {
+        let line = "[2024-05-05T12:34:56+0200] [PACMAN] starting full system upgrade";
+        assert_eq!(parse_log_time(line), Some(1_714_905_296));
+        let old = "[2019-01-01 12:34] [PACMAN] starting full system upgrade";
+        assert_eq!(parse_log_time(old), Some(1_546_346_040));
+        assert_eq!(parse_rfc2822("Sun, 05 May 2024 10:34:56 +0000"), Some(1_714_905_296));
+    }
+
+    #[test]
+    fn only_unacknowledged_news_since_the_last_upgrade_blocks() {
+        let item = |published| NewsItem {
+            title: format!("news {published}"),
+            link: String::new(),
+            published,
+            summary: strip_tags("<p>Manual <code>intervention</code>\n required</p>"),
+        };
+        let items = [item(100), item(200), item(300)];
+        assert_eq!(items[0].summary, "Manual intervention required");
+        let blocking = blocking_news(&items, 150, 250);
+        assert_eq!(blocking.len(), 1);
+        assert_eq!(blocking[0].published, 300);
+        assert!(blocking_news(&items, 300, 0).is_empty());
+    }
+}
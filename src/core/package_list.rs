@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::core::models::{ActionKind, PackageSource, TransactionAction};
+
+// Exported with `jq -r '.repo[]' list.json | pacman -S --needed -` in mind, so each
+// source is a plain list of names.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackageList {
+    pub repo: Vec<String>,
+    pub aur: Vec<String>,
+    pub flatpak: Vec<String>,
+}
+
+impl PackageList {
+    pub fn count(&self) -> usize {
+        self.repo.len() + self.aur.len() + self.flatpak.len()
+    }
+
+    pub fn install_actions(&self) -> Vec<TransactionAction> {
+        let sources = [
+            (PackageSource::Repo, &self.repo),
+            (PackageSource::Aur, &self.aur),
+            (PackageSource::Flatpak, &self.flatpak),
+        ];
+        sources
+            .into_iter()
+            .flat_map(|(source, names)| {
+                names.iter().map(move |name| TransactionAction {
+                    name: name.clone(),
+                    source,
+                    kind: ActionKind::Install,
+                    origin: None,
+                    delete_data: false,
+                    unused_runtimes: false,
+                })
+            })
+            .collect()
+    }
+}
+
+// Same character set the privileged helper accepts, minus names that would read as options.
+pub fn is_valid_package_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 128
+        && !name.starts_with(['-', '.'])
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-._@".contains(c))
+}
+
+pub fn is_valid_flatpak_id(id: &str) -> bool {
+    let parts: Vec<&str> = id.split('.').collect();
+    id.len() <= 255
+        && parts.len() >= 2
+        && parts.iter().all(|part| {
+            !part.is_empty()
+                && !part.starts_with(|c: char| c.is_ascii_digit())
+                && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        })
+}
+
+pub fn package_list_to_json(list: &PackageList) -> Result<String> {
+    Ok(serde_json::to_string_pretty(list)?)
+}
+
+// Returns the list with invalid entries dropped, plus the entries that were dropped.
+pub fn package_list_from_json(data: &str) -> Result<(PackageList, Vec<String>)> {
+    let parsed: PackageList = serde_json::from_str(data).context("not an Aurora package list")?;
+    let mut rejected = Vec::new();
+    let mut keep = |names: Vec<String>, valid: fn(&str) -> bool| -> Vec<String> {
+        let mut kept = Vec::new();
+        for name in names {
+            let name = name.trim().to_string();
+            if !valid(&name) {
+                rejected.push(name);
+            } else if !kept.contains(&name) {
+                kept.push(name);
+            }
+        }
+        kept
+    };
+    let list = PackageList {
+        repo: keep(parsed.repo, is_valid_package_name),
+        aur: keep(parsed.aur, is_valid_package_name),
+        flatpak: keep(parsed.flatpak, is_valid_flatpak_id),
+    };
+    Ok((list, rejected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_drops_invalid_and_duplicate_names() {
+        let data = r#"{
+            "repo": ["firefox", "gtk4", "--overwrite=*", "firefox", "rm -rf"],
+            "aur": ["yay-bin", ".hidden"],
+            "flatpak": ["org.gnome.Maps", "not-an-id", "com.example.App; true"]
+        }"#;
+        let (list, rejected) = package_list_from_json(data).unwrap();
+        assert_eq!(list.repo, ["firefox", "gtk4"]);
+        assert_eq!(list.aur, ["yay-bin"]);
+        assert_eq!(list.flatpak, ["org.gnome.Maps"]);
+        assert_eq!(
+            rejected,
+            ["--overwrite=*", "rm -rf", ".hidden", "not-an-id", "com.example.App; true"]
+        );
+    }
+
+    #[test]
+    fn export_round_trips_and_queues_installs() {
+        let list = PackageList {
+            repo: vec![String::from("base-devel")],
+            aur: Vec::new(),
+            flatpak: vec![String::from("org.mozilla.firefox")],
+        };
+        let (parsed, rejected) =
+            package_list_from_json(&package_list_to_json(&list).unwrap()).unwrap();
+        assert_eq!(parsed, list);
+        assert!(rejected.is_empty());
+
+        let actions = parsed.install_actions();
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[1].source, PackageSource::Flatpak);
+        assert_eq!(actions[1].kind, ActionKind::Install);
+        assert!(package_list_from_json("[1, 2]").is_err());
+    }
+}
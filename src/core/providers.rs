@@ -1,25 +1,187 @@
-use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::process::{Command, Output};
+use std::sync::Mutex;
+use std::time::Duration;
 
-use crate::core::models::{PackageDetails, PackageSummary};
+use crate::core::error::{ProviderError, Result};
+use crate::core::models::{
+    FlatpakRemote, PackageDetails, PackageSummary, SearchMode, Settings, UnusedRuntime,
+};
+use crate::core::runner::{output_with_timeout, CommandSpec};
+use crate::core::updates::{FlatpakUpdate, PackageUpdate};
 
 pub trait PacmanProvider: Send + Sync {
-    fn search(&self, query: &str) -> Result<Vec<PackageSummary>>;
+    fn search(&self, query: &str, mode: SearchMode) -> Result<Vec<PackageSummary>>;
     fn info_repo(&self, name: &str) -> Result<PackageDetails>;
     fn info_installed(&self, name: &str) -> Result<PackageDetails>;
     fn list_installed(&self) -> Result<Vec<PackageSummary>>;
+    fn installed_among(&self, names: &[String]) -> Result<HashSet<String>>;
+    fn list_foreign(&self) -> Result<HashSet<String>>;
+    fn list_explicit_names(&self) -> Result<HashSet<String>>;
+    fn install_dates(&self) -> Result<HashMap<String, i64>>;
+    fn desktop_app_names(&self) -> Result<HashSet<String>>;
+    fn count_pending_updates(&self) -> Result<usize>;
+    fn list_files(&self, name: &str, installed: bool) -> Result<Vec<String>>;
+    fn owner_of_file(&self, path: &str) -> Result<Vec<String>>;
+    fn group_members(&self, name: &str) -> Result<Vec<String>>;
+    fn removal_preview(&self, names: &[String]) -> Result<Vec<String>>;
+    fn install_preview(&self, names: &[String]) -> Result<Vec<String>>;
+    fn download_sizes(&self, names: &[String]) -> Result<HashMap<String, u64>>;
+    fn installed_sizes(&self, names: &[String]) -> Result<HashMap<String, u64>>;
+    fn updates(&self) -> Result<Vec<PackageUpdate>>;
 }
 
 pub trait AurProvider: Send + Sync {
-    fn search(&self, query: &str) -> Result<Vec<PackageSummary>>;
+    fn search(&self, query: &str, mode: SearchMode) -> Result<Vec<PackageSummary>>;
     fn info(&self, name: &str) -> Result<PackageDetails>;
+    fn updates(&self) -> Result<Vec<PackageUpdate>>;
+    fn pkgbuild(&self, name: &str) -> Result<String>;
 }
 
 pub trait FlatpakProvider: Send + Sync {
-    fn search(&self, query: &str) -> Result<Vec<PackageSummary>>;
+    fn search(&self, query: &str, mode: SearchMode) -> Result<Vec<PackageSummary>>;
     fn info(&self, name: &str) -> Result<PackageDetails>;
     fn list_installed(&self) -> Result<Vec<PackageSummary>>;
+    fn installed_among(&self, names: &[String]) -> Result<HashSet<String>>;
+    fn remotes_for(&self, app_id: &str) -> Result<Vec<String>>;
+    fn list_remotes(&self) -> Result<Vec<FlatpakRemote>>;
+    fn add_remote(&self, name: &str, url: &str) -> CommandSpec;
+    fn remove_remote(&self, name: &str) -> CommandSpec;
+    fn updates(&self) -> Result<Vec<FlatpakUpdate>>;
+    fn unused_runtimes(&self) -> Result<Vec<UnusedRuntime>>;
+    fn remove_unused(&self) -> CommandSpec;
 }
 
 pub mod pacman;
 pub mod aur;
 pub mod flatpak;
+
+pub fn capture_timeout(settings: &Mutex<Settings>) -> Duration {
+    Duration::from_secs(u64::from(settings.lock().unwrap().command_timeout_secs.max(1)))
+}
+
+// Runs a capture command under the configured timeout. The exit status is left to the caller.
+fn capture(program: &str, command: &mut Command, timeout: Duration) -> Result<Output> {
+    match output_with_timeout(command.env("LC_ALL", "C"), timeout) {
+        Ok(Some(output)) => Ok(output),
+        Ok(None) => Err(ProviderError::TimedOut {
+            program: program.to_string(),
+            secs: timeout.as_secs(),
+        }),
+        Err(err) => Err(ProviderError::spawn(program, err)),
+    }
+}
+
+// `pacman -Qu` and the AUR helpers' `-Qua` exit with 1 and stay quiet when nothing is
+// outdated; any other failure, including a 1 with errors on stderr, is a real failure.
+fn update_check_failed(output: &Output) -> bool {
+    let nothing_outdated = output.status.code() == Some(1) && output.stderr.is_empty();
+    !output.status.success() && !nothing_outdated
+}
+
+// Pairs each `repo/name version ...` header of `-Ss` output with its indented description.
+// A header without a description line no longer swallows the following result.
+fn search_entries(output: &str) -> Vec<(&str, String)> {
+    let mut entries: Vec<(&str, String)> = Vec::new();
+    for line in output.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            if let Some((_, summary)) = entries.last_mut() {
+                if !summary.is_empty() {
+                    summary.push(' ');
+                }
+                summary.push_str(line.trim());
+            }
+        } else {
+            entries.push((line.trim(), String::new()));
+        }
+    }
+    entries
+}
+
+// Parses `Key : value` output such as `pacman -Si` or `flatpak info`. Only known keys start a
+// field and the value is everything after the first colon, so values may contain colons.
+// With `continuations`, indented lines extend the previous field (pacman's "Optional Deps").
+fn info_fields<'k>(output: &str, keys: &[&'k str], continuations: bool) -> Vec<(&'k str, String)> {
+    let mut fields: Vec<(&'k str, String)> = Vec::new();
+    let mut open = false;
+    for line in output.lines() {
+        if line.trim().is_empty() {
+            open = false;
+            continue;
+        }
+        let known = line
+            .split_once(':')
+            .and_then(|(key, value)| keys.iter().find(|k| **k == key.trim()).map(|k| (*k, value)));
+        if let Some((key, value)) = known {
+            fields.push((key, value.trim().to_string()));
+            open = true;
+        } else if continuations && open && line.starts_with(char::is_whitespace) {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push('\n');
+                value.push_str(line.trim());
+            }
+        } else {
+            open = false;
+        }
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    #[test]
+    fn only_a_quiet_exit_one_means_no_updates() {
+        let output = |code: i32, stderr: &str| Output {
+            status: ExitStatus::from_raw(code << 8),
+            stdout: Vec::new(),
+            stderr: stderr.as_bytes().to_vec(),
+        };
+        assert!(!update_check_failed(&output(0, "")));
+        assert!(!update_check_failed(&output(1, "")));
+        assert!(update_check_failed(&output(1, "error: failed to init transaction")));
+        assert!(update_check_failed(&output(2, "")));
+    }
+
+    #[test]
+    fn info_fields_keep_colons_and_continuations() {
+        let output = "\
+Name            : python
+Description     : The Python language: batteries included
+Optional Deps   : python-setuptools: for building
+                  sqlite [installed]
+Required By     : None
+Some New Field  : ignored
+                  also ignored
+";
+        let fields = info_fields(output, &["Name", "Description", "Optional Deps", "Required By"], true);
+        assert_eq!(
+            fields,
+            [
+                ("Name", String::from("python")),
+                ("Description", String::from("The Python language: batteries included")),
+                ("Optional Deps", String::from("python-setuptools: for building\nsqlite [installed]")),
+                ("Required By", String::from("None")),
+            ]
+        );
+    }
+
+    #[test]
+    fn search_entries_tolerate_missing_descriptions() {
+        let output = "extra/foo 1.0-1\nextra/bar 2.0-1 [installed]\n    Bar: a tool\n";
+        let entries = search_entries(output);
+        assert_eq!(
+            entries,
+            [
+                ("extra/foo 1.0-1", String::new()),
+                ("extra/bar 2.0-1 [installed]", String::from("Bar: a tool")),
+            ]
+        );
+    }
+}
@@ -1,11 +1,38 @@
 use std::ffi::OsStr;
-use std::process::Command;
+use std::fs;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
-use anyhow::{anyhow, Result};
+use crate::core::cache::cache_dir;
+use crate::core::error::{ProviderError, Result};
+use crate::core::models::{
+    AurMetadata, PackageDetails, PackageSource, PackageSummary, SearchMode, Settings,
+};
+use crate::core::providers::{
+    capture, capture_timeout, info_fields, search_entries, update_check_failed, AurProvider,
+};
+use crate::core::updates::{parse_aur_updates, PackageUpdate};
 
-use crate::core::models::{PackageDetails, PackageSource, PackageSummary, Settings};
-use crate::core::providers::AurProvider;
+const INFO_KEYS: &[&str] = &[
+    "Name",
+    "Version",
+    "Description",
+    "URL",
+    "Depends On",
+    "Make Deps",
+    "Optional Deps",
+    "Installed Size",
+    "Download Size",
+    "Maintainer",
+    "Votes",
+    "Popularity",
+    "Out-of-date",
+    "Out Of Date",
+    "Last Modified",
+];
 
 #[derive(Debug, Clone)]
 pub struct Aur {
@@ -21,35 +48,84 @@ impl Aur {
         self.settings.lock().unwrap().aur_helper.as_str().to_string()
     }
 
+    fn output<I, S>(&self, helper: &str, args: I) -> Result<Output>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        capture(
+            helper,
+            Command::new(helper).args(args),
+            capture_timeout(&self.settings),
+        )
+    }
+
     fn run_capture<I, S>(&self, args: I) -> Result<String>
     where
         I: IntoIterator<Item = S>,
         S: AsRef<OsStr>,
     {
         let helper = self.helper_bin();
-        let output = Command::new(&helper)
-            .args(args)
-            .env("LC_ALL", "C")
-            .output()?;
+        let output = self.output(&helper, args)?;
         if !output.status.success() {
-            return Err(anyhow!("{} failed with status {}", helper, output.status));
+            return Err(ProviderError::command_failed(&helper, &output));
         }
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
     }
 
+    fn read_pkgbuild(&self, helper: &str, name: &str, dir: &Path) -> Result<String> {
+        let output = capture(
+            helper,
+            Command::new(helper).args(["-G", "--", name]).current_dir(dir),
+            capture_timeout(&self.settings),
+        )?;
+        if !output.status.success() {
+            return Err(ProviderError::command_failed(helper, &output));
+        }
+        // Split packages are cloned under their pkgbase, which need not match the name.
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path().join("PKGBUILD");
+            if path.is_file() {
+                return Ok(fs::read_to_string(path)?);
+            }
+        }
+        Err(ProviderError::ParseError(format!(
+            "{helper} did not download a PKGBUILD for {name}"
+        )))
+    }
+
+    fn search_args(query: &str, mode: SearchMode) -> Option<Vec<String>> {
+        let mut args = vec!["-Ss".to_string()];
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect();
+        if terms.is_empty() || (mode == SearchMode::Exact && terms.len() > 1) {
+            return None;
+        }
+        // yay and paru both accept --searchby; name-desc is their default.
+        if mode != SearchMode::NameDescription {
+            args.push("--searchby".to_string());
+            args.push("name".to_string());
+        }
+        // Terms after "--" are never parsed as helper flags.
+        args.push("--".to_string());
+        args.extend(terms);
+        Some(args)
+    }
+
     fn parse_search_output(output: &str) -> Vec<PackageSummary> {
         let mut results = Vec::new();
-        let mut lines = output.lines();
-        while let Some(line) = lines.next() {
-            if line.trim().is_empty() {
-                continue;
-            }
-            let header = line.trim();
-            let summary = lines.next().unwrap_or("").trim().to_string();
+        for (header, summary) in search_entries(output) {
             let mut parts = header.split_whitespace();
             let repo_pkg = parts.next().unwrap_or("");
             let version = parts.next().unwrap_or("").to_string();
             let name = repo_pkg.split('/').nth(1).unwrap_or(repo_pkg).to_string();
+            // yay prints "(+votes popularity)" after the version, paru "[+votes ~popularity]".
+            let popularity = parts
+                .nth(1)
+                .map(|field| field.trim_start_matches('~').trim_end_matches([')', ']']))
+                .and_then(|value| value.parse().ok());
             results.push(PackageSummary {
                 name,
                 summary,
@@ -57,38 +133,48 @@ impl Aur {
                 source: PackageSource::Aur,
                 installed: false,
                 origin: None,
+                popularity,
+                group_size: None,
+                install_date: None,
             });
         }
         results
     }
 
-    fn parse_info(output: &str) -> PackageDetails {
+    fn parse_info(output: &str) -> Result<PackageDetails> {
         let mut name = String::new();
         let mut version = String::new();
         let mut desc = String::new();
         let mut summary = String::new();
         let mut size = None;
         let mut home = None;
+        let mut aur = AurMetadata::default();
 
-        for line in output.lines() {
-            if let Some((k, v)) = line.split_once(':') {
-                let key = k.trim();
-                let value = v.trim();
-                match key {
-                    "Name" => name = value.to_string(),
-                    "Version" => version = value.to_string(),
-                    "Description" => {
-                        desc = value.to_string();
-                        summary = value.to_string();
-                    }
-                    "Installed Size" | "Download Size" => size = Some(value.to_string()),
-                    "URL" => home = Some(value.to_string()),
-                    _ => {}
+        for (key, value) in info_fields(output, INFO_KEYS, true) {
+            match key {
+                "Name" => name = value,
+                "Version" => version = value,
+                "Description" => {
+                    summary = value.clone();
+                    desc = value;
                 }
+                "Installed Size" | "Download Size" => size = Some(value),
+                "URL" => home = Some(value),
+                "Votes" => aur.votes = value.parse().ok(),
+                "Popularity" => aur.popularity = value.parse().ok(),
+                "Maintainer" if value != "None" => aur.maintainer = Some(value),
+                "Out-of-date" | "Out Of Date" if value != "No" => aur.out_of_date = Some(value),
+                "Last Modified" => aur.last_modified = Some(value),
+                _ => {}
             }
         }
+        if name.is_empty() || version.is_empty() {
+            return Err(ProviderError::ParseError(String::from(
+                "unrecognized AUR info output",
+            )));
+        }
 
-        PackageDetails {
+        Ok(PackageDetails {
             name,
             summary,
             description: desc,
@@ -99,27 +185,107 @@ impl Aur {
             home,
             screenshots: Vec::new(),
             icon_name: None,
-        }
+            aur,
+            permissions: Vec::new(),
+        })
     }
 }
 
 impl AurProvider for Aur {
-    fn search(&self, query: &str) -> Result<Vec<PackageSummary>> {
-        let mut args = vec!["-Ss".to_string()];
-        let terms: Vec<String> = query
-            .split_whitespace()
-            .map(|s| s.to_string())
-            .collect();
-        if terms.is_empty() {
+    fn search(&self, query: &str, mode: SearchMode) -> Result<Vec<PackageSummary>> {
+        let Some(args) = Self::search_args(query, mode) else {
             return Ok(Vec::new());
-        }
-        args.extend(terms);
+        };
         let output = self.run_capture(args)?;
-        Ok(Self::parse_search_output(&output))
+        let mut results = Self::parse_search_output(&output);
+        results.retain(|pkg| mode.matches_name(&pkg.name, query));
+        Ok(results)
     }
 
     fn info(&self, name: &str) -> Result<PackageDetails> {
         let output = self.run_capture(["-Si", name])?;
-        Ok(Self::parse_info(&output))
+        Self::parse_info(&output)
+    }
+
+    fn updates(&self) -> Result<Vec<PackageUpdate>> {
+        let helper = self.helper_bin();
+        let output = self.output(&helper, ["-Qua"])?;
+        if update_check_failed(&output) {
+            return Err(ProviderError::command_failed(&helper, &output));
+        }
+        Ok(parse_aur_updates(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    // -G clones the AUR git repository into the working directory, so it runs in a private
+    // scratch directory under the user's cache, removed again whether or not the clone worked.
+    fn pkgbuild(&self, name: &str) -> Result<String> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let helper = self.helper_bin();
+        let parent = cache_dir().join("pkgbuild");
+        fs::create_dir_all(&parent)?;
+        let unique = NEXT.fetch_add(1, Ordering::Relaxed);
+        let dir = parent.join(format!("{}-{unique}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::DirBuilder::new().mode(0o700).create(&dir)?;
+        let result = self.read_pkgbuild(&helper, name, &dir);
+        let _ = fs::remove_dir_all(&dir);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_helper_info() {
+        let output = "\
+Repository      : aur
+Name            : visual-studio-code-bin
+Version         : 1.94.2-1
+Description     : Visual Studio Code (vscode): Editor for building and debugging
+URL             : https://code.visualstudio.com/
+AUR URL         : https://aur.archlinux.org/packages/visual-studio-code-bin
+Depends On      : libxkbfile  gnupg  gtk3  libsecret  nss  gcc-libs  libnotify
+Optional Deps   : glib2: Needed for move to trash functionality
+                  libdbusmenu-glib: Needed for KDE global menu
+Maintainer      : dcelasun
+Votes           : 1402
+Popularity      : 19.87
+Out-of-date     : No
+";
+        let details = Aur::parse_info(output).unwrap();
+        assert_eq!(details.name, "visual-studio-code-bin");
+        assert_eq!(details.version, "1.94.2-1");
+        assert_eq!(details.summary, "Visual Studio Code (vscode): Editor for building and debugging");
+        assert_eq!(details.home.as_deref(), Some("https://code.visualstudio.com/"));
+        assert_eq!(details.aur.votes, Some(1402));
+        assert_eq!(details.aur.popularity, Some(19.87));
+        assert_eq!(details.aur.maintainer.as_deref(), Some("dcelasun"));
+        assert!(details.aur.out_of_date.is_none());
+    }
+
+    #[test]
+    fn search_output_pairs_headers_with_descriptions() {
+        let output = "\
+aur/paru 2.0.4-1 (+2100 12.45) (Installed)
+    Feature packed AUR helper
+aur/paru-git 2.0.4.r0-1 (+190 0.82)
+aur/yay 12.4.2-1 (+2500 15.10)
+    Yet another yogurt. Pacman wrapper and AUR helper written in go.
+";
+        let results = Aur::parse_search_output(output);
+        let names: Vec<&str> = results.iter().map(|pkg| pkg.name.as_str()).collect();
+        assert_eq!(names, ["paru", "paru-git", "yay"]);
+        assert_eq!(results[1].summary, "");
+        assert_eq!(results[2].popularity, Some(15.10));
+    }
+
+    #[test]
+    fn search_terms_cannot_become_flags() {
+        let args = Aur::search_args("-S firefox", SearchMode::NameDescription).unwrap();
+        assert_eq!(args, ["-Ss", "--", "-S", "firefox"]);
+        let args = Aur::search_args("-S", SearchMode::Name).unwrap();
+        assert_eq!(args, ["-Ss", "--searchby", "name", "--", "-S"]);
     }
 }
@@ -1,21 +1,46 @@
-use std::process::Command;
+use std::collections::{HashMap, HashSet};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
 
-use anyhow::{anyhow, Result};
+use crate::core::error::{ProviderError, Result};
+use crate::core::models::{
+    AurMetadata, FlatpakRemote, PackageDetails, PackageSource, PackageSummary, SearchMode,
+    Settings, UnusedRuntime,
+};
+use crate::core::providers::{capture, capture_timeout, info_fields, FlatpakProvider};
+use crate::core::runner::CommandSpec;
+use crate::core::updates::FlatpakUpdate;
 
-use crate::core::models::{PackageDetails, PackageSource, PackageSummary};
-use crate::core::providers::FlatpakProvider;
+const INFO_KEYS: &[&str] = &[
+    "ID",
+    "Application",
+    "Summary",
+    "Description",
+    "Version",
+    "Website",
+    "URL",
+    "Installed",
+    "Installed Size",
+];
 
-#[derive(Debug, Default)]
-pub struct Flatpak;
+#[derive(Debug)]
+pub struct Flatpak {
+    settings: Arc<Mutex<Settings>>,
+}
 
 impl Flatpak {
-    fn run_capture(args: &[&str]) -> Result<String> {
-        let output = Command::new("flatpak")
-            .args(args)
-            .env("LC_ALL", "C")
-            .output()?;
+    pub fn new(settings: Arc<Mutex<Settings>>) -> Self {
+        Self { settings }
+    }
+
+    fn run_capture(&self, args: &[&str]) -> Result<String> {
+        let output = capture(
+            "flatpak",
+            Command::new("flatpak").args(args),
+            capture_timeout(&self.settings),
+        )?;
         if !output.status.success() {
-            return Err(anyhow!("flatpak failed with status {}", output.status));
+            return Err(ProviderError::command_failed("flatpak", &output));
         }
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
     }
@@ -46,7 +71,15 @@ impl Flatpak {
                 version: display_version,
                 source: PackageSource::Flatpak,
                 installed: false,
-                origin: if remote.is_empty() { None } else { Some(remote) },
+                // Several remotes come back comma-separated; leave the choice to the user.
+                origin: if remote.is_empty() || remote.contains(',') {
+                    None
+                } else {
+                    Some(remote)
+                },
+                popularity: None,
+                group_size: None,
+                install_date: None,
             });
         }
         results
@@ -78,12 +111,85 @@ impl Flatpak {
                 source: PackageSource::Flatpak,
                 installed: true,
                 origin: None,
+                popularity: None,
+                group_size: None,
+                install_date: None,
             });
         }
         results
     }
 
-    fn parse_info(output: &str) -> PackageDetails {
+    // `remote-ls --updates --columns=application,version,branch,remote`
+    fn parse_updates(output: &str) -> Vec<FlatpakUpdate> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let mut cols = line.split('\t').map(str::trim);
+                let app_id = cols.next().unwrap_or("").to_string();
+                if app_id.is_empty() {
+                    return None;
+                }
+                Some(FlatpakUpdate {
+                    app_id,
+                    version: cols.next().unwrap_or("").to_string(),
+                    branch: cols.next().unwrap_or("").to_string(),
+                    remote: cols.next().unwrap_or("").to_string(),
+                })
+            })
+            .collect()
+    }
+
+    // The numbered rows of the table `uninstall` shows before asking to proceed:
+    // " 1.     org.gnome.Platform     46     r".
+    fn parse_unused(output: &str) -> Vec<UnusedRuntime> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let [index, id, branch, ..] = fields.as_slice() else {
+                    return None;
+                };
+                let number = index.strip_suffix('.')?;
+                if number.is_empty() || !number.bytes().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                Some(UnusedRuntime {
+                    id: id.to_string(),
+                    branch: branch.to_string(),
+                    size: None,
+                })
+            })
+            .collect()
+    }
+
+    // `list --columns=application,branch,size`
+    fn parse_sizes(output: &str) -> HashMap<(String, String), u64> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let mut cols = line.split('\t').map(str::trim);
+                let id = cols.next()?.to_string();
+                let branch = cols.next()?.to_string();
+                Some(((id, branch), parse_size(cols.next()?)?))
+            })
+            .collect()
+    }
+
+    fn parse_remotes(output: &str) -> Vec<FlatpakRemote> {
+        let mut remotes = Vec::new();
+        for line in output.lines() {
+            let cols: Vec<&str> = line.split('\t').collect();
+            let name = cols.first().unwrap_or(&"").trim().to_string();
+            if name.is_empty() {
+                continue;
+            }
+            let url = cols.get(1).unwrap_or(&"").trim().to_string();
+            remotes.push(FlatpakRemote { name, url });
+        }
+        remotes
+    }
+
+    fn parse_info(output: &str) -> Result<PackageDetails> {
         let mut name = String::new();
         let mut version = String::new();
         let mut desc = String::new();
@@ -91,27 +197,35 @@ impl Flatpak {
         let mut home = None;
         let mut size = None;
 
-        for line in output.lines() {
-            if let Some((k, v)) = line.split_once(':') {
-                let key = k.trim();
-                let value = v.trim();
-                match key {
-                    "Name" | "Application" => name = value.to_string(),
-                    "Summary" => summary = value.to_string(),
-                    "Description" => desc = value.to_string(),
-                    "Version" => version = value.to_string(),
-                    "Website" | "URL" => home = Some(value.to_string()),
-                    "Installed Size" => size = Some(value.to_string()),
-                    _ => {}
-                }
+        for (key, value) in info_fields(output, INFO_KEYS, false) {
+            match key {
+                "ID" | "Application" => name = value,
+                "Summary" => summary = value,
+                "Description" => desc = value,
+                "Version" => version = value,
+                "Website" | "URL" => home = Some(value),
+                "Installed" | "Installed Size" => size = Some(value),
+                _ => {}
             }
         }
+        if name.is_empty() {
+            return Err(ProviderError::ParseError(String::from(
+                "unrecognized flatpak info output",
+            )));
+        }
 
+        // Current flatpak prints "Title - summary" above the fields instead of a Summary key.
         if summary.is_empty() {
-            summary = desc.clone();
+            summary = output
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .and_then(|line| line.split_once(" - "))
+                .map(|(_, text)| text.trim().to_string())
+                .unwrap_or_else(|| desc.clone());
         }
 
-        PackageDetails {
+        Ok(PackageDetails {
             name,
             summary,
             description: desc,
@@ -122,28 +236,317 @@ impl Flatpak {
             home,
             screenshots: Vec::new(),
             icon_name: None,
-        }
+            aur: AurMetadata::default(),
+            permissions: Vec::new(),
+        })
     }
 }
 
 impl FlatpakProvider for Flatpak {
-    fn search(&self, query: &str) -> Result<Vec<PackageSummary>> {
-        let output = Self::run_capture(&[
+    fn search(&self, query: &str, mode: SearchMode) -> Result<Vec<PackageSummary>> {
+        let output = self.run_capture(&[
             "search",
             "--columns=application,description,version,branch,remote",
             query,
         ])?;
-        Ok(Self::parse_search(&output))
+        let mut results = Self::parse_search(&output);
+        results.retain(|pkg| mode.matches_name(&pkg.name, query));
+        Ok(results)
     }
 
     fn info(&self, name: &str) -> Result<PackageDetails> {
-        let output = Self::run_capture(&["info", name])?;
-        Ok(Self::parse_info(&output))
+        let output = self.run_capture(&["info", name])?;
+        let mut details = Self::parse_info(&output)?;
+        if let Ok(permissions) = self.run_capture(&["info", "--show-permissions", name]) {
+            details.permissions = parse_permissions(&permissions);
+        }
+        Ok(details)
     }
 
     fn list_installed(&self) -> Result<Vec<PackageSummary>> {
         let output =
-            Self::run_capture(&["list", "--app", "--columns=application,description,version,branch"])?;
+            self.run_capture(&["list", "--app", "--columns=application,description,version,branch"])?;
         Ok(Self::parse_list(&output))
     }
+
+    fn installed_among(&self, names: &[String]) -> Result<HashSet<String>> {
+        if names.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let output = self.run_capture(&["list", "--app", "--columns=application"])?;
+        Ok(output
+            .lines()
+            .map(str::trim)
+            .filter(|id| names.iter().any(|name| name == id))
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn remotes_for(&self, app_id: &str) -> Result<Vec<String>> {
+        let output = self.run_capture(&["search", "--columns=application,remote", app_id])?;
+        let mut remotes: Vec<String> = output
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .filter(|(id, _)| id.trim() == app_id)
+            .flat_map(|(_, remote)| remote.split(','))
+            .map(|remote| remote.trim().to_string())
+            .filter(|remote| !remote.is_empty())
+            .collect();
+        remotes.sort();
+        remotes.dedup();
+        Ok(remotes)
+    }
+
+    fn list_remotes(&self) -> Result<Vec<FlatpakRemote>> {
+        let output = self.run_capture(&["remotes", "--columns=name,url"])?;
+        Ok(Self::parse_remotes(&output))
+    }
+
+    fn add_remote(&self, name: &str, url: &str) -> CommandSpec {
+        CommandSpec::new(
+            "flatpak",
+            vec![
+                "remote-add".to_string(),
+                "--if-not-exists".to_string(),
+                name.to_string(),
+                url.to_string(),
+            ],
+        )
+    }
+
+    fn remove_remote(&self, name: &str) -> CommandSpec {
+        CommandSpec::new(
+            "flatpak",
+            vec!["remote-delete".to_string(), name.to_string()],
+        )
+    }
+
+    fn updates(&self) -> Result<Vec<FlatpakUpdate>> {
+        let output = self.run_capture(&[
+            "remote-ls",
+            "--updates",
+            "--columns=application,version,branch,remote",
+        ])?;
+        Ok(Self::parse_updates(&output))
+    }
+
+    // There is no dry-run flag, but without a terminal flatpak answers its own confirmation
+    // prompt with "n", so this only prints the table of what would go.
+    fn unused_runtimes(&self) -> Result<Vec<UnusedRuntime>> {
+        let output = capture(
+            "flatpak",
+            Command::new("flatpak")
+                .args(["uninstall", "--unused"])
+                .stdin(Stdio::null()),
+            capture_timeout(&self.settings),
+        )?;
+        let mut unused = Self::parse_unused(&String::from_utf8_lossy(&output.stdout));
+        if unused.is_empty() {
+            return Ok(unused);
+        }
+        let sizes = Self::parse_sizes(
+            &self.run_capture(&["list", "--runtime", "--columns=application,branch,size"])?,
+        );
+        for runtime in &mut unused {
+            runtime.size = sizes
+                .get(&(runtime.id.clone(), runtime.branch.clone()))
+                .copied();
+        }
+        Ok(unused)
+    }
+
+    fn remove_unused(&self) -> CommandSpec {
+        CommandSpec::new(
+            "flatpak",
+            vec!["uninstall".to_string(), "--unused".to_string(), "-y".to_string()],
+        )
+    }
+}
+
+// flatpak prints sizes with SI units ("325.6 MB"), sometimes joined by a no-break space.
+fn parse_size(value: &str) -> Option<u64> {
+    let mut parts = value.split_whitespace();
+    let number: f64 = parts.next()?.parse().ok()?;
+    let scale = match parts.next()? {
+        "bytes" | "B" => 1.0,
+        "kB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        _ => return None,
+    };
+    Some((number * scale).round() as u64)
+}
+
+// Turns the `--show-permissions` keyfile into `flatpak override` style entries such as
+// `filesystem=host` or `talk-name=org.freedesktop.Notifications`.
+fn parse_permissions(output: &str) -> Vec<String> {
+    let mut permissions = Vec::new();
+    let mut section = String::new();
+    for line in output.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            section = name.to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match section.as_str() {
+            "Context" => {
+                let prefix = match key {
+                    "shared" => "share",
+                    "sockets" => "socket",
+                    "devices" => "device",
+                    "filesystems" => "filesystem",
+                    "features" => "allow",
+                    "persistent" => "persist",
+                    _ => continue,
+                };
+                permissions.extend(
+                    value
+                        .split(';')
+                        .filter(|item| !item.is_empty())
+                        .map(|item| format!("{prefix}={item}")),
+                );
+            }
+            "Session Bus Policy" | "System Bus Policy" => {
+                let system = if section.starts_with("System") { "system-" } else { "" };
+                if value != "none" {
+                    permissions.push(format!("{system}{value}-name={key}"));
+                }
+            }
+            _ => {}
+        }
+    }
+    permissions
+}
+
+pub fn is_broad_permission(permission: &str) -> bool {
+    let Some((kind, value)) = permission.split_once('=') else {
+        return false;
+    };
+    // A leading `!` revokes the permission.
+    if value.starts_with('!') {
+        return false;
+    }
+    match kind {
+        "filesystem" => {
+            let target = value.split(':').next().unwrap_or(value);
+            matches!(target, "host" | "host-os" | "host-etc" | "home" | "~" | "/")
+        }
+        "device" => value == "all",
+        "socket" => matches!(value, "session-bus" | "system-bus"),
+        "talk-name" | "own-name" | "system-talk-name" | "system-own-name" => {
+            value == "org.freedesktop.Flatpak" || value == "*"
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_permissions_and_flags_broad_ones() {
+        let output = "\
+[Context]
+shared=network;ipc;
+sockets=x11;wayland;pulseaudio;
+devices=dri;
+filesystems=xdg-download;host:ro;
+
+[Session Bus Policy]
+org.freedesktop.Notifications=talk
+org.freedesktop.Flatpak=talk
+";
+        let permissions = parse_permissions(output);
+        assert_eq!(
+            permissions,
+            [
+                "share=network",
+                "share=ipc",
+                "socket=x11",
+                "socket=wayland",
+                "socket=pulseaudio",
+                "device=dri",
+                "filesystem=xdg-download",
+                "filesystem=host:ro",
+                "talk-name=org.freedesktop.Notifications",
+                "talk-name=org.freedesktop.Flatpak",
+            ]
+        );
+        let broad: Vec<&String> = permissions.iter().filter(|p| is_broad_permission(p)).collect();
+        assert_eq!(broad, ["filesystem=host:ro", "talk-name=org.freedesktop.Flatpak"]);
+    }
+
+    #[test]
+    fn parses_info_with_title_line() {
+        let output = "
+Maps - Find places around the world
+
+          ID: org.gnome.Maps
+         Ref: app/org.gnome.Maps/x86_64/stable
+        Arch: x86_64
+      Branch: stable
+     Version: 47.0
+     License: GPL-2.0+
+      Origin: flathub
+Installation: system
+   Installed: 10.2 MB
+     Runtime: org.gnome.Platform/x86_64/47
+
+      Commit: 1f2e3d4c
+     Subject: Update to 47.0: new search (abc123)
+        Date: 2024-09-20 10:00:00 +0000
+";
+        let details = Flatpak::parse_info(output).unwrap();
+        assert_eq!(details.name, "org.gnome.Maps");
+        assert_eq!(details.summary, "Find places around the world");
+        assert_eq!(details.version, "47.0");
+        assert_eq!(details.size.as_deref(), Some("10.2 MB"));
+    }
+
+    #[test]
+    fn parses_update_columns() {
+        let updates = Flatpak::parse_updates(
+            "org.gnome.Maps\t46.1\tstable\tflathub\norg.freedesktop.Platform\t\t23.08\tflathub\n\n",
+        );
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].app_id, "org.gnome.Maps");
+        assert_eq!(updates[0].version, "46.1");
+        assert_eq!(updates[1].version, "");
+        assert_eq!(updates[1].branch, "23.08");
+        assert_eq!(updates[1].remote, "flathub");
+    }
+
+    #[test]
+    fn parses_unused_runtimes_and_their_sizes() {
+        let unused = Flatpak::parse_unused(
+            "\n        ID                                   Branch    Op\n \
+             1.     org.freedesktop.Platform.GL.default  23.08     r\n \
+             2.     org.gnome.Platform                   45        r\n\n\
+             Proceed with these changes to the user installation? [Y/n]: n\n",
+        );
+        assert_eq!(unused.len(), 2);
+        assert_eq!(unused[1].id, "org.gnome.Platform");
+        assert_eq!(unused[1].branch, "45");
+        assert!(Flatpak::parse_unused("Nothing unused to uninstall\n").is_empty());
+
+        let sizes = Flatpak::parse_sizes(
+            "org.gnome.Platform\t45\t1.2\u{a0}GB\norg.gnome.Platform\t46\t325.6 MB\n",
+        );
+        let size = |branch: &str| sizes.get(&("org.gnome.Platform".to_string(), branch.to_string()));
+        assert_eq!(size("45"), Some(&1_200_000_000));
+        assert_eq!(size("46"), Some(&325_600_000));
+    }
+
+    #[test]
+    fn garbage_info_is_an_error() {
+        assert!(Flatpak::parse_info("error: org.gnome.Nope/*unspecified*/*unspecified* not installed").is_err());
+    }
 }
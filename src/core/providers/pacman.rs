@@ -1,43 +1,71 @@
 use std::ffi::OsStr;
-use std::collections::HashSet;
-use std::process::Command;
+use std::collections::{HashMap, HashSet};
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
 
-use anyhow::{anyhow, Result};
+use crate::core::error::{ProviderError, Result};
+use crate::core::models::{
+    AurMetadata, PackageDetails, PackageSource, PackageSummary, SearchMode, Settings,
+};
+use crate::core::providers::{
+    capture, capture_timeout, info_fields, search_entries, update_check_failed, PacmanProvider,
+};
+use crate::core::runner::command_exists;
+use crate::core::size::parse_size;
+use crate::core::updates::{parse_pacman_updates, PackageUpdate};
 
-use crate::core::models::{PackageDetails, PackageSource, PackageSummary};
-use crate::core::providers::PacmanProvider;
+const INFO_KEYS: &[&str] = &[
+    "Name",
+    "Version",
+    "Description",
+    "URL",
+    "Depends On",
+    "Optional Deps",
+    "Installed Size",
+    "Download Size",
+];
 
-#[derive(Debug, Default)]
-pub struct Pacman;
+#[derive(Debug)]
+pub struct Pacman {
+    settings: Arc<Mutex<Settings>>,
+    download_size_cache: Mutex<HashMap<String, u64>>,
+}
 
 impl Pacman {
-    fn run_capture<I, S>(args: I) -> Result<String>
+    pub fn new(settings: Arc<Mutex<Settings>>) -> Self {
+        Self {
+            settings,
+            download_size_cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn output<I, S>(&self, args: I) -> Result<Output>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        capture(
+            "pacman",
+            Command::new("pacman").args(args),
+            capture_timeout(&self.settings),
+        )
+    }
+
+    fn run_capture<I, S>(&self, args: I) -> Result<String>
     where
         I: IntoIterator<Item = S>,
         S: AsRef<OsStr>,
     {
-        let output = Command::new("pacman")
-            .args(args)
-            .env("LC_ALL", "C")
-            .output()?;
+        let output = self.output(args)?;
         if !output.status.success() {
-            return Err(anyhow!(
-                "pacman failed with status {}",
-                output.status
-            ));
+            return Err(ProviderError::command_failed("pacman", &output));
         }
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
     }
 
     fn parse_search_output(output: &str) -> Vec<PackageSummary> {
         let mut results = Vec::new();
-        let mut lines = output.lines();
-        while let Some(line) = lines.next() {
-            if line.trim().is_empty() {
-                continue;
-            }
-            let header = line.trim();
-            let summary = lines.next().unwrap_or("").trim().to_string();
+        for (header, summary) in search_entries(output) {
             let mut parts = header.split_whitespace();
             let repo_pkg = parts.next().unwrap_or("");
             let version = parts.next().unwrap_or("").to_string();
@@ -49,12 +77,15 @@ impl Pacman {
                 source: PackageSource::Repo,
                 installed: false,
                 origin: None,
+                popularity: None,
+                group_size: None,
+                install_date: None,
             });
         }
         results
     }
 
-    fn parse_info(output: &str, source: PackageSource) -> PackageDetails {
+    fn parse_info(output: &str, source: PackageSource) -> Result<PackageDetails> {
         let mut name = String::new();
         let mut version = String::new();
         let mut desc = String::new();
@@ -62,25 +93,26 @@ impl Pacman {
         let mut size = None;
         let mut home = None;
 
-        for line in output.lines() {
-            if let Some((k, v)) = line.split_once(':') {
-                let key = k.trim();
-                let value = v.trim();
-                match key {
-                    "Name" => name = value.to_string(),
-                    "Version" => version = value.to_string(),
-                    "Description" => {
-                        desc = value.to_string();
-                        summary = value.to_string();
-                    }
-                    "Installed Size" | "Download Size" => size = Some(value.to_string()),
-                    "URL" => home = Some(value.to_string()),
-                    _ => {}
+        for (key, value) in info_fields(output, INFO_KEYS, true) {
+            match key {
+                "Name" => name = value,
+                "Version" => version = value,
+                "Description" => {
+                    summary = value.clone();
+                    desc = value;
                 }
+                "Installed Size" | "Download Size" => size = Some(value),
+                "URL" => home = Some(value),
+                _ => {}
             }
         }
+        if name.is_empty() || version.is_empty() {
+            return Err(ProviderError::ParseError(String::from(
+                "unrecognized pacman info output",
+            )));
+        }
 
-        PackageDetails {
+        Ok(PackageDetails {
             name,
             summary,
             description: desc,
@@ -91,11 +123,101 @@ impl Pacman {
             home,
             screenshots: Vec::new(),
             icon_name: None,
+            aur: AurMetadata::default(),
+            permissions: Vec::new(),
+        })
+    }
+
+    // Both `-Ql` and `-Fl` print "pkgname path" per line; the files database omits the
+    // leading slash and directories end with one, which we drop.
+    fn parse_file_list(output: &str) -> Vec<String> {
+        output
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(_, path)| path.trim())
+            .filter(|path| !path.is_empty() && !path.ends_with('/'))
+            .map(|path| {
+                if path.starts_with('/') {
+                    path.to_string()
+                } else {
+                    format!("/{path}")
+                }
+            })
+            .collect()
+    }
+
+    // `-Fq` prints "repo/name" once per matching file, so a bare file name that several
+    // packages ship repeats them.
+    fn parse_file_owners(output: &str) -> Vec<String> {
+        let mut owners: Vec<String> = Vec::new();
+        for line in output.lines() {
+            let Some(entry) = line.split_whitespace().next() else {
+                continue;
+            };
+            let name = entry.rsplit('/').next().unwrap_or(entry);
+            if !owners.iter().any(|owner| owner == name) {
+                owners.push(name.to_string());
+            }
+        }
+        owners
+    }
+
+    // Multi-package `-Si`/`-Qi` output; packages that are not found are simply missing.
+    fn parse_sizes(output: &str, key: &str) -> HashMap<String, u64> {
+        let mut sizes = HashMap::new();
+        let mut name = String::new();
+        for (field, value) in info_fields(output, &["Name", key], false) {
+            if field == "Name" {
+                name = value;
+            } else if let Some(bytes) = parse_size(&value) {
+                sizes.entry(name.clone()).or_insert(bytes);
+            }
+        }
+        sizes
+    }
+
+    fn query_sizes(&self, flag: &str, key: &str, names: &[String]) -> Result<HashMap<String, u64>> {
+        let args = [flag, "--"].into_iter().chain(names.iter().map(String::as_str));
+        let output = self.output(args)?;
+        if !output.status.success() && output.stdout.is_empty() {
+            return Err(ProviderError::command_failed("pacman", &output));
         }
+        Ok(Self::parse_sizes(&String::from_utf8_lossy(&output.stdout), key))
     }
 
-    fn list_foreign_names() -> Result<HashSet<String>> {
-        let output = Self::run_capture(["-Qm"])?;
+    // `expac --timefmt=%s '%n\t%l'` prints one "name<TAB>unix time" line per package.
+    fn parse_expac_dates(output: &str) -> HashMap<String, i64> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let (name, date) = line.split_once('\t')?;
+                Some((name.to_string(), date.trim().parse().ok()?))
+            })
+            .collect()
+    }
+
+    fn parse_install_dates(output: &str) -> HashMap<String, i64> {
+        let mut dates = HashMap::new();
+        let mut name = None;
+        for line in output.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            match key.trim() {
+                "Name" => name = Some(value.trim().to_string()),
+                "Install Date" => {
+                    if let (Some(name), Some(date)) = (name.take(), parse_c_locale_date(value)) {
+                        dates.insert(name, date);
+                    }
+                }
+                _ => {}
+            }
+        }
+        dates
+    }
+
+    fn list_names(&self, flag: &str) -> Result<HashSet<String>> {
+        let output = self.run_capture([flag])?;
         let mut names = HashSet::new();
         for line in output.lines() {
             let mut parts = line.split_whitespace();
@@ -109,35 +231,30 @@ impl Pacman {
 }
 
 impl PacmanProvider for Pacman {
-    fn search(&self, query: &str) -> Result<Vec<PackageSummary>> {
-        let mut args = vec!["-Ss".to_string()];
-        let terms: Vec<String> = query
-            .split_whitespace()
-            .map(|s| s.to_string())
-            .collect();
-        if terms.is_empty() {
+    fn search(&self, query: &str, mode: SearchMode) -> Result<Vec<PackageSummary>> {
+        let Some(args) = search_args(query, mode) else {
             return Ok(Vec::new());
-        }
-        args.extend(terms);
-        let output = Self::run_capture(args)?;
-        Ok(Self::parse_search_output(&output))
+        };
+        let output = self.run_capture(args)?;
+        let mut results = Self::parse_search_output(&output);
+        results.retain(|pkg| mode.matches_name(&pkg.name, query));
+        Ok(results)
     }
 
     fn info_repo(&self, name: &str) -> Result<PackageDetails> {
-        let output = Self::run_capture(["-Si", name])?;
-        Ok(Self::parse_info(&output, PackageSource::Repo))
+        let output = self.run_capture(["-Si", name])?;
+        Self::parse_info(&output, PackageSource::Repo)
     }
 
     fn info_installed(&self, name: &str) -> Result<PackageDetails> {
-        let output = Self::run_capture(["-Qi", name])?;
-        let mut details = Self::parse_info(&output, PackageSource::Repo);
+        let output = self.run_capture(["-Qi", name])?;
+        let mut details = Self::parse_info(&output, PackageSource::Repo)?;
         details.installed = true;
         Ok(details)
     }
 
     fn list_installed(&self) -> Result<Vec<PackageSummary>> {
-        let output = Self::run_capture(["-Q"])?;
-        let foreign = Self::list_foreign_names().unwrap_or_default();
+        let output = self.run_capture(["-Q"])?;
         let mut results = Vec::new();
         for line in output.lines() {
             let mut parts = line.split_whitespace();
@@ -146,20 +263,376 @@ impl PacmanProvider for Pacman {
             if name.is_empty() {
                 continue;
             }
-            let source = if foreign.contains(&name) {
-                PackageSource::Aur
-            } else {
-                PackageSource::Repo
-            };
             results.push(PackageSummary {
                 name,
                 summary: String::from(""),
                 version,
-                source,
+                source: PackageSource::Repo,
                 installed: true,
                 origin: None,
+                popularity: None,
+                group_size: None,
+                install_date: None,
             });
         }
         Ok(results)
     }
+
+    fn installed_among(&self, names: &[String]) -> Result<HashSet<String>> {
+        if names.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let args = ["-Qq", "--"].into_iter().chain(names.iter().map(String::as_str));
+        let output = self.output(args)?;
+        // Names that are not installed are reported on stderr with exit code 1; the
+        // installed ones are still printed.
+        if !output.status.success() && output.status.code() != Some(1) {
+            return Err(ProviderError::command_failed("pacman", &output));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn list_foreign(&self) -> Result<HashSet<String>> {
+        self.list_names("-Qmq")
+    }
+
+    fn list_explicit_names(&self) -> Result<HashSet<String>> {
+        self.list_names("-Qeq")
+    }
+
+    // Each package with a desktop entry also owns /usr/share/applications, so one query
+    // finds every installed application.
+    fn desktop_app_names(&self) -> Result<HashSet<String>> {
+        let output = self.run_capture(["-Qqo", "/usr/share/applications"])?;
+        Ok(output
+            .lines()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    // One batched query either way; expac is preferred because it prints Unix times.
+    fn install_dates(&self) -> Result<HashMap<String, i64>> {
+        if command_exists("expac") {
+            let output = capture(
+                "expac",
+                Command::new("expac").args(["--timefmt=%s", "%n\t%l"]),
+                capture_timeout(&self.settings),
+            )?;
+            if output.status.success() {
+                return Ok(Self::parse_expac_dates(&String::from_utf8_lossy(&output.stdout)));
+            }
+        }
+        Ok(Self::parse_install_dates(&self.run_capture(["-Qi"])?))
+    }
+
+    fn count_pending_updates(&self) -> Result<usize> {
+        Ok(self.updates()?.iter().filter(|update| !update.ignored).count())
+    }
+
+    fn list_files(&self, name: &str, installed: bool) -> Result<Vec<String>> {
+        let flag = if installed { "-Ql" } else { "-Fl" };
+        let output = self.output([flag, "--", name])?;
+        if !installed && files_database_missing(&output) {
+            return Err(ProviderError::FilesDatabaseMissing);
+        }
+        if !output.status.success() {
+            return Err(ProviderError::command_failed("pacman", &output));
+        }
+        Ok(Self::parse_file_list(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    fn owner_of_file(&self, path: &str) -> Result<Vec<String>> {
+        let output = self.output(["-Fq", "--", path.trim()])?;
+        if files_database_missing(&output) {
+            return Err(ProviderError::FilesDatabaseMissing);
+        }
+        // -F exits with 1 and prints nothing when no package has the file.
+        if !output.status.success() && !output.stderr.is_empty() {
+            return Err(ProviderError::command_failed("pacman", &output));
+        }
+        Ok(Self::parse_file_owners(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    // Sync groups first, then local ones so groups of packages no longer in a repo still count.
+    fn group_members(&self, name: &str) -> Result<Vec<String>> {
+        for flag in ["-Sgq", "-Qgq"] {
+            let output = self.output([flag, "--", name])?;
+            // Both exit with 1 when no group has that name.
+            if !output.status.success() {
+                continue;
+            }
+            let members: Vec<String> = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect();
+            if !members.is_empty() {
+                return Ok(members);
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    // Mirrors the `-Rns` used for queued removals so the list matches what would go.
+    fn removal_preview(&self, names: &[String]) -> Result<Vec<String>> {
+        let mut args = vec![
+            "-Rns".to_string(),
+            "--print".to_string(),
+            "--print-format".to_string(),
+            "%n".to_string(),
+            "--".to_string(),
+        ];
+        args.extend(names.iter().cloned());
+        let output = self.run_capture(args)?;
+        Ok(output
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    // Targets plus the dependencies that are not installed yet; groups expand to their members.
+    fn install_preview(&self, names: &[String]) -> Result<Vec<String>> {
+        let mut args = vec![
+            "-S".to_string(),
+            "--print".to_string(),
+            "--print-format".to_string(),
+            "%n".to_string(),
+            "--".to_string(),
+        ];
+        args.extend(names.iter().cloned());
+        let output = self.run_capture(args)?;
+        Ok(output
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn download_sizes(&self, names: &[String]) -> Result<HashMap<String, u64>> {
+        let missing: Vec<String> = {
+            let cache = self.download_size_cache.lock().unwrap();
+            names
+                .iter()
+                .filter(|name| !cache.contains_key(*name))
+                .cloned()
+                .collect()
+        };
+        if !missing.is_empty() {
+            let fetched = self.query_sizes("-Si", "Download Size", &missing)?;
+            self.download_size_cache.lock().unwrap().extend(fetched);
+        }
+        let cache = self.download_size_cache.lock().unwrap();
+        Ok(names
+            .iter()
+            .filter_map(|name| cache.get(name).map(|bytes| (name.clone(), *bytes)))
+            .collect())
+    }
+
+    fn updates(&self) -> Result<Vec<PackageUpdate>> {
+        let output = self.output(["-Qu"])?;
+        if update_check_failed(&output) {
+            return Err(ProviderError::command_failed("pacman", &output));
+        }
+        Ok(parse_pacman_updates(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    fn installed_sizes(&self, names: &[String]) -> Result<HashMap<String, u64>> {
+        if names.is_empty() {
+            return Ok(HashMap::new());
+        }
+        self.query_sizes("-Qi", "Installed Size", names)
+    }
+}
+
+// Without a files database pacman only warns "use '-Fy' to download" and prints nothing.
+fn files_database_missing(output: &Output) -> bool {
+    output.stdout.is_empty() && String::from_utf8_lossy(&output.stderr).contains("-Fy")
+}
+
+fn search_args(query: &str, mode: SearchMode) -> Option<Vec<String>> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|s| s.to_string())
+        .collect();
+    if terms.is_empty() || (mode == SearchMode::Exact && terms.len() > 1) {
+        return None;
+    }
+    // "--" stops option parsing so a term like "-S" is searched for, not run as an operation.
+    let mut args = vec!["-Ss".to_string(), "--".to_string()];
+    if mode == SearchMode::Exact {
+        args.push(exact_name_pattern(&terms[0]));
+    } else {
+        args.extend(terms);
+    }
+    Some(args)
+}
+
+fn exact_name_pattern(name: &str) -> String {
+    let mut pattern = String::from("^");
+    for c in name.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('$');
+    pattern
+}
+
+// pacman formats dates with "%c", which under LC_ALL=C reads "Tue Jan  9 14:03:12 2024".
+// The local time is taken as UTC; the result is only used for ordering and showing the day.
+fn parse_c_locale_date(value: &str) -> Option<i64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_, month, day, time, year] = parts.as_slice() else {
+        return None;
+    };
+    let month = MONTHS.iter().position(|m| m == month)? as i64 + 1;
+    let day: i64 = day.parse().ok()?;
+    let year: i64 = year.parse().ok()?;
+    let mut clock = time.split(':').map(|part| part.parse::<i64>().ok());
+    let (hours, minutes, seconds) = (clock.next()??, clock.next()??, clock.next()??);
+
+    // Days since 1970-01-01 for a proleptic Gregorian date.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    Some(days * 86_400 + hours * 3600 + minutes * 60 + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_install_dates_from_either_source() {
+        let info = "\
+Name            : firefox
+Version         : 131.0-1
+Install Date    : Tue Jan  9 14:03:12 2024
+Install Reason  : Explicitly installed
+
+Name            : glibc
+Install Date    : not a date
+";
+        let dates = Pacman::parse_install_dates(info);
+        assert_eq!(dates.get("firefox"), Some(&1_704_808_992));
+        assert!(!dates.contains_key("glibc"));
+
+        let expac = Pacman::parse_expac_dates("firefox\t1704808992\nbroken\n");
+        assert_eq!(expac.len(), 1);
+        assert_eq!(expac["firefox"], 1_704_808_992);
+    }
+
+    #[test]
+    fn parses_sync_info() {
+        let output = "\
+Repository      : extra
+Name            : firefox
+Version         : 131.0-1
+Description     : Fast, Private & Safe Web Browser
+Architecture    : x86_64
+URL             : https://www.mozilla.org/firefox/
+Licenses        : MPL-2.0
+Depends On      : gtk3  libxt  mime-types  dbus-glib  nss  ttf-font
+Optional Deps   : hunspell-en_US: Spell checking, American English
+                  libnotify: Notification integration
+                  speech-dispatcher: Text-to-Speech
+Download Size   : 71.10 MiB
+Installed Size  : 255.71 MiB
+Packager        : Jan Alexander Steffens (heftig) <heftig@archlinux.org>
+Build Date      : Tue 01 Oct 2024 08:00:00 PM UTC
+Validated By    : MD5 Sum  SHA-256 Sum  Signature
+";
+        let details = Pacman::parse_info(output, PackageSource::Repo).unwrap();
+        assert_eq!(details.name, "firefox");
+        assert_eq!(details.version, "131.0-1");
+        assert_eq!(details.summary, "Fast, Private & Safe Web Browser");
+        assert_eq!(details.home.as_deref(), Some("https://www.mozilla.org/firefox/"));
+        assert_eq!(details.size.as_deref(), Some("255.71 MiB"));
+    }
+
+    #[test]
+    fn parses_local_info_with_colons_in_description() {
+        let output = "\
+Name            : python
+Version         : 3.12.6-1
+Description     : The Python programming language: next generation
+URL             : https://www.python.org/
+Optional Deps   : python-setuptools: for building Python packages using tooling [installed]
+                  sqlite: for a default database integration [installed]
+Required By     : meson  python-gobject
+Installed Size  : 77.41 MiB
+Install Reason  : Installed as a dependency for another package
+";
+        let details = Pacman::parse_info(output, PackageSource::Repo).unwrap();
+        assert_eq!(details.name, "python");
+        assert_eq!(details.version, "3.12.6-1");
+        assert_eq!(details.description, "The Python programming language: next generation");
+        assert_eq!(details.size.as_deref(), Some("77.41 MiB"));
+    }
+
+    #[test]
+    fn garbage_info_is_an_error() {
+        assert!(Pacman::parse_info("error: package 'nope' was not found\n", PackageSource::Repo).is_err());
+    }
+
+    #[test]
+    fn sizes_are_read_per_package() {
+        let output = "\
+Repository      : extra
+Name            : firefox
+Download Size   : 71.10 MiB
+Installed Size  : 254.87 MiB
+
+Repository      : core
+Name            : bash
+Download Size   : 1.50 MiB
+";
+        let sizes = Pacman::parse_sizes(output, "Download Size");
+        assert_eq!(sizes.len(), 2);
+        assert_eq!(sizes["firefox"], 74_553_754);
+        assert_eq!(sizes["bash"], 1_572_864);
+    }
+
+    #[test]
+    fn file_lists_skip_directories_and_get_absolute_paths() {
+        let local = "bash /usr/\nbash /usr/bin/\nbash /usr/bin/bash\nbash /etc/bash.bashrc\n";
+        assert_eq!(Pacman::parse_file_list(local), ["/usr/bin/bash", "/etc/bash.bashrc"]);
+
+        let sync = "bash usr/\nbash usr/bin/bash\nbash usr/share/doc/bash/README\n";
+        assert_eq!(
+            Pacman::parse_file_list(sync),
+            ["/usr/bin/bash", "/usr/share/doc/bash/README"]
+        );
+    }
+
+    #[test]
+    fn file_owners_drop_repos_and_duplicates() {
+        let output = "extra/vim\nextra/gvim\nextra/vim\n\n";
+        assert_eq!(Pacman::parse_file_owners(output), ["vim", "gvim"]);
+        assert!(Pacman::parse_file_owners("").is_empty());
+    }
+
+    #[test]
+    fn search_terms_cannot_become_flags() {
+        let args = search_args("-S firefox", SearchMode::NameDescription).unwrap();
+        assert_eq!(args, vec!["-Ss", "--", "-S", "firefox"]);
+    }
 }
@@ -1,10 +1,11 @@
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, Read, Write};
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
-use std::process::{Command, Stdio};
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Output, Stdio};
 use std::sync::mpsc::Receiver;
 use std::thread;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::{anyhow, Result};
 use std::sync::mpsc::Sender;
@@ -16,6 +17,7 @@ pub struct CommandSpec {
     pub program: String,
     pub args: Vec<String>,
     pub env: Vec<(String, String)>,
+    pub cwd: Option<String>,
 }
 
 impl CommandSpec {
@@ -24,11 +26,20 @@ impl CommandSpec {
             program: program.to_string(),
             args,
             env: vec![(String::from("LC_ALL"), String::from("C"))],
+            cwd: None,
         }
     }
 
+    pub fn with_cwd(mut self, dir: &str) -> Self {
+        self.cwd = Some(dir.to_string());
+        self
+    }
+
     pub fn display_line(&self) -> String {
         let mut parts = Vec::new();
+        if let Some(dir) = &self.cwd {
+            parts.push(format!("cd {} &&", shell_quote(dir)));
+        }
         for (k, v) in &self.env {
             parts.push(format!("{k}={}", shell_quote(v)));
         }
@@ -40,7 +51,11 @@ impl CommandSpec {
     }
 
     fn shell_command(&self) -> String {
-        let mut parts = vec!["env".to_string()];
+        let mut parts = Vec::new();
+        if let Some(dir) = &self.cwd {
+            parts.push(format!("cd {} &&", shell_quote(dir)));
+        }
+        parts.push("env".to_string());
         for (k, v) in &self.env {
             parts.push(format!("{k}={}", shell_quote(v)));
         }
@@ -58,16 +73,8 @@ pub enum LogEvent {
     Finished(i32),
 }
 
-#[derive(Debug, Clone)]
-pub struct CommandRunner {
-    pub log_limit: usize,
-}
-
-impl Default for CommandRunner {
-    fn default() -> Self {
-        Self { log_limit: 1000 }
-    }
-}
+#[derive(Debug, Clone, Default)]
+pub struct CommandRunner;
 
 impl CommandRunner {
     pub fn run_capture(&self, spec: &CommandSpec) -> Result<String> {
@@ -76,6 +83,9 @@ impl CommandRunner {
         for (k, v) in &spec.env {
             cmd.env(k, v);
         }
+        if let Some(dir) = &spec.cwd {
+            cmd.current_dir(dir);
+        }
         let output = cmd.output()?;
         if !output.status.success() {
             return Err(anyhow!("command failed with status {}", output.status));
@@ -98,6 +108,9 @@ impl CommandRunner {
             for (k, v) in &spec.env {
                 cmd.env(k, v);
             }
+            if let Some(dir) = &spec.cwd {
+                cmd.current_dir(dir);
+            }
 
             let mut child = match cmd.spawn() {
                 Ok(child) => child,
@@ -184,7 +197,8 @@ impl CommandRunner {
     ) -> Result<()> {
         let Some(terminal) = resolve_terminal(preferred_terminal) else {
             return Err(anyhow!(
-                "No supported terminal found. Install kitty, konsole, or alacritty."
+                "No supported terminal found. Install one of: {}.",
+                supported_terminals()
             ));
         };
 
@@ -198,8 +212,11 @@ impl CommandRunner {
             let exit_file = std::env::temp_dir().join(format!("aurora-exit-{stamp}.code"));
             let exit_file_text = exit_file.to_string_lossy().to_string();
 
+            // The marker tells a window closed mid-run (marker only) apart from a terminal that
+            // never ran the script (no file) and from a real exit code.
             let script = format!(
-                "printf '%s\\n' {trace}\n{command}\ncode=$?\nprintf '%s\\n' \"$code\" > {exit_file}\nexit \"$code\"\n",
+                "printf '%s\\n' {marker} > {exit_file}\nprintf '%s\\n' {trace}\n{command}\ncode=$?\nprintf '%s\\n' \"$code\" > {exit_file}\nexit \"$code\"\n",
+                marker = STARTED_MARKER,
                 trace = shell_quote(&format!("[aurora] Running: {display_line}")),
                 command = shell_command,
                 exit_file = shell_quote(&exit_file_text),
@@ -219,14 +236,28 @@ impl CommandRunner {
                 }
             };
 
-            let final_code = std::fs::read_to_string(&exit_file)
-                .ok()
-                .and_then(|value| value.trim().parse::<i32>().ok())
-                .unwrap_or(status);
+            let contents = std::fs::read_to_string(&exit_file).ok();
             let _ = std::fs::remove_file(&exit_file);
-            let _ = sender.send(LogEvent::Line(format!(
-                "External terminal finished with exit code {final_code}"
-            )));
+            let (message, final_code) = match external_outcome(contents.as_deref(), status) {
+                ExternalOutcome::Finished(code) => (
+                    format!("External terminal finished with exit code {code}"),
+                    code,
+                ),
+                ExternalOutcome::ClosedEarly => (
+                    String::from(
+                        "Terminal closed before completion; the command may have been interrupted",
+                    ),
+                    TERMINAL_CLOSED_CODE,
+                ),
+                ExternalOutcome::NeverStarted(code) => (
+                    format!(
+                        "{} exited ({status}) without running the command",
+                        terminal.label()
+                    ),
+                    code,
+                ),
+            };
+            let _ = sender.send(LogEvent::Line(message));
             let _ = sender.send(LogEvent::Finished(final_code));
         });
 
@@ -234,6 +265,32 @@ impl CommandRunner {
     }
 }
 
+const STARTED_MARKER: &str = "started";
+// What a shell gets when its terminal goes away: 128 + SIGHUP.
+const TERMINAL_CLOSED_CODE: i32 = 129;
+
+#[derive(Debug, PartialEq, Eq)]
+enum ExternalOutcome {
+    Finished(i32),
+    ClosedEarly,
+    NeverStarted(i32),
+}
+
+fn external_outcome(exit_file: Option<&str>, terminal_status: i32) -> ExternalOutcome {
+    match exit_file.map(str::trim) {
+        Some(STARTED_MARKER) => ExternalOutcome::ClosedEarly,
+        Some(value) => match value.parse::<i32>() {
+            Ok(code) => ExternalOutcome::Finished(code),
+            Err(_) => ExternalOutcome::ClosedEarly,
+        },
+        None => ExternalOutcome::NeverStarted(if terminal_status == 0 {
+            1
+        } else {
+            terminal_status
+        }),
+    }
+}
+
 fn shell_quote(input: &str) -> String {
     if input.is_empty() {
         return "''".to_string();
@@ -241,7 +298,55 @@ fn shell_quote(input: &str) -> String {
     format!("'{}'", input.replace('\'', "'\"'\"'"))
 }
 
-fn command_exists(name: &str) -> bool {
+// Like `Command::output`, but returns None once `timeout` passes. The child runs in its own
+// process group so helpers that spawned pacman or curl are killed together, then reaped.
+pub fn output_with_timeout(
+    command: &mut Command,
+    timeout: Duration,
+) -> std::io::Result<Option<Output>> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()?;
+    let stdout = child.stdout.take().map(read_to_end_in_background);
+    let stderr = child.stderr.take().map(read_to_end_in_background);
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            unsafe {
+                libc::kill(-(child.id() as i32), libc::SIGKILL);
+            }
+            let _ = child.wait();
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    let collect = |reader: Option<thread::JoinHandle<Vec<u8>>>| {
+        reader
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default()
+    };
+    Ok(Some(Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    }))
+}
+
+fn read_to_end_in_background<R: Read + Send + 'static>(mut pipe: R) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = pipe.read_to_end(&mut buffer);
+        buffer
+    })
+}
+
+pub fn command_exists(name: &str) -> bool {
     if name.is_empty() || name.contains('/') {
         return false;
     }
@@ -272,9 +377,15 @@ fn command_exists(name: &str) -> bool {
 
 fn resolve_terminal(preferred: TerminalEmulator) -> Option<TerminalEmulator> {
     match preferred {
-        TerminalEmulator::Auto => [TerminalEmulator::Kitty, TerminalEmulator::Konsole, TerminalEmulator::Alacritty]
-            .into_iter()
-            .find(|terminal| command_exists(terminal.binary())),
+        TerminalEmulator::Auto => {
+            let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+            desktop_terminals(&desktop)
+                .iter()
+                .chain(TerminalEmulator::all())
+                .copied()
+                .filter(|terminal| *terminal != TerminalEmulator::Auto)
+                .find(|terminal| command_exists(terminal.binary()))
+        }
         _ => {
             if command_exists(preferred.binary()) {
                 Some(preferred)
@@ -285,6 +396,34 @@ fn resolve_terminal(preferred: TerminalEmulator) -> Option<TerminalEmulator> {
     }
 }
 
+// XDG_CURRENT_DESKTOP is a colon-separated list such as "ubuntu:GNOME".
+fn desktop_terminals(desktop: &str) -> &'static [TerminalEmulator] {
+    for name in desktop.split(':') {
+        match name.to_ascii_uppercase().as_str() {
+            "KDE" => return &[TerminalEmulator::Konsole],
+            "GNOME" => {
+                return &[
+                    TerminalEmulator::Ptyxis,
+                    TerminalEmulator::Console,
+                    TerminalEmulator::GnomeTerminal,
+                ]
+            }
+            "XFCE" => return &[TerminalEmulator::Xfce4Terminal],
+            _ => {}
+        }
+    }
+    &[]
+}
+
+fn supported_terminals() -> String {
+    TerminalEmulator::all()
+        .iter()
+        .filter(|terminal| **terminal != TerminalEmulator::Auto)
+        .map(|terminal| terminal.binary())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl TerminalEmulator {
     fn binary(self) -> &'static str {
         match self {
@@ -292,33 +431,90 @@ impl TerminalEmulator {
             TerminalEmulator::Kitty => "kitty",
             TerminalEmulator::Konsole => "konsole",
             TerminalEmulator::Alacritty => "alacritty",
+            TerminalEmulator::GnomeTerminal => "gnome-terminal",
+            TerminalEmulator::Ptyxis => "ptyxis",
+            TerminalEmulator::Xfce4Terminal => "xfce4-terminal",
+            TerminalEmulator::Console => "kgx",
+            TerminalEmulator::Foot => "foot",
+            TerminalEmulator::Xterm => "xterm",
         }
     }
 
+    // The exit code is read from a file once the terminal process returns, so terminals
+    // that hand off to a running server are asked to stay in the foreground.
     fn launch_args(self, script: &str) -> Vec<String> {
-        match self {
-            TerminalEmulator::Kitty => vec![
-                "sh".to_string(),
-                "-lc".to_string(),
-                script.to_string(),
-            ],
-            TerminalEmulator::Konsole => vec![
-                "-e".to_string(),
-                "sh".to_string(),
-                "-lc".to_string(),
-                script.to_string(),
-            ],
-            TerminalEmulator::Alacritty => vec![
-                "-e".to_string(),
-                "sh".to_string(),
-                "-lc".to_string(),
-                script.to_string(),
-            ],
-            TerminalEmulator::Auto => vec![
-                "sh".to_string(),
-                "-lc".to_string(),
-                script.to_string(),
-            ],
+        let prefix: &[&str] = match self {
+            TerminalEmulator::Auto | TerminalEmulator::Kitty | TerminalEmulator::Foot => &[],
+            TerminalEmulator::Konsole | TerminalEmulator::Alacritty | TerminalEmulator::Xterm => {
+                &["-e"]
+            }
+            TerminalEmulator::Console => &["--wait", "--"],
+            TerminalEmulator::GnomeTerminal => &["--wait", "--"],
+            TerminalEmulator::Ptyxis => &["--standalone", "--"],
+            TerminalEmulator::Xfce4Terminal => &["--disable-server", "-x"],
+        };
+        prefix
+            .iter()
+            .map(|arg| arg.to_string())
+            .chain(["sh".to_string(), "-lc".to_string(), script.to_string()])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_terminal_runs_the_script_as_one_shell_argument() {
+        let script = "pacman -Syu\ncode=$?\nprintf '%s\\n' \"$code\" > '/tmp/exit file'";
+        for terminal in TerminalEmulator::all() {
+            let args = terminal.launch_args(script);
+            let tail = &args[args.len() - 3..];
+            assert_eq!(tail, ["sh", "-lc", script], "{}", terminal.label());
+            assert_eq!(args.iter().filter(|arg| *arg == script).count(), 1);
         }
     }
+
+    #[test]
+    fn external_outcomes_distinguish_closed_windows() {
+        assert_eq!(
+            external_outcome(Some("0\n"), 0),
+            ExternalOutcome::Finished(0)
+        );
+        assert_eq!(
+            external_outcome(Some("1\n"), 0),
+            ExternalOutcome::Finished(1)
+        );
+        assert_eq!(
+            external_outcome(Some("started\n"), 0),
+            ExternalOutcome::ClosedEarly
+        );
+        assert_eq!(external_outcome(None, 0), ExternalOutcome::NeverStarted(1));
+        assert_eq!(
+            external_outcome(None, 127),
+            ExternalOutcome::NeverStarted(127)
+        );
+    }
+
+    #[test]
+    fn slow_commands_are_killed_at_the_deadline() {
+        let started = Instant::now();
+        let output = output_with_timeout(
+            Command::new("sh").args(["-c", "sleep 5"]),
+            Duration::from_millis(200),
+        )
+        .unwrap();
+        assert!(output.is_none());
+        assert!(started.elapsed() < Duration::from_secs(3));
+
+        let output = output_with_timeout(
+            Command::new("sh").args(["-c", "echo hello"]),
+            Duration::from_secs(5),
+        )
+        .unwrap()
+        .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"hello\n");
+    }
 }
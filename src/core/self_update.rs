@@ -0,0 +1,70 @@
+use anyhow::{anyhow, Result};
+
+use crate::core::aur_rpc::fetch_info;
+use crate::core::runner::{CommandRunner, CommandSpec};
+use crate::core::version::vercmp;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfUpdate {
+    pub package: String,
+    pub installed: String,
+    pub available: String,
+}
+
+// Returns None when Aurora was not installed from a package (built from source), when the
+// package is a VCS build whose AUR version is meaningless, or when it is already current.
+pub fn check_self_update(runner: &CommandRunner) -> Result<Option<SelfUpdate>> {
+    let exe = std::env::current_exe()?;
+    let owner = CommandSpec::new(
+        "pacman",
+        vec!["-Qqo".to_string(), exe.to_string_lossy().to_string()],
+    );
+    let Ok(package) = runner.run_capture(&owner) else {
+        return Ok(None);
+    };
+    let package = package.trim().to_string();
+    if package.is_empty() || is_vcs_package(&package) {
+        return Ok(None);
+    }
+
+    let query = CommandSpec::new("pacman", vec!["-Q".to_string(), package.clone()]);
+    let installed = runner
+        .run_capture(&query)?
+        .split_whitespace()
+        .nth(1)
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("could not read the installed version of {package}"))?;
+
+    let Some(available) = fetch_info(&package)?.map(|info| info.version) else {
+        return Ok(None);
+    };
+
+    let newer = vercmp(&available, &installed).is_gt();
+    Ok(newer.then_some(SelfUpdate {
+        package,
+        installed,
+        available,
+    }))
+}
+
+fn is_vcs_package(name: &str) -> bool {
+    ["-git", "-hg", "-svn", "-bzr"]
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::aur_rpc::parse_info;
+
+    #[test]
+    fn reads_version_from_aur_rpc_response() {
+        let body = r#"{"resultcount":1,"results":[{"Name":"aurora-gui-bin","Version":"0.2.0-1"}],"type":"multiinfo","version":5}"#;
+        assert_eq!(parse_info(body).unwrap()[0].version, "0.2.0-1");
+        let empty = r#"{"resultcount":0,"results":[],"type":"multiinfo","version":5}"#;
+        assert!(parse_info(empty).unwrap().is_empty());
+        assert!(is_vcs_package("aurora-gui-git"));
+        assert!(!is_vcs_package("aurora-gui-bin"));
+    }
+}
@@ -0,0 +1,40 @@
+const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+
+// pacman prints sizes such as "71.10 MiB" using binary units.
+pub fn parse_size(value: &str) -> Option<u64> {
+    let (number, unit) = value.trim().split_once(' ')?;
+    let number: f64 = number.trim().parse().ok()?;
+    let power = UNITS.iter().position(|candidate| *candidate == unit.trim())?;
+    Some((number * 1024f64.powi(power as i32)).round() as u64)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_formats_pacman_sizes() {
+        assert_eq!(parse_size("71.10 MiB"), Some(74_553_754));
+        assert_eq!(parse_size("512.00 B"), Some(512));
+        assert_eq!(parse_size("1.50 GiB"), Some(1_610_612_736));
+        assert_eq!(parse_size("n/a"), None);
+        assert_eq!(parse_size("3 parsecs"), None);
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(74_553_754), "71.1 MiB");
+        assert_eq!(format_size(1_610_612_736), "1.5 GiB");
+    }
+}
@@ -1,27 +1,142 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::Duration;
 
+use anyhow::{anyhow, Result};
+
+use crate::core::cache::helper_path;
 use crate::core::models::{
-    ActionKind, AurHelperKind, PackageSource, Settings, TerminalMode, TransactionAction,
+    ActionKind, AurHelperKind, EscalationMethod, PackageSource, Settings, TerminalMode, TransactionAction,
     TransactionQueue,
 };
-use crate::core::runner::CommandSpec;
+use crate::core::runner::{command_exists, output_with_timeout, CommandSpec};
+use crate::core::version::vercmp;
 
 #[derive(Debug, Clone)]
 pub struct TransactionPlan {
     pub commands: Vec<CommandSpec>,
+    pub actions: Vec<TransactionAction>,
+}
+
+// What planning needs to know about the AUR helper, looked up without running it so plans
+// can be built on the main loop.
+#[derive(Debug, Clone, Copy)]
+struct AurHelperState {
+    installed: bool,
+    sudo_wiring: bool,
+}
+
+impl AurHelperState {
+    fn current(settings: &Settings) -> Self {
+        Self {
+            installed: command_exists(settings.aur_helper.as_str()),
+            sudo_wiring: known_sudo_wiring(settings.aur_helper),
+        }
+    }
 }
 
 pub fn plan_transactions(queue: &TransactionQueue, settings: &Settings) -> TransactionPlan {
-    let mut commands = Vec::new();
+    plan_with_aur_helper(queue, settings, AurHelperState::current(settings))
+}
+
+// The repo upgrade is only left to the AUR helper when the helper is actually installed,
+// otherwise the whole system upgrade would be lost with it.
+fn plan_with_aur_helper(
+    queue: &TransactionQueue,
+    settings: &Settings,
+    aur_helper: AurHelperState,
+) -> TransactionPlan {
+    let mut commands: Vec<CommandSpec> = Vec::new();
+    let mut sysupgrade_index: Option<usize> = None;
+    let mut upgrade_targets: Vec<String> = Vec::new();
+    let combined_upgrade =
+        aur_helper.installed && !settings.separate_aur_upgrade && aur_upgrade_covers_repo(queue);
+    let repo_sysupgrade = !combined_upgrade
+        && queue
+            .actions
+            .iter()
+            .any(|action| action.source == PackageSource::Repo && action.kind == ActionKind::Upgrade);
     for action in &queue.actions {
-        if let Some(cmd) = command_for_action(action, settings) {
+        if combined_upgrade && action.source == PackageSource::Repo && action.kind == ActionKind::Upgrade {
+            continue;
+        }
+        // Every repo upgrade shares one -Syu, and named targets and repo installs ride along on
+        // it so they are installed in the same transaction as the upgrade. The actions keep
+        // their own kind, so installs can still be undone.
+        if repo_sysupgrade && action.source == PackageSource::Repo && action.kind == ActionKind::Install {
+            if !upgrade_targets.contains(&action.name) {
+                upgrade_targets.push(action.name.clone());
+            }
+            continue;
+        }
+        if action.source == PackageSource::Repo && action.kind == ActionKind::Upgrade {
+            if is_named_repo_upgrade(action) && !upgrade_targets.contains(&action.name) {
+                upgrade_targets.push(action.name.clone());
+            }
+            if sysupgrade_index.is_some() {
+                continue;
+            }
+            sysupgrade_index = Some(commands.len());
+        }
+        if let Some(cmd) = command_with_wiring(action, settings, aur_helper.sudo_wiring) {
             commands.push(cmd);
         }
     }
-    TransactionPlan { commands }
+    if let (Some(index), false) = (sysupgrade_index, upgrade_targets.is_empty()) {
+        let args = &mut commands[index].args;
+        args.push("--needed".to_string());
+        args.extend(upgrade_targets);
+    }
+    TransactionPlan {
+        commands,
+        actions: queue.actions.clone(),
+    }
 }
 
-pub fn command_for_action(action: &TransactionAction, settings: &Settings) -> Option<CommandSpec> {
+pub fn inverse_action(action: &TransactionAction) -> Option<TransactionAction> {
+    let kind = match action.kind {
+        ActionKind::Install => ActionKind::Remove,
+        ActionKind::Remove => ActionKind::Install,
+        ActionKind::Upgrade | ActionKind::Reinstall => return None,
+    };
+    if action.unused_runtimes {
+        return None;
+    }
+    Some(TransactionAction {
+        name: action.name.clone(),
+        source: action.source,
+        kind,
+        origin: action.origin.clone(),
+        delete_data: false,
+        unused_runtimes: false,
+    })
+}
+
+// The AUR helper's -Syu upgrades repo packages too, so a plain system upgrade queued next to
+// it would sync and upgrade twice. Named repo targets still need pacman's own -Syu.
+fn aur_upgrade_covers_repo(queue: &TransactionQueue) -> bool {
+    let mut repo_upgrades = queue
+        .actions
+        .iter()
+        .filter(|action| action.source == PackageSource::Repo && action.kind == ActionKind::Upgrade)
+        .peekable();
+    let has_aur_upgrade = queue
+        .actions
+        .iter()
+        .any(|action| action.source == PackageSource::Aur && action.kind == ActionKind::Upgrade);
+    has_aur_upgrade
+        && !queue.has_repo_installs()
+        && repo_upgrades.peek().is_some()
+        && repo_upgrades.all(|action| !is_named_repo_upgrade(action))
+}
+
+fn is_named_repo_upgrade(action: &TransactionAction) -> bool {
+    !action.name.is_empty() && action.name != "system"
+}
+
+pub fn noconfirm_args(settings: &Settings) -> Vec<String> {
     // Integrated logs run through pipes, so interactive package prompts can block forever.
     // Force non-interactive mode there; user setting still controls external-terminal mode.
     let noninteractive = settings.allow_noconfirm || settings.terminal_mode == TerminalMode::Integrated;
@@ -29,43 +144,82 @@ pub fn command_for_action(action: &TransactionAction, settings: &Settings) -> Op
     if noninteractive {
         noconfirm.push("--noconfirm".to_string());
     }
+    noconfirm
+}
+
+// pkexec and sudo reset the environment, so escalated commands never see the variables.
+pub fn command_for_action(action: &TransactionAction, settings: &Settings) -> Option<CommandSpec> {
+    command_with_wiring(action, settings, known_sudo_wiring(settings.aur_helper))
+}
+
+fn command_with_wiring(
+    action: &TransactionAction,
+    settings: &Settings,
+    sudo_wiring: bool,
+) -> Option<CommandSpec> {
+    let cmd = action_command(action, settings, sudo_wiring)?;
+    Some(if settings.env_all_commands && cmd.program != settings.escalation.as_str() {
+        with_user_env(cmd, settings)
+    } else {
+        cmd
+    })
+}
+
+fn action_command(
+    action: &TransactionAction,
+    settings: &Settings,
+    sudo_wiring: bool,
+) -> Option<CommandSpec> {
+    let noconfirm = noconfirm_args(settings);
     let helper = helper_path();
+    let sudo = settings.escalation.as_str();
 
     match action.source {
         PackageSource::Repo => match action.kind {
-            ActionKind::Install => {
+            ActionKind::Install | ActionKind::Reinstall => {
                 let mut args = vec![helper.clone(), "pacman".to_string(), "-S".to_string()];
                 args.extend(noconfirm.clone());
+                args.extend(needed_args(action, settings));
                 args.push(action.name.clone());
-                Some(CommandSpec::new("pkexec", args))
+                Some(CommandSpec::new(sudo, args))
             }
             ActionKind::Remove => {
                 let mut args = vec![helper.clone(), "pacman".to_string(), "-Rns".to_string()];
                 args.extend(noconfirm.clone());
                 args.push(action.name.clone());
-                Some(CommandSpec::new("pkexec", args))
+                Some(CommandSpec::new(sudo, args))
             }
             ActionKind::Upgrade => {
                 let mut args = vec![helper.clone(), "pacman".to_string(), "-Syu".to_string()];
                 args.extend(noconfirm.clone());
-                Some(CommandSpec::new("pkexec", args))
+                Some(CommandSpec::new(sudo, args))
             }
         },
         PackageSource::Aur => match action.kind {
-            ActionKind::Install => Some(aur_command(settings.aur_helper, "-S", &action.name, &noconfirm, &helper)),
-            ActionKind::Remove => Some(aur_command(settings.aur_helper, "-Rns", &action.name, &noconfirm, &helper)),
-            ActionKind::Upgrade => Some(aur_command(settings.aur_helper, "-Syu", &action.name, &noconfirm, &helper)),
+            ActionKind::Install | ActionKind::Reinstall => {
+                let flags = [noconfirm.clone(), needed_args(action, settings)].concat();
+                Some(aur_command(settings, "-S", &action.name, &flags, &helper, sudo_wiring))
+            }
+            ActionKind::Remove => {
+                Some(aur_command(settings, "-Rns", &action.name, &noconfirm, &helper, sudo_wiring))
+            }
+            ActionKind::Upgrade => {
+                Some(aur_command(settings, "-Syu", &action.name, &noconfirm, &helper, sudo_wiring))
+            }
         },
         PackageSource::Flatpak => match action.kind {
-            ActionKind::Install => {
+            ActionKind::Install | ActionKind::Reinstall => {
                 let mut args = vec!["install".to_string()];
-                if settings.allow_noconfirm {
+                if action.kind == ActionKind::Reinstall {
+                    args.push("--reinstall".to_string());
+                }
+                let origin = action.origin.as_deref().filter(|origin| !origin.is_empty());
+                // Without an origin flatpak may ask which remote to use, which -y cannot answer.
+                if settings.allow_noconfirm && origin.is_some() {
                     args.push("-y".to_string());
                 }
-                if let Some(origin) = &action.origin {
-                    if !origin.is_empty() {
-                        args.push(origin.clone());
-                    }
+                if let Some(origin) = origin {
+                    args.push(origin.to_string());
                 }
                 args.push(action.name.clone());
                 Some(CommandSpec::new("flatpak", args))
@@ -75,6 +229,13 @@ pub fn command_for_action(action: &TransactionAction, settings: &Settings) -> Op
                 if settings.allow_noconfirm {
                     args.push("-y".to_string());
                 }
+                if action.unused_runtimes {
+                    args.push("--unused".to_string());
+                    return Some(CommandSpec::new("flatpak", args));
+                }
+                if action.delete_data {
+                    args.push("--delete-data".to_string());
+                }
                 args.push(action.name.clone());
                 Some(CommandSpec::new("flatpak", args))
             }
@@ -92,32 +253,698 @@ pub fn command_for_action(action: &TransactionAction, settings: &Settings) -> Op
     }
 }
 
-fn aur_command(helper: AurHelperKind, op: &str, pkg: &str, noconfirm: &[String], helper_path: &str) -> CommandSpec {
+// `-S` without `--needed` reinstalls a package that is already current, which is exactly
+// what an explicit reinstall asks for.
+fn needed_args(action: &TransactionAction, settings: &Settings) -> Vec<String> {
+    if settings.skip_up_to_date && action.kind == ActionKind::Install {
+        vec!["--needed".to_string()]
+    } else {
+        Vec::new()
+    }
+}
+
+fn aur_command(
+    settings: &Settings,
+    op: &str,
+    pkg: &str,
+    noconfirm: &[String],
+    helper_path: &str,
+    sudo_wiring: bool,
+) -> CommandSpec {
     let mut args = vec![op.to_string()];
     args.extend(noconfirm.to_vec());
     if op != "-Syu" {
         args.push(pkg.to_string());
     }
 
-    // Best-effort: ask yay/paru to use pkexec + aurora-helper for pacman calls.
-    // This respects the "privileged ops via helper" requirement when supported by the helper.
-    args.push("--sudo".to_string());
-    args.push("pkexec".to_string());
-    args.push("--sudoflags".to_string());
-    args.push(helper_path.to_string());
+    // Ask yay/paru to use pkexec/sudo + aurora-helper for pacman calls. Helpers that do not
+    // know the flags would reject the whole command, so they keep their default sudo.
+    if sudo_wiring {
+        args.extend(sudo_wiring_args(settings.aur_helper, settings.escalation, helper_path));
+    }
 
-    CommandSpec::new(helper.as_str(), args)
+    with_user_env(CommandSpec::new(settings.aur_helper.as_str(), args), settings)
 }
 
-fn helper_path() -> String {
-    if let Ok(exe) = std::env::current_exe() {
-        if let Some(dir) = exe.parent() {
-            let mut candidate = PathBuf::from(dir);
-            candidate.push("aurora-helper");
-            if candidate.exists() {
-                return candidate.to_string_lossy().to_string();
+// Aurora reads command output in the C locale, so the locale stays under its control.
+const RESERVED_ENV: [&str; 4] = ["LC_ALL", "LANG", "LANGUAGE", "LC_MESSAGES"];
+
+pub fn validate_env_name(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_uppercase() || c == '_')
+        && chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+    if !valid {
+        return Err(anyhow!("{name} is not a valid name; use A-Z, 0-9 and _"));
+    }
+    if RESERVED_ENV.contains(&name) {
+        return Err(anyhow!("{name} is set by Aurora and cannot be overridden"));
+    }
+    Ok(())
+}
+
+// AUR helpers escalate on their own through the configured tool, so they count as well.
+pub fn escalates(cmd: &CommandSpec, settings: &Settings) -> bool {
+    cmd.program == settings.escalation.as_str() || cmd.program == settings.aur_helper.as_str()
+}
+
+// Only applied to commands that run as the user; escalated ones would hand the variables to
+// pkexec or sudo, which reset the environment anyway. Names from a hand-edited settings file
+// are checked again here.
+fn with_user_env(mut cmd: CommandSpec, settings: &Settings) -> CommandSpec {
+    for (name, value) in &settings.build_env {
+        let present = cmd.env.iter().any(|(key, _)| key == name);
+        if !present && validate_env_name(name).is_ok() {
+            cmd.env.push((name.clone(), value.clone()));
+        }
+    }
+    cmd
+}
+
+fn sudo_wiring_args(helper: AurHelperKind, escalation: EscalationMethod, helper_path: &str) -> Vec<String> {
+    match helper {
+        AurHelperKind::Yay => vec![
+            "--sudo".to_string(),
+            escalation.as_str().to_string(),
+            "--sudoflags".to_string(),
+            helper_path.to_string(),
+        ],
+        // Keep each value attached so paru cannot read the helper path as a target.
+        AurHelperKind::Paru => vec![
+            format!("--sudo={}", escalation.as_str()),
+            format!("--sudoflags={helper_path}"),
+        ],
+    }
+}
+
+fn sudo_wiring_cell(helper: AurHelperKind) -> &'static OnceLock<bool> {
+    static YAY: OnceLock<bool> = OnceLock::new();
+    static PARU: OnceLock<bool> = OnceLock::new();
+    match helper {
+        AurHelperKind::Yay => &YAY,
+        AurHelperKind::Paru => &PARU,
+    }
+}
+
+// Runs `<helper> --help` once per helper and remembers whether it knows --sudoflags. This
+// blocks, so the GUI calls it from a worker at startup; a missing helper counts as unsupported.
+pub fn probe_sudo_wiring(helper: AurHelperKind) -> bool {
+    *sudo_wiring_cell(helper).get_or_init(|| {
+        output_with_timeout(Command::new(helper.as_str()).arg("--help"), Duration::from_secs(5))
+            .ok()
+            .flatten()
+            .is_some_and(|output| String::from_utf8_lossy(&output.stdout).contains("--sudoflags"))
+    })
+}
+
+// Never runs the helper; until the probe has finished the wiring is left off.
+fn known_sudo_wiring(helper: AurHelperKind) -> bool {
+    sudo_wiring_cell(helper).get().copied().unwrap_or(false)
+}
+
+fn has_sudo_wiring(cmd: &CommandSpec) -> bool {
+    cmd.args
+        .iter()
+        .any(|arg| arg == "--sudoflags" || arg.starts_with("--sudoflags="))
+}
+
+// sudo asks for the password on a tty. Besides sudo itself, that covers AUR helpers when sudo
+// is the configured escalation and when their wiring was left off, since they then fall back
+// to plain sudo.
+pub fn prompts_for_sudo(cmd: &CommandSpec, settings: &Settings) -> bool {
+    if cmd.program == settings.aur_helper.as_str() && !has_sudo_wiring(cmd) {
+        return true;
+    }
+    settings.escalation == EscalationMethod::Sudo && escalates(cmd, settings)
+}
+
+pub fn clean_cache_command(settings: &Settings) -> CommandSpec {
+    CommandSpec::new(
+        settings.escalation.as_str(),
+        vec![
+            helper_path(),
+            "clean-cache".to_string(),
+            settings.cache_keep_versions.clamp(1, 10).to_string(),
+        ],
+    )
+}
+
+pub fn sync_files_db_command(settings: &Settings) -> CommandSpec {
+    CommandSpec::new(
+        settings.escalation.as_str(),
+        vec![helper_path(), "pacman".to_string(), "-Fy".to_string()],
+    )
+}
+
+pub fn refresh_mirrors_command(settings: &Settings) -> CommandSpec {
+    CommandSpec::new(
+        settings.escalation.as_str(),
+        vec![helper_path(), "refresh-mirrors".to_string()],
+    )
+}
+
+pub fn read_pacnew_command(path: &str, escalation: EscalationMethod) -> CommandSpec {
+    let mut args = noninteractive_escalation_args(escalation);
+    args.extend([helper_path(), "read-pacnew".to_string(), path.to_string()]);
+    CommandSpec::new(escalation.as_str(), args)
+}
+
+// Captured commands have no terminal, so sudo must fail instead of waiting for a password.
+// pkexec asks through the desktop's polkit agent.
+pub fn noninteractive_escalation_args(escalation: EscalationMethod) -> Vec<String> {
+    match escalation {
+        EscalationMethod::Pkexec => Vec::new(),
+        EscalationMethod::Sudo => vec!["-n".to_string()],
+    }
+}
+
+pub fn validate_pkgbuild_dir(dir: &Path) -> Result<()> {
+    if !dir.is_dir() {
+        return Err(anyhow!("{} is not a directory", dir.display()));
+    }
+    if !dir.join("PKGBUILD").is_file() {
+        return Err(anyhow!("{} does not contain a PKGBUILD", dir.display()));
+    }
+    // makepkg refuses to run as root; only the final install is escalated.
+    if unsafe { libc::geteuid() } == 0 {
+        return Err(anyhow!("makepkg cannot be run as root"));
+    }
+    Ok(())
+}
+
+pub fn pkgbuild_package_list_command(dir: &str) -> CommandSpec {
+    CommandSpec::new("makepkg", vec!["--packagelist".to_string()]).with_cwd(dir)
+}
+
+pub fn pkgbuild_srcinfo_command(dir: &str) -> CommandSpec {
+    CommandSpec::new("makepkg", vec!["--printsrcinfo".to_string()]).with_cwd(dir)
+}
+
+// Build, make and check dependencies named in `makepkg --printsrcinfo` output, without version
+// constraints and without the packages the PKGBUILD produces itself.
+pub fn srcinfo_dependencies(srcinfo: &str) -> Vec<String> {
+    let arch_suffix = format!("_{}", std::env::consts::ARCH);
+    let mut produced = Vec::new();
+    let mut deps: Vec<String> = Vec::new();
+    for line in srcinfo.lines() {
+        let Some((key, value)) = line.trim().split_once(" = ") else {
+            continue;
+        };
+        if key == "pkgname" {
+            produced.push(value.to_string());
+            continue;
+        }
+        let key = key.strip_suffix(arch_suffix.as_str()).unwrap_or(key);
+        if !matches!(key, "depends" | "makedepends" | "checkdepends") {
+            continue;
+        }
+        let name = value.split(['<', '>', '=']).next().unwrap_or_default().trim();
+        if !name.is_empty() && !deps.iter().any(|dep| dep == name) {
+            deps.push(name.to_string());
+        }
+    }
+    deps.retain(|dep| !produced.contains(dep));
+    deps
+}
+
+// Installs the dependencies through the helper, builds as the current user, then installs
+// the resulting archives through the helper.
+pub fn pkgbuild_commands(
+    dir: &str,
+    packages: &[String],
+    deps: &[String],
+    settings: &Settings,
+) -> Vec<CommandSpec> {
+    let noconfirm = noconfirm_args(settings);
+    let mut commands = Vec::new();
+    if !deps.is_empty() {
+        let mut args = vec![helper_path(), "pacman".to_string(), "-S".to_string()];
+        args.extend(noconfirm.clone());
+        args.extend(["--asdeps".to_string(), "--needed".to_string()]);
+        args.extend(deps.iter().cloned());
+        commands.push(CommandSpec::new(settings.escalation.as_str(), args));
+    }
+    let mut build_args = vec!["--force".to_string(), "--cleanbuild".to_string()];
+    build_args.extend(noconfirm.clone());
+    commands.push(with_user_env(CommandSpec::new("makepkg", build_args).with_cwd(dir), settings));
+    let mut install_args = vec![helper_path(), "pacman".to_string(), "-U".to_string()];
+    install_args.extend(noconfirm);
+    install_args.extend(packages.iter().cloned());
+    commands.push(CommandSpec::new(settings.escalation.as_str(), install_args));
+    commands
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionChange {
+    pub name: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl VersionChange {
+    pub fn describe(&self) -> String {
+        match (&self.before, &self.after) {
+            (Some(before), Some(after)) if vercmp(after, before).is_lt() => {
+                format!("{} {before} \u{2192} {after} (downgraded)", self.name)
             }
+            (Some(before), Some(after)) => format!("{} {before} \u{2192} {after}", self.name),
+            (None, Some(after)) => format!("{} {after} (installed)", self.name),
+            (Some(before), None) => format!("{} {before} (removed)", self.name),
+            (None, None) => self.name.clone(),
+        }
+    }
+}
+
+// Compares two `name -> version` snapshots of the installed packages.
+pub fn version_changes(
+    before: &BTreeMap<String, String>,
+    after: &BTreeMap<String, String>,
+) -> Vec<VersionChange> {
+    let mut changes = Vec::new();
+    for (name, old) in before {
+        match after.get(name) {
+            Some(new) if new == old => {}
+            new => changes.push(VersionChange {
+                name: name.clone(),
+                before: Some(old.clone()),
+                after: new.cloned(),
+            }),
         }
     }
-    "/usr/bin/aurora-helper".to_string()
+    for (name, new) in after {
+        if !before.contains_key(name) {
+            changes.push(VersionChange {
+                name: name.clone(),
+                before: None,
+                after: Some(new.clone()),
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.name.cmp(&b.name));
+    changes
+}
+
+// pkexec exits with 126 when the polkit dialog is dismissed; the wrapped command never ran.
+pub fn is_auth_cancelled(cmd: &CommandSpec, code: i32) -> bool {
+    cmd.program == "pkexec" && code == 126
+}
+
+// 127 means authorization was refused or pkexec itself failed, which is an error, not a cancel.
+pub fn is_auth_failure(cmd: &CommandSpec, code: i32) -> bool {
+    cmd.program == "pkexec" && code == 127
+}
+
+// aurora-helper reports arguments it refuses to pass to pacman as
+// "aurora-helper: validation: flag not allowed: --dbonly".
+pub fn parse_helper_rejection(line: &str) -> Option<&str> {
+    let reason = line.trim().strip_prefix("aurora-helper: validation:")?.trim();
+    (!reason.is_empty()).then_some(reason)
+}
+
+// pacman names each file a mirror could not serve ("failed retrieving file 'x' from mirror :
+// The requested URL returned error: 404") and then gives up on the whole transaction.
+pub fn is_mirror_failure(line: &str) -> bool {
+    line.contains("failed retrieving file")
+        || line.contains("failed to commit transaction (download library error)")
+}
+
+pub fn parse_conflict_path(line: &str) -> Option<String> {
+    // pacman reports file conflicts as "pkg: /some/path exists in filesystem (owned by other)".
+    let (head, _) = line.split_once(" exists in filesystem")?;
+    let start = head.find('/')?;
+    let path = head[start..].trim();
+    if path.is_empty() {
+        return None;
+    }
+    Some(path.to_string())
+}
+
+pub fn with_overwrite(cmd: &CommandSpec, paths: &[String]) -> Option<CommandSpec> {
+    if paths.is_empty() {
+        return None;
+    }
+    let op_index = cmd
+        .args
+        .iter()
+        .position(|arg| matches!(arg.as_str(), "-S" | "-Syu" | "-U"))?;
+    let mut retry = cmd.clone();
+    let flags = paths
+        .iter()
+        .map(|path| format!("--overwrite={}", overwrite_pattern(path)));
+    retry.args.splice(op_index + 1..op_index + 1, flags);
+    Some(retry)
+}
+
+fn overwrite_pattern(path: &str) -> String {
+    let mut pattern = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            '*' | '?' | '[' | ']' | '\\' => {
+                pattern.push('\\');
+                pattern.push(c);
+            }
+            // pacman splits --overwrite values on commas, so match them with a single-char wildcard.
+            ',' => pattern.push('?'),
+            _ => pattern.push(c),
+        }
+    }
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELPER: AurHelperState = AurHelperState {
+        installed: true,
+        sudo_wiring: true,
+    };
+
+    fn action(kind: ActionKind) -> TransactionAction {
+        TransactionAction {
+            name: String::from("org.gnome.Maps"),
+            source: PackageSource::Flatpak,
+            kind,
+            origin: Some(String::from("flathub")),
+            delete_data: false,
+            unused_runtimes: false,
+        }
+    }
+
+    #[test]
+    fn inverse_of_install_is_remove() {
+        let inverse = inverse_action(&action(ActionKind::Install)).unwrap();
+        assert_eq!(inverse.kind, ActionKind::Remove);
+        assert_eq!(inverse.name, "org.gnome.Maps");
+        assert_eq!(inverse.source, PackageSource::Flatpak);
+    }
+
+    #[test]
+    fn inverse_of_remove_is_install_from_same_origin() {
+        let inverse = inverse_action(&action(ActionKind::Remove)).unwrap();
+        assert_eq!(inverse.kind, ActionKind::Install);
+        assert_eq!(inverse.origin.as_deref(), Some("flathub"));
+    }
+
+    #[test]
+    fn upgrades_cannot_be_undone() {
+        assert!(inverse_action(&action(ActionKind::Upgrade)).is_none());
+    }
+
+    #[test]
+    fn pkgbuild_builds_in_place_and_installs_through_helper() {
+        let packages = vec![String::from("/home/me/pkg/foo-1.0-1-x86_64.pkg.tar.zst")];
+        let commands = pkgbuild_commands("/home/me/pkg", &packages, &[], &Settings::default());
+        assert_eq!(commands[0].program, "makepkg");
+        assert_eq!(commands[0].cwd.as_deref(), Some("/home/me/pkg"));
+        assert_eq!(commands[1].program, "pkexec");
+        assert!(commands[1].cwd.is_none());
+        assert_eq!(
+            commands[1].args[1..],
+            ["pacman", "-U", "--noconfirm", "/home/me/pkg/foo-1.0-1-x86_64.pkg.tar.zst"]
+        );
+    }
+
+    #[test]
+    fn pkgbuild_installs_srcinfo_dependencies_first() {
+        let srcinfo = "pkgbase = foo\n\tpkgver = 1.0\n\tmakedepends = cmake>=3.20\n\
+            \tdepends = glibc\n\tdepends = foo-common=1.0\n\tdepends = glibc\n\n\
+            pkgname = foo\n\tdepends = zlib\n\npkgname = foo-common\n";
+        let deps = srcinfo_dependencies(srcinfo);
+        assert_eq!(deps, ["cmake", "glibc", "zlib"]);
+
+        let packages = vec![String::from("/home/me/pkg/foo-1.0-1-x86_64.pkg.tar.zst")];
+        let commands = pkgbuild_commands("/home/me/pkg", &packages, &deps, &Settings::default());
+        assert_eq!(
+            commands[0].args[1..],
+            ["pacman", "-S", "--noconfirm", "--asdeps", "--needed", "cmake", "glibc", "zlib"]
+        );
+        assert_eq!(commands[1].program, "makepkg");
+        assert_eq!(commands.len(), 3);
+    }
+
+    #[test]
+    fn flatpak_reinstall_passes_reinstall_flag() {
+        let cmd = command_for_action(&action(ActionKind::Reinstall), &Settings::default()).unwrap();
+        assert_eq!(cmd.program, "flatpak");
+        assert_eq!(cmd.args, ["install", "--reinstall", "flathub", "org.gnome.Maps"]);
+    }
+
+    #[test]
+    fn build_env_reaches_aur_builds_and_optionally_everything() {
+        let mut settings = Settings::default();
+        settings.build_env.insert("MAKEFLAGS".to_string(), "-j8".to_string());
+        settings.build_env.insert("lc_all".to_string(), "skipped".to_string());
+        let aur = TransactionAction {
+            name: String::from("yay-bin"),
+            source: PackageSource::Aur,
+            kind: ActionKind::Install,
+            origin: None,
+            delete_data: false,
+            unused_runtimes: false,
+        };
+        let has_makeflags = |cmd: &CommandSpec| cmd.env.iter().any(|(k, v)| k == "MAKEFLAGS" && v == "-j8");
+
+        let cmd = command_for_action(&aur, &settings).unwrap();
+        assert!(has_makeflags(&cmd));
+        assert_eq!(cmd.env.len(), 2);
+        let flatpak = command_for_action(&action(ActionKind::Install), &settings).unwrap();
+        assert!(!has_makeflags(&flatpak));
+
+        settings.env_all_commands = true;
+        assert!(has_makeflags(&command_for_action(&action(ActionKind::Install), &settings).unwrap()));
+        assert_eq!(command_for_action(&aur, &settings).unwrap().env.len(), 2);
+        let repo = TransactionAction {
+            source: PackageSource::Repo,
+            ..aur.clone()
+        };
+        assert!(!has_makeflags(&command_for_action(&repo, &settings).unwrap()));
+
+        assert!(validate_env_name("PACKAGER").is_ok());
+        assert!(validate_env_name("_HTTP_PROXY2").is_ok());
+        assert!(validate_env_name("LC_ALL").is_err());
+        assert!(validate_env_name("2FAST").is_err());
+        assert!(validate_env_name("http_proxy").is_err());
+        assert!(validate_env_name("").is_err());
+    }
+
+    #[test]
+    fn recognises_mirror_download_failures() {
+        assert!(is_mirror_failure(
+            "error: failed retrieving file 'firefox-128.0-1-x86_64.pkg.tar.zst' from mirror.example.org : The requested URL returned error: 404"
+        ));
+        assert!(is_mirror_failure(
+            "error: failed to commit transaction (download library error)"
+        ));
+        assert!(!is_mirror_failure("error: failed to commit transaction (conflicting files)"));
+    }
+
+    #[test]
+    fn flatpak_removals_only_delete_data_when_asked() {
+        let mut remove = action(ActionKind::Remove);
+        let cmd = command_for_action(&remove, &Settings::default()).unwrap();
+        assert_eq!(cmd.args, ["uninstall", "org.gnome.Maps"]);
+        remove.delete_data = true;
+        let cmd = command_for_action(&remove, &Settings::default()).unwrap();
+        assert_eq!(cmd.args, ["uninstall", "--delete-data", "org.gnome.Maps"]);
+
+        remove.unused_runtimes = true;
+        let cmd = command_for_action(&remove, &Settings::default()).unwrap();
+        assert_eq!(cmd.args, ["uninstall", "--unused"]);
+        assert!(inverse_action(&remove).is_none());
+    }
+
+    #[test]
+    fn installs_skip_current_packages_unless_reinstalling() {
+        let mut settings = Settings::default();
+        let firefox = |kind| TransactionAction {
+            name: String::from("firefox"),
+            source: PackageSource::Repo,
+            kind,
+            origin: None,
+            delete_data: false,
+            unused_runtimes: false,
+        };
+        let install = command_for_action(&firefox(ActionKind::Install), &settings).unwrap();
+        assert_eq!(install.args[1..], ["pacman", "-S", "--noconfirm", "--needed", "firefox"]);
+        let reinstall = command_for_action(&firefox(ActionKind::Reinstall), &settings).unwrap();
+        assert!(!reinstall.args.contains(&String::from("--needed")));
+
+        settings.skip_up_to_date = false;
+        let install = command_for_action(&firefox(ActionKind::Install), &settings).unwrap();
+        assert!(!install.args.contains(&String::from("--needed")));
+    }
+
+    #[test]
+    fn aur_upgrade_replaces_plain_system_upgrade() {
+        let upgrade = |name: &str, source| TransactionAction {
+            name: name.to_string(),
+            source,
+            kind: ActionKind::Upgrade,
+            origin: None,
+            delete_data: false,
+            unused_runtimes: false,
+        };
+        let mut queue = TransactionQueue::default();
+        queue.push(upgrade("system", PackageSource::Repo));
+        queue.push(upgrade("aur", PackageSource::Aur));
+        queue.push(upgrade("flatpak", PackageSource::Flatpak));
+        let mut settings = Settings::default();
+        let programs = |plan: TransactionPlan| -> Vec<String> {
+            plan.commands.into_iter().map(|cmd| cmd.program).collect()
+        };
+        assert_eq!(programs(plan_with_aur_helper(&queue, &settings, HELPER)), ["yay", "flatpak"]);
+
+        settings.separate_aur_upgrade = true;
+        assert_eq!(
+            programs(plan_with_aur_helper(&queue, &settings, HELPER)),
+            ["pkexec", "yay", "flatpak"]
+        );
+
+        settings.separate_aur_upgrade = false;
+        queue.push(upgrade("firefox", PackageSource::Repo));
+        queue.push(upgrade("mesa", PackageSource::Repo));
+        let plan = plan_with_aur_helper(&queue, &settings, HELPER);
+        assert_eq!(
+            plan.commands[0].args[1..],
+            ["pacman", "-Syu", "--noconfirm", "--needed", "firefox", "mesa"]
+        );
+        assert_eq!(programs(plan), ["pkexec", "yay", "flatpak"]);
+    }
+
+    #[test]
+    fn repo_installs_join_the_system_upgrade() {
+        let action = |name: &str, kind| TransactionAction {
+            name: name.to_string(),
+            source: PackageSource::Repo,
+            kind,
+            origin: None,
+            delete_data: false,
+            unused_runtimes: false,
+        };
+        let mut queue = TransactionQueue::default();
+        queue.push(action("firefox", ActionKind::Install));
+        queue.push(action("system", ActionKind::Upgrade));
+        let plan = plan_with_aur_helper(&queue, &Settings::default(), HELPER);
+        assert_eq!(plan.commands.len(), 1);
+        assert_eq!(
+            plan.commands[0].args[1..],
+            ["pacman", "-Syu", "--noconfirm", "--needed", "firefox"]
+        );
+        let undo = inverse_action(&plan.actions[0]).expect("install can be undone");
+        assert_eq!(undo.kind, ActionKind::Remove);
+    }
+
+    #[test]
+    fn missing_aur_helper_keeps_the_repo_upgrade() {
+        let upgrade = |name: &str, source| TransactionAction {
+            name: name.to_string(),
+            source,
+            kind: ActionKind::Upgrade,
+            origin: None,
+            delete_data: false,
+            unused_runtimes: false,
+        };
+        let mut queue = TransactionQueue::default();
+        queue.push(upgrade("system", PackageSource::Repo));
+        queue.push(upgrade("aur", PackageSource::Aur));
+        let plan = plan_with_aur_helper(
+            &queue,
+            &Settings::default(),
+            AurHelperState {
+                installed: false,
+                sudo_wiring: true,
+            },
+        );
+        let programs: Vec<_> = plan.commands.iter().map(|cmd| cmd.program.as_str()).collect();
+        assert_eq!(programs, ["pkexec", "yay"]);
+    }
+
+    #[test]
+    fn sudo_wiring_matches_each_helper() {
+        let helper = "/usr/lib/aurora/aurora-helper";
+        let mut settings = Settings {
+            escalation: EscalationMethod::Pkexec,
+            terminal_mode: TerminalMode::External,
+            ..Settings::default()
+        };
+
+        let yay = aur_command(&settings, "-S", "yay-bin", &[], helper, true);
+        assert_eq!(yay.program, "yay");
+        assert_eq!(yay.args, ["-S", "yay-bin", "--sudo", "pkexec", "--sudoflags", helper]);
+        assert!(!prompts_for_sudo(&yay, &settings));
+
+        settings.aur_helper = AurHelperKind::Paru;
+        let paru = aur_command(&settings, "-Syu", "", &[], helper, true);
+        assert_eq!(paru.program, "paru");
+        assert_eq!(
+            paru.args,
+            ["-Syu", "--sudo=pkexec", "--sudoflags=/usr/lib/aurora/aurora-helper"]
+        );
+        assert!(!prompts_for_sudo(&paru, &settings));
+
+        // Without the wiring the helper falls back to sudo, even for pkexec users.
+        let unwired = aur_command(&settings, "-S", "paru-bin", &[], helper, false);
+        assert_eq!(unwired.args, ["-S", "paru-bin"]);
+        assert!(prompts_for_sudo(&unwired, &settings));
+
+        settings.escalation = EscalationMethod::Sudo;
+        assert!(prompts_for_sudo(&aur_command(&settings, "-S", "x", &[], helper, true), &settings));
+    }
+
+    #[test]
+    fn only_pkexec_exit_codes_count_as_cancelled_auth() {
+        let pkexec = CommandSpec::new("pkexec", vec![String::from("pacman")]);
+        assert!(is_auth_cancelled(&pkexec, 126));
+        assert!(!is_auth_cancelled(&pkexec, 127));
+        assert!(is_auth_failure(&pkexec, 127));
+        assert!(!is_auth_cancelled(&pkexec, 1));
+        assert!(!is_auth_failure(&pkexec, 1));
+        let flatpak = CommandSpec::new("flatpak", vec![String::from("install")]);
+        assert!(!is_auth_cancelled(&flatpak, 126));
+    }
+
+    #[test]
+    fn helper_rejections_are_told_apart_from_pacman_errors() {
+        assert_eq!(
+            parse_helper_rejection("aurora-helper: validation: flag not allowed: --dbonly"),
+            Some("flag not allowed: --dbonly")
+        );
+        assert_eq!(parse_helper_rejection("error: target not found: nope"), None);
+        assert_eq!(parse_helper_rejection("aurora-helper error: must be run as root"), None);
+    }
+
+    #[test]
+    fn version_changes_cover_upgrades_installs_and_removals() {
+        let snapshot = |entries: &[(&str, &str)]| -> BTreeMap<String, String> {
+            entries
+                .iter()
+                .map(|(name, version)| (name.to_string(), version.to_string()))
+                .collect()
+        };
+        let before = snapshot(&[
+            ("firefox", "1.2"),
+            ("glibc", "2.39"),
+            ("mesa", "1:24.1-1"),
+            ("oldlib", "0.1"),
+        ]);
+        let after = snapshot(&[
+            ("firefox", "1.3"),
+            ("glibc", "2.39"),
+            ("mesa", "1:24.0-1"),
+            ("newlib", "4.0"),
+        ]);
+        let described: Vec<String> = version_changes(&before, &after)
+            .iter()
+            .map(VersionChange::describe)
+            .collect();
+        assert_eq!(
+            described,
+            [
+                "firefox 1.2 \u{2192} 1.3",
+                "mesa 1:24.1-1 \u{2192} 1:24.0-1 (downgraded)",
+                "newlib 4.0 (installed)",
+                "oldlib 0.1 (removed)",
+            ]
+        );
+    }
 }
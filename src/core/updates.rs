@@ -0,0 +1,337 @@
+use std::collections::HashSet;
+
+use crate::core::context::AppContext;
+use crate::core::error::ProviderError;
+use crate::core::models::{ActionKind, PackageSource, TransactionAction};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageUpdate {
+    pub name: String,
+    pub old_version: String,
+    pub new_version: String,
+    pub ignored: bool,
+    // From a helper "(rebuild)" annotation or, for AUR packages, a pkgrel-only bump.
+    pub rebuild: bool,
+    // Any other annotation the helper printed after the versions.
+    pub notes: Vec<String>,
+}
+
+impl PackageUpdate {
+    pub fn describe(&self) -> String {
+        let mut text = format!("{} {} -> {}", self.name, self.old_version, self.new_version);
+        for note in &self.notes {
+            text.push_str(&format!(" [{note}]"));
+        }
+        if self.ignored {
+            text.push_str(" [ignored]");
+        }
+        text
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlatpakUpdate {
+    pub app_id: String,
+    pub version: String,
+    pub branch: String,
+    pub remote: String,
+}
+
+// `pacman -Qu` prints "name old -> new", with " [ignored]" for IgnorePkg entries. Warnings and
+// anything else that does not have that shape are skipped.
+pub fn parse_pacman_updates(output: &str) -> Vec<PackageUpdate> {
+    output.lines().filter_map(parse_update_line).collect()
+}
+
+// `yay -Qua` and `paru -Qua` use the pacman format, optionally followed by helper annotations
+// such as "(rebuild)". A release-only bump is treated as a rebuild as well.
+pub fn parse_aur_updates(output: &str) -> Vec<PackageUpdate> {
+    output
+        .lines()
+        .filter_map(parse_update_line)
+        .map(|mut update| {
+            update.rebuild = update.rebuild || is_pkgrel_bump(&update.old_version, &update.new_version);
+            update
+        })
+        .collect()
+}
+
+fn parse_update_line(line: &str) -> Option<PackageUpdate> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [name, old_version, "->", new_version, annotations @ ..] = fields.as_slice() else {
+        return None;
+    };
+    let mut update = PackageUpdate {
+        name: name.to_string(),
+        old_version: old_version.to_string(),
+        new_version: new_version.to_string(),
+        ignored: false,
+        rebuild: false,
+        notes: Vec::new(),
+    };
+    for annotation in annotations {
+        let note = annotation.trim_matches(|c| matches!(c, '(' | ')' | '[' | ']'));
+        if note.eq_ignore_ascii_case("ignored") {
+            update.ignored = true;
+        } else if note.eq_ignore_ascii_case("rebuild") {
+            update.rebuild = true;
+        } else if !note.is_empty() {
+            update.notes.push(note.to_string());
+        }
+    }
+    Some(update)
+}
+
+// "1.2-1 -> 1.2-2": only the pkgrel changed, which for AUR packages usually means a rebuild.
+pub fn is_pkgrel_bump(old: &str, new: &str) -> bool {
+    match (old.rsplit_once('-'), new.rsplit_once('-')) {
+        (Some((old_ver, old_rel)), Some((new_ver, new_rel))) => {
+            old_ver == new_ver && old_rel != new_rel
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateItem {
+    pub action: TransactionAction,
+    pub display: String,
+    // Set when the update is most likely a rebuild against new libraries rather than new code.
+    pub rebuild: bool,
+}
+
+impl UpdateItem {
+    fn new(action: TransactionAction, display: String) -> Self {
+        Self {
+            action,
+            display,
+            rebuild: false,
+        }
+    }
+}
+
+// Checks every enabled source, also returning the errors of sources that could not be checked.
+pub fn collect_updates_checked(
+    ctx: &AppContext,
+) -> (Vec<UpdateItem>, Vec<ProviderError>) {
+    let mut errors = Vec::new();
+    let mut checked = |result: Result<Vec<UpdateItem>, ProviderError>| {
+        result.unwrap_or_else(|err| {
+            errors.push(err);
+            Vec::new()
+        })
+    };
+    let pacman = checked(ctx.pacman.updates().map(pacman_update_items));
+    let aur = checked(collect_aur_updates(ctx));
+    let flatpak = checked(collect_flatpak_updates(ctx));
+    (merge_updates(pacman, aur, flatpak), errors)
+}
+
+pub fn update_error_note(err: &ProviderError) -> Option<String> {
+    match err {
+        // Flatpak is optional; not having it is not worth a warning.
+        ProviderError::NotInstalled(program) if program == "flatpak" => None,
+        ProviderError::NotInstalled(program) => {
+            Some(format!("{program} is not installed; its updates were skipped"))
+        }
+        other => Some(format!("update check failed: {other}")),
+    }
+}
+
+fn merge_updates(
+    pacman: Vec<UpdateItem>,
+    aur: Vec<UpdateItem>,
+    flatpak: Vec<UpdateItem>,
+) -> Vec<UpdateItem> {
+    // Some helpers include repo packages in -Qua output; the pacman entry wins for those.
+    let repo_names: HashSet<String> = pacman.iter().map(|item| item.action.name.clone()).collect();
+    let mut seen = HashSet::new();
+    let mut items = Vec::new();
+    for item in pacman.into_iter().chain(aur).chain(flatpak) {
+        if item.action.source == PackageSource::Aur && repo_names.contains(&item.action.name) {
+            continue;
+        }
+        if seen.insert((item.action.source, item.action.name.clone())) {
+            items.push(item);
+        }
+    }
+    items
+}
+
+fn pacman_update_items(updates: Vec<PackageUpdate>) -> Vec<UpdateItem> {
+    updates
+        .into_iter()
+        .map(|update| {
+            UpdateItem::new(
+                TransactionAction {
+                    name: update.name.clone(),
+                    source: PackageSource::Repo,
+                    kind: ActionKind::Install,
+                    origin: None,
+                    delete_data: false,
+                    unused_runtimes: false,
+                },
+                update.describe(),
+            )
+        })
+        .collect()
+}
+
+fn collect_aur_updates(ctx: &AppContext) -> Result<Vec<UpdateItem>, ProviderError> {
+    let skip = ctx
+        .settings
+        .lock()
+        .map(|settings| settings.offline || !settings.enable_aur)
+        .unwrap_or(false);
+    if skip {
+        return Ok(Vec::new());
+    }
+    Ok(aur_update_items(ctx.aur.updates()?))
+}
+
+fn aur_update_items(updates: Vec<PackageUpdate>) -> Vec<UpdateItem> {
+    updates
+        .into_iter()
+        .map(|update| UpdateItem {
+            action: TransactionAction {
+                name: update.name.clone(),
+                source: PackageSource::Aur,
+                kind: ActionKind::Install,
+                origin: None,
+                delete_data: false,
+                unused_runtimes: false,
+            },
+            display: format!("{} (AUR)", update.describe()),
+            rebuild: update.rebuild,
+        })
+        .collect()
+}
+
+fn collect_flatpak_updates(ctx: &AppContext) -> Result<Vec<UpdateItem>, ProviderError> {
+    if !ctx.settings.lock().map(|settings| settings.enable_flatpak).unwrap_or(true) {
+        return Ok(Vec::new());
+    }
+    Ok(ctx
+        .flatpak
+        .updates()?
+        .into_iter()
+        .map(|update| {
+            let mut display = update.app_id.clone();
+            if !update.version.is_empty() {
+                display.push_str(&format!(" {}", update.version));
+            } else if !update.branch.is_empty() {
+                display.push_str(&format!(" {}", update.branch));
+            }
+            if !update.remote.is_empty() {
+                display.push_str(&format!(" ({})", update.remote));
+            }
+            display.push_str(" [Flatpak]");
+            UpdateItem::new(
+                TransactionAction {
+                    name: update.app_id,
+                    source: PackageSource::Flatpak,
+                    kind: ActionKind::Upgrade,
+                    origin: None,
+                    delete_data: false,
+                    unused_runtimes: false,
+                },
+                display,
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pacman_updates() {
+        let updates = parse_pacman_updates(
+            "linux 6.9.1.arch1-1 -> 6.9.2.arch1-1\n\
+             warning: database file for 'testing' does not exist\n\
+             \n\
+             firefox 126.0-1 -> 126.0.1-1 [ignored]\n",
+        );
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].name, "linux");
+        assert_eq!(updates[0].old_version, "6.9.1.arch1-1");
+        assert_eq!(updates[0].new_version, "6.9.2.arch1-1");
+        assert!(!updates[0].ignored);
+        assert!(updates[1].ignored);
+        assert_eq!(updates[1].describe(), "firefox 126.0-1 -> 126.0.1-1 [ignored]");
+    }
+
+    #[test]
+    fn keeps_epochs_in_versions() {
+        let updates = parse_pacman_updates("python-pip 1:23.3-1 -> 1:24.0-1\n");
+        assert_eq!(updates[0].old_version, "1:23.3-1");
+        assert_eq!(updates[0].new_version, "1:24.0-1");
+        assert!(is_pkgrel_bump("1:2.3-1", "1:2.3-2"));
+        assert!(!is_pkgrel_bump("1:2.3-1", "2:2.3-2"));
+    }
+
+    #[test]
+    fn aur_annotations_become_flags() {
+        let updates = parse_aur_updates(
+            "libfoo-git 1.0-1 -> 1.1-1 (rebuild)\n\
+             zoom 6.0.0-1 -> 6.0.0-2\n\
+             spotify 1.2-1 -> 1.3-1 (orphaned)\n\
+             not an update line\n",
+        );
+        assert_eq!(updates.len(), 3);
+        assert!(updates[0].rebuild);
+        assert!(updates[1].rebuild);
+        assert!(!updates[2].rebuild);
+        assert_eq!(updates[2].notes, ["orphaned"]);
+    }
+
+    const PACMAN_QU: &str = "linux 6.9.1.arch1-1 -> 6.9.2.arch1-1\n\
+        firefox 126.0-1 -> 126.0.1-1\n";
+    // yay -Qua with a helper that also reports repo packages.
+    const AUR_QUA: &str = "firefox 126.0-1 -> 126.0.1-1\n\
+        visual-studio-code-bin 1.89.0-1 -> 1.89.1-1\n\
+        visual-studio-code-bin 1.89.0-1 -> 1.89.1-1\n";
+
+    fn names(items: &[UpdateItem], source: PackageSource) -> Vec<&str> {
+        items
+            .iter()
+            .filter(|item| item.action.source == source)
+            .map(|item| item.action.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn merge_prefers_pacman_entries_over_helper_duplicates() {
+        let items = merge_updates(
+            pacman_update_items(parse_pacman_updates(PACMAN_QU)),
+            aur_update_items(parse_aur_updates(AUR_QUA)),
+            Vec::new(),
+        );
+        assert_eq!(names(&items, PackageSource::Repo), vec!["linux", "firefox"]);
+        assert_eq!(names(&items, PackageSource::Aur), vec!["visual-studio-code-bin"]);
+        assert_eq!(items.len(), 3);
+    }
+
+    #[test]
+    fn merge_keeps_same_name_from_flatpak() {
+        let flatpak = vec![UpdateItem::new(
+            TransactionAction {
+                name: String::from("firefox"),
+                source: PackageSource::Flatpak,
+                kind: ActionKind::Upgrade,
+                origin: None,
+                delete_data: false,
+                unused_runtimes: false,
+            },
+            String::from("firefox [Flatpak]"),
+        )];
+        let items = merge_updates(
+            pacman_update_items(parse_pacman_updates(PACMAN_QU)),
+            Vec::new(),
+            flatpak,
+        );
+        assert_eq!(names(&items, PackageSource::Flatpak), vec!["firefox"]);
+        assert_eq!(items.len(), 3);
+    }
+}
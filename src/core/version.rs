@@ -0,0 +1,137 @@
+use std::cmp::Ordering;
+
+// Same ordering as pacman's `vercmp` for "[epoch:]version[-pkgrel]". The pkgrel only counts
+// when both sides have one, so "1.0" and "1.0-3" compare equal.
+pub fn vercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let (a_epoch, a_version, a_rel) = split_version(a);
+    let (b_epoch, b_version, b_rel) = split_version(b);
+    compare_segments(a_epoch, b_epoch)
+        .then_with(|| compare_segments(a_version, b_version))
+        .then_with(|| match (a_rel, b_rel) {
+            (Some(a_rel), Some(b_rel)) => compare_segments(a_rel, b_rel),
+            _ => Ordering::Equal,
+        })
+}
+
+fn split_version(full: &str) -> (&str, &str, Option<&str>) {
+    let (epoch, rest) = match full.split_once(':') {
+        Some((epoch, rest)) if epoch.bytes().all(|c| c.is_ascii_digit()) => {
+            (if epoch.is_empty() { "0" } else { epoch }, rest)
+        }
+        _ => ("0", full),
+    };
+    match rest.rsplit_once('-') {
+        Some((version, rel)) => (epoch, version, Some(rel)),
+        None => (epoch, rest, None),
+    }
+}
+
+// rpmvercmp as used by libalpm: runs of digits and runs of letters are compared in turn,
+// numbers numerically, and a numeric run is always newer than an alphabetic one.
+fn compare_segments(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let (sep_a, sep_b) = (i, j);
+        while i < a.len() && !a[i].is_ascii_alphanumeric() {
+            i += 1;
+        }
+        while j < b.len() && !b[j].is_ascii_alphanumeric() {
+            j += 1;
+        }
+        if i == a.len() || j == b.len() {
+            break;
+        }
+        if i - sep_a != j - sep_b {
+            return (i - sep_a).cmp(&(j - sep_b));
+        }
+
+        let numeric = a[i].is_ascii_digit();
+        let run = |s: &[u8], start: usize| {
+            let len = s[start..]
+                .iter()
+                .take_while(|c| {
+                    if numeric {
+                        c.is_ascii_digit()
+                    } else {
+                        c.is_ascii_alphabetic()
+                    }
+                })
+                .count();
+            start + len
+        };
+        let (end_a, end_b) = (run(a, i), run(b, j));
+        if end_b == j {
+            return if numeric { Ordering::Greater } else { Ordering::Less };
+        }
+        let order = if numeric {
+            let trim = |s: &[u8]| {
+                let zeros = s.iter().take_while(|c| **c == b'0').count();
+                s[zeros..].to_vec()
+            };
+            let (run_a, run_b) = (trim(&a[i..end_a]), trim(&b[j..end_b]));
+            run_a.len().cmp(&run_b.len()).then_with(|| run_a.cmp(&run_b))
+        } else {
+            a[i..end_a].cmp(&b[j..end_b])
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+        i = end_a;
+        j = end_b;
+    }
+
+    let (rest_a, rest_b) = (&a[i..], &b[j..]);
+    if rest_a.is_empty() && rest_b.is_empty() {
+        return Ordering::Equal;
+    }
+    // A leftover letter run ("1.0rc1" against "1.0") marks a pre-release.
+    let older = match rest_a.first() {
+        None => !rest_b[0].is_ascii_alphabetic(),
+        Some(c) => c.is_ascii_alphabetic(),
+    };
+    if older {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Ordering::{Equal, Greater, Less};
+
+    #[test]
+    fn epochs_win_over_versions() {
+        assert_eq!(vercmp("1:1.0", "2.0"), Greater);
+        assert_eq!(vercmp("1:23.3-1", "1:24.0-1"), Less);
+        assert_eq!(vercmp("0:1.0", "1.0"), Equal);
+    }
+
+    #[test]
+    fn pkgrel_only_counts_when_both_sides_have_one() {
+        assert_eq!(vercmp("1.0-2", "1.0-1"), Greater);
+        assert_eq!(vercmp("1.0-1.1", "1.0-1"), Greater);
+        assert_eq!(vercmp("1.0", "1.0-3"), Equal);
+    }
+
+    #[test]
+    fn compares_numeric_and_alpha_segments_like_vercmp() {
+        assert_eq!(vercmp("1.10", "1.9"), Greater);
+        assert_eq!(vercmp("1.0.1", "1.0"), Greater);
+        assert_eq!(vercmp("1.001", "1.1"), Equal);
+        assert_eq!(vercmp("1.0rc1", "1.0"), Less);
+        assert_eq!(vercmp("1.0alpha", "1.0beta"), Less);
+        assert_eq!(vercmp("1.0.a", "1.0.1"), Less);
+        assert_eq!(vercmp("1.0a", "1.0.1"), Less);
+        assert_eq!(vercmp("6.9.2.arch1", "6.9.1.arch1"), Greater);
+        assert_eq!(vercmp("1..0", "1.0"), Greater);
+    }
+}
@@ -1,8 +1,14 @@
 mod app;
+mod cli;
 mod core;
 mod ui;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
     let app = app::AuroraApp::new();
     app.run();
 }
@@ -3,14 +3,25 @@ use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::mpsc;
 
+use gtk::gdk;
 use gtk::gio;
 use gtk::prelude::*;
 use libadwaita as adw;
 use adw::prelude::*;
 
 use crate::core::appstream::AppStreamClient;
-use crate::core::models::{PackageDetails, PackageSource, PackageSummary};
+use crate::core::aur_rpc::fetch_info;
+use crate::core::cache::favorite_keys;
+use crate::core::distrobox;
+use crate::core::error::ProviderError;
+use crate::core::models::{
+    ActionKind, AurMetadata, PackageDetails, PackageSource, PackageSummary, TransactionAction,
+};
+use crate::core::providers::flatpak::is_broad_permission;
+use crate::core::transactions::command_for_action;
 use crate::ui::{AppContext, UiHandles};
+use crate::ui::widgets::accessible::set_icon_label;
+use crate::ui::widgets::card::{favorite_button, load_package_icon};
 use crate::ui::widgets::screenshot_carousel::ScreenshotCarousel;
 
 pub fn show_details(ctx: &AppContext, handles: &UiHandles, summary: PackageSummary) {
@@ -29,7 +40,7 @@ pub fn show_details(ctx: &AppContext, handles: &UiHandles, summary: PackageSumma
     let back_btn = gtk::Button::from_icon_name("go-previous-symbolic");
     back_btn.add_css_class("flat");
     back_btn.set_halign(gtk::Align::Start);
-    back_btn.set_tooltip_text(Some("Back"));
+    set_icon_label(&back_btn, "Back");
 
     let header = gtk::Box::new(gtk::Orientation::Horizontal, 12);
     let icon = gtk::Image::from_icon_name("application-x-executable");
@@ -52,6 +63,11 @@ pub fn show_details(ctx: &AppContext, handles: &UiHandles, summary: PackageSumma
     }));
     source_badge.add_css_class("pill");
     badges.append(&source_badge);
+    let out_of_date_badge = gtk::Label::new(Some("Out of date"));
+    out_of_date_badge.add_css_class("pill");
+    out_of_date_badge.add_css_class("warning");
+    out_of_date_badge.set_visible(false);
+    badges.append(&out_of_date_badge);
 
     text_col.append(&name);
     text_col.append(&summary_label);
@@ -66,13 +82,33 @@ pub fn show_details(ctx: &AppContext, handles: &UiHandles, summary: PackageSumma
     action_btn.add_css_class("suggested-action");
     let update_btn = gtk::Button::with_label("Update");
     update_btn.set_visible(summary.installed);
+    let reinstall_btn = gtk::Button::with_label("Reinstall");
+    reinstall_btn.set_tooltip_text(Some("Reinstall the installed version"));
+    reinstall_btn.set_visible(summary.installed);
+    let distrobox_btn = gtk::Button::with_label("Try in Distrobox");
+    distrobox_btn.set_tooltip_text(Some("Install inside a container instead of the host"));
+    distrobox_btn.set_visible(summary.source == PackageSource::Aur && distrobox::is_available());
+    let pkgbuild_btn = gtk::Button::with_label("Review PKGBUILD");
+    pkgbuild_btn.set_tooltip_text(Some("Read the build script before installing"));
+    pkgbuild_btn.set_visible(
+        summary.source == PackageSource::Aur && !ctx.settings.lock().unwrap().offline,
+    );
     let open_home_btn = gtk::Button::with_label("Open Homepage");
     open_home_btn.set_visible(false);
     let logs_btn = gtk::Button::with_label("View Logs");
+    let copy_cmd_btn = gtk::Button::from_icon_name("edit-copy-symbolic");
+    set_icon_label(&copy_cmd_btn, "Copy command");
+    copy_cmd_btn.add_css_class("flat");
     button_row.append(&action_btn);
     button_row.append(&update_btn);
+    button_row.append(&reinstall_btn);
+    button_row.append(&distrobox_btn);
+    button_row.append(&pkgbuild_btn);
     button_row.append(&open_home_btn);
     button_row.append(&logs_btn);
+    button_row.append(&copy_cmd_btn);
+    let starred = favorite_keys().contains(&(summary.name.clone(), summary.source));
+    button_row.append(&favorite_button(&summary, starred));
 
     let carousel = ScreenshotCarousel::new();
 
@@ -87,10 +123,28 @@ pub fn show_details(ctx: &AppContext, handles: &UiHandles, summary: PackageSumma
     homepage.set_xalign(0.0);
     homepage.set_selectable(true);
     homepage.set_wrap(true);
+    let aur_info = gtk::Label::new(None);
+    aur_info.set_xalign(0.0);
+    aur_info.set_wrap(true);
+    aur_info.set_visible(false);
     details.append(&version);
     details.append(&installed);
     details.append(&size);
     details.append(&homepage);
+    details.append(&aur_info);
+
+    let load_error = gtk::Label::new(None);
+    load_error.set_xalign(0.0);
+    load_error.set_wrap(true);
+    load_error.add_css_class("warning");
+    load_error.set_visible(false);
+    details.append(&load_error);
+
+    let permissions_box = gtk::Box::new(gtk::Orientation::Vertical, 2);
+    permissions_box.set_visible(false);
+
+    let files_expander = files_expander(ctx, &summary.name, installed_state.clone());
+    files_expander.set_visible(has_file_list(summary.source, summary.installed));
 
     let description = gtk::Label::new(Some(""));
     description.set_xalign(0.0);
@@ -101,6 +155,8 @@ pub fn show_details(ctx: &AppContext, handles: &UiHandles, summary: PackageSumma
     root.append(&button_row);
     root.append(carousel.widget());
     root.append(&details);
+    root.append(&permissions_box);
+    root.append(&files_expander);
     root.append(&description);
 
     page.set_child(Some(&root));
@@ -120,14 +176,22 @@ pub fn show_details(ctx: &AppContext, handles: &UiHandles, summary: PackageSumma
     let size_clone = size.clone();
     let description_clone = description.clone();
     let carousel_clone = carousel.clone();
+    let aur_info_clone = aur_info.clone();
+    let load_error_clone = load_error.clone();
+    let permissions_box_clone = permissions_box.clone();
+    let files_expander_clone = files_expander.clone();
+    let out_of_date_badge_clone = out_of_date_badge.clone();
     let action_btn_clone = action_btn.clone();
     let update_btn_clone = update_btn.clone();
+    let reinstall_btn_clone = reinstall_btn.clone();
     let open_home_btn_clone = open_home_btn.clone();
     let homepage_clone = homepage.clone();
     let installed_state_clone = installed_state.clone();
     let home_url = Rc::new(RefCell::new(None::<String>));
     let home_url_clone = home_url.clone();
     let appstream = ctx.appstream.clone();
+    let summary_for_icon = summary.clone();
+    let offline = ctx.settings.lock().unwrap().offline;
 
     let (tx, rx) = mpsc::channel();
     std::thread::spawn(move || {
@@ -137,9 +201,19 @@ pub fn show_details(ctx: &AppContext, handles: &UiHandles, summary: PackageSumma
 
     glib::idle_add_local(move || {
         match rx.try_recv() {
-            Ok(details) => {
+            Ok((details, error)) => {
+                if let Some(message) = error {
+                    load_error_clone.set_text(&message);
+                    load_error_clone.set_visible(true);
+                }
                 if let Some(icon_name) = &details.icon_name {
                     icon_clone.set_icon_name(Some(icon_name));
+                } else {
+                    let lookup = PackageSummary {
+                        installed: details.installed,
+                        ..summary_for_icon.clone()
+                    };
+                    load_package_icon(&icon_clone, &lookup);
                 }
                 summary_label_clone.set_text(&details.summary);
                 version_clone.set_text(&format!("Version: {}", details.version));
@@ -150,6 +224,7 @@ pub fn show_details(ctx: &AppContext, handles: &UiHandles, summary: PackageSumma
                 *installed_state_clone.borrow_mut() = details.installed;
                 action_btn_clone.set_label(if details.installed { "Remove" } else { "Install" });
                 update_btn_clone.set_visible(details.installed);
+                reinstall_btn_clone.set_visible(details.installed);
                 if let Some(size) = &details.size {
                     size_clone.set_text(&format!("Size: {size}"));
                 }
@@ -163,7 +238,28 @@ pub fn show_details(ctx: &AppContext, handles: &UiHandles, summary: PackageSumma
                     open_home_btn_clone.set_visible(false);
                 }
                 description_clone.set_text(&details.description);
-                carousel_clone.set_screenshots(details.screenshots.clone());
+                if details.source == PackageSource::Aur {
+                    let meta = &details.aur;
+                    let mut info = format!(
+                        "Votes: {}    Popularity: {}    Maintainer: {}",
+                        meta.votes.map_or("-".to_string(), |v| v.to_string()),
+                        meta.popularity.map_or("-".to_string(), |p| format!("{p:.2}")),
+                        meta.maintainer.as_deref().unwrap_or("orphaned"),
+                    );
+                    if let Some(modified) = &meta.last_modified {
+                        info.push_str(&format!("    Last Updated: {modified}"));
+                    }
+                    aur_info_clone.set_text(&info);
+                    aur_info_clone.set_visible(true);
+                    if let Some(since) = &meta.out_of_date {
+                        out_of_date_badge_clone
+                            .set_tooltip_text(Some(&format!("Flagged out of date: {since}")));
+                        out_of_date_badge_clone.set_visible(true);
+                    }
+                }
+                render_permissions(&permissions_box_clone, &details.permissions);
+                files_expander_clone.set_visible(has_file_list(details.source, details.installed));
+                carousel_clone.set_screenshots(details.screenshots.clone(), offline);
                 glib::ControlFlow::Break
             }
             Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
@@ -175,9 +271,9 @@ pub fn show_details(ctx: &AppContext, handles: &UiHandles, summary: PackageSumma
     let pkg_name = summary.name.clone();
     let pkg_source = summary.source;
     let pkg_origin = summary.origin.clone();
-    let installed_state = installed_state.clone();
+    let installed_for_action = installed_state.clone();
     action_btn.connect_clicked(move |_| {
-        if *installed_state.borrow() {
+        if *installed_for_action.borrow() {
             queue.add_remove(pkg_name.clone(), pkg_source);
         } else {
             queue.add_install(pkg_name.clone(), pkg_source, pkg_origin.clone());
@@ -192,6 +288,87 @@ pub fn show_details(ctx: &AppContext, handles: &UiHandles, summary: PackageSumma
         queue.add_install(pkg_name.clone(), pkg_source, pkg_origin.clone());
     });
 
+    let queue = handles.queue.clone();
+    let pkg_name = summary.name.clone();
+    let pkg_source = summary.source;
+    let pkg_origin = summary.origin.clone();
+    reinstall_btn.connect_clicked(move |_| {
+        queue.add_reinstall(pkg_name.clone(), pkg_source, pkg_origin.clone());
+    });
+
+    let ctx_distrobox = ctx.clone();
+    let handles_distrobox = handles.clone();
+    let pkg_name = summary.name.clone();
+    distrobox_btn.connect_clicked(move |button| {
+        button.set_sensitive(false);
+        let (tx, rx) = mpsc::channel();
+        let runner = ctx_distrobox.runner.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(distrobox::list_containers(&runner).map_err(|err| err.to_string()));
+        });
+
+        let button = button.clone();
+        let ctx = ctx_distrobox.clone();
+        let handles = handles_distrobox.clone();
+        let pkg_name = pkg_name.clone();
+        glib::idle_add_local(move || match rx.try_recv() {
+            Ok(result) => {
+                button.set_sensitive(true);
+                match result {
+                    Ok(containers) if containers.is_empty() => handles
+                        .toasts
+                        .add_toast(adw::Toast::new("No distrobox containers found")),
+                    Ok(containers) => {
+                        choose_container(&button, &ctx, &handles, &pkg_name, containers)
+                    }
+                    Err(err) => handles.toasts.add_toast(adw::Toast::new(&format!(
+                        "Could not list distrobox containers: {err}"
+                    ))),
+                }
+                glib::ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                button.set_sensitive(true);
+                glib::ControlFlow::Break
+            }
+        });
+    });
+
+    let ctx_pkgbuild = ctx.clone();
+    let handles_pkgbuild = handles.clone();
+    let pkg_name = summary.name.clone();
+    pkgbuild_btn.connect_clicked(move |button| {
+        button.set_sensitive(false);
+        let (tx, rx) = mpsc::channel();
+        let aur = ctx_pkgbuild.aur.clone();
+        let name = pkg_name.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(aur.pkgbuild(&name).map_err(|err| err.to_string()));
+        });
+
+        let button = button.clone();
+        let handles = handles_pkgbuild.clone();
+        let pkg_name = pkg_name.clone();
+        glib::idle_add_local(move || match rx.try_recv() {
+            Ok(result) => {
+                button.set_sensitive(true);
+                match result {
+                    Ok(pkgbuild) => show_pkgbuild_window(&button, &handles, &pkg_name, &pkgbuild),
+                    Err(err) => handles.toasts.add_toast(adw::Toast::new(&format!(
+                        "Could not download the PKGBUILD: {err}"
+                    ))),
+                }
+                glib::ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                button.set_sensitive(true);
+                glib::ControlFlow::Break
+            }
+        });
+    });
+
     let home_url = home_url.clone();
     let toasts = handles.toasts.clone();
     open_home_btn.connect_clicked(move |_| {
@@ -202,6 +379,36 @@ pub fn show_details(ctx: &AppContext, handles: &UiHandles, summary: PackageSumma
         }
     });
 
+    let ctx_copy = ctx.clone();
+    let installed_for_copy = installed_state.clone();
+    let toasts = handles.toasts.clone();
+    let pkg_name = summary.name.clone();
+    let pkg_source = summary.source;
+    let pkg_origin = summary.origin.clone();
+    copy_cmd_btn.connect_clicked(move |_| {
+        let action = TransactionAction {
+            name: pkg_name.clone(),
+            source: pkg_source,
+            kind: if *installed_for_copy.borrow() {
+                ActionKind::Remove
+            } else {
+                ActionKind::Install
+            },
+            origin: pkg_origin.clone(),
+            delete_data: false,
+            unused_runtimes: false,
+        };
+        let settings = ctx_copy.settings.lock().unwrap().clone();
+        let Some(cmd) = command_for_action(&action, &settings) else {
+            toasts.add_toast(adw::Toast::new("No command available for this package"));
+            return;
+        };
+        if let Some(display) = gdk::Display::default() {
+            display.clipboard().set_text(&cmd.display_line());
+            toasts.add_toast(adw::Toast::new("Command copied to clipboard"));
+        }
+    });
+
     let drawer = handles.log_drawer.clone();
     logs_btn.connect_clicked(move |_| {
         let visible = drawer.is_visible();
@@ -209,36 +416,134 @@ pub fn show_details(ctx: &AppContext, handles: &UiHandles, summary: PackageSumma
     });
 }
 
-fn load_details(ctx: AppContext, summary: PackageSummary, appstream: Arc<AppStreamClient>) -> PackageDetails {
-    let mut details = match summary.source {
+fn show_pkgbuild_window(source: &gtk::Button, handles: &UiHandles, name: &str, pkgbuild: &str) {
+    let buffer = gtk::TextBuffer::new(None);
+    buffer.set_text(pkgbuild);
+    let view = gtk::TextView::with_buffer(&buffer);
+    view.set_editable(false);
+    view.set_monospace(true);
+    view.set_left_margin(8);
+    view.set_top_margin(8);
+    let scroller = gtk::ScrolledWindow::new();
+    scroller.set_vexpand(true);
+    scroller.set_child(Some(&view));
+
+    let window = adw::Window::new();
+    let proceed = gtk::Button::with_label("Proceed to Install");
+    proceed.add_css_class("suggested-action");
+    let queue = handles.queue.clone();
+    let pkg_name = name.to_string();
+    let window_for_proceed = window.clone();
+    proceed.connect_clicked(move |_| {
+        queue.add_install(pkg_name.clone(), PackageSource::Aur, None);
+        window_for_proceed.close();
+    });
+    let header = adw::HeaderBar::new();
+    header.pack_end(&proceed);
+
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    content.append(&header);
+    content.append(&scroller);
+
+    window.set_title(Some(&format!("PKGBUILD for {name}")));
+    window.set_modal(true);
+    window.set_default_size(900, 600);
+    window.set_content(Some(&content));
+    if let Some(parent) = source.root().and_downcast::<gtk::Window>() {
+        window.set_transient_for(Some(&parent));
+    }
+    window.present();
+}
+
+fn choose_container(
+    source: &gtk::Button,
+    ctx: &AppContext,
+    handles: &UiHandles,
+    package: &str,
+    containers: Vec<String>,
+) {
+    let names: Vec<&str> = containers.iter().map(String::as_str).collect();
+    let picker = gtk::DropDown::from_strings(&names);
+    let dialog = adw::MessageDialog::new(
+        source.root().and_downcast::<gtk::Window>().as_ref(),
+        Some("Try in Distrobox"),
+        Some(&format!(
+            "Install {package} inside a container. The host system is not touched."
+        )),
+    );
+    dialog.set_extra_child(Some(&picker));
+    dialog.add_response("cancel", "Cancel");
+    dialog.add_response("install", "Install");
+    dialog.set_response_appearance("install", adw::ResponseAppearance::Suggested);
+
+    let ctx = ctx.clone();
+    let queue = handles.queue.clone();
+    let package = package.to_string();
+    dialog.connect_response(None, move |dialog, response| {
+        if response == "install" {
+            if let Some(container) = containers.get(picker.selected() as usize) {
+                let settings = ctx.settings.lock().unwrap().clone();
+                let command = distrobox::install_command(container, &package, &settings);
+                queue.run_commands(vec![command]);
+            }
+        }
+        dialog.close();
+    });
+    dialog.present();
+}
+
+// The error is returned alongside fallback details so the page can say why fields are missing.
+fn load_details(
+    ctx: AppContext,
+    summary: PackageSummary,
+    appstream: Arc<AppStreamClient>,
+) -> (PackageDetails, Option<String>) {
+    let offline = ctx.settings.lock().unwrap().offline;
+    let loaded = match summary.source {
         PackageSource::Repo => {
             if summary.installed {
-                ctx.pacman.info_installed(&summary.name).unwrap_or_else(|_| fallback_details(&summary))
+                ctx.pacman.info_installed(&summary.name)
             } else {
-                ctx.pacman.info_repo(&summary.name).unwrap_or_else(|_| fallback_details(&summary))
+                ctx.pacman.info_repo(&summary.name)
             }
         }
+        // The RPC gives structured metadata; the helper's text output is the fallback when
+        // the request fails.
         PackageSource::Aur => {
+            let rpc = if offline {
+                None
+            } else {
+                fetch_info(&summary.name).ok().flatten()
+            };
             if summary.installed {
                 ctx.pacman
                     .info_installed(&summary.name)
                     .map(|mut details| {
                         details.source = PackageSource::Aur;
                         details.installed = true;
+                        if let Some(info) = &rpc {
+                            details.aur = info.metadata();
+                        }
                         details
                     })
-                    .or_else(|_| ctx.aur.info(&summary.name))
-                    .unwrap_or_else(|_| fallback_details(&summary))
+                    .or_else(|err| match rpc {
+                        Some(info) => Ok(info.into_details()),
+                        None if offline => Err(err),
+                        None => ctx.aur.info(&summary.name),
+                    })
+            } else if let Some(info) = rpc {
+                Ok(info.into_details())
+            } else if offline {
+                Ok(fallback_details(&summary))
             } else {
-                ctx.aur
-                    .info(&summary.name)
-                    .unwrap_or_else(|_| fallback_details(&summary))
+                ctx.aur.info(&summary.name)
             }
         }
-        PackageSource::Flatpak => ctx
-            .flatpak
-            .info(&summary.name)
-            .unwrap_or_else(|_| fallback_details(&summary)),
+        PackageSource::Flatpak => ctx.flatpak.info(&summary.name),
+    };
+    let (mut details, error) = match loaded {
+        Ok(details) => (details, None),
+        Err(err) => (fallback_details(&summary), Some(load_error_message(&err))),
     };
 
     if let Some(component) = appstream.search_component(&summary.name) {
@@ -256,7 +561,135 @@ fn load_details(ctx: AppContext, summary: PackageSummary, appstream: Arc<AppStre
         }
     }
 
-    details
+    (details, error)
+}
+
+fn load_error_message(err: &ProviderError) -> String {
+    match err {
+        ProviderError::NotInstalled(_) | ProviderError::TimedOut { .. } => {
+            format!("{err}; showing search data instead.")
+        }
+        ProviderError::ParseError(_) => String::from(
+            "The package manager returned information Aurora could not read; showing search data instead.",
+        ),
+        ProviderError::CommandFailed { .. }
+        | ProviderError::FilesDatabaseMissing
+        | ProviderError::Io(_) => {
+            format!("Could not read package information ({err}); showing search data instead.")
+        }
+    }
+}
+
+fn render_permissions(container: &gtk::Box, permissions: &[String]) {
+    if permissions.is_empty() {
+        return;
+    }
+    let title = gtk::Label::new(Some("Sandbox Permissions"));
+    title.add_css_class("heading");
+    title.set_xalign(0.0);
+    container.append(&title);
+    for permission in permissions {
+        let label = gtk::Label::new(Some(permission));
+        label.set_xalign(0.0);
+        label.add_css_class("monospace");
+        if is_broad_permission(permission) {
+            label.add_css_class("warning");
+            label.set_tooltip_text(Some("Broad access outside the sandbox"));
+        }
+        container.append(&label);
+    }
+    container.set_visible(true);
+}
+
+// Installed packages are listed from the local database; repo packages need the files database.
+fn has_file_list(source: PackageSource, installed: bool) -> bool {
+    match source {
+        PackageSource::Repo => true,
+        PackageSource::Aur => installed,
+        PackageSource::Flatpak => false,
+    }
+}
+
+fn files_expander(ctx: &AppContext, name: &str, installed: Rc<RefCell<bool>>) -> gtk::Expander {
+    let expander = gtk::Expander::new(Some("Files"));
+
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    content.set_margin_top(6);
+    let filter = gtk::SearchEntry::new();
+    filter.set_placeholder_text(Some("Filter files"));
+    let status = gtk::Label::new(Some("Loading file list..."));
+    status.set_xalign(0.0);
+    status.set_wrap(true);
+    status.add_css_class("dim-label");
+    let list = gtk::ListBox::new();
+    list.set_selection_mode(gtk::SelectionMode::None);
+    list.add_css_class("boxed-list");
+    let scroller = gtk::ScrolledWindow::new();
+    scroller.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+    scroller.set_min_content_height(240);
+    scroller.set_child(Some(&list));
+    content.append(&filter);
+    content.append(&status);
+    content.append(&scroller);
+    expander.set_child(Some(&content));
+
+    let list_filter = filter.clone();
+    list.set_filter_func(move |row| {
+        let query = list_filter.text().to_lowercase();
+        query.is_empty()
+            || row
+                .child()
+                .and_then(|child| child.downcast::<gtk::Label>().ok())
+                .is_some_and(|label| label.text().to_lowercase().contains(&query))
+    });
+    let list_clone = list.clone();
+    filter.connect_search_changed(move |_| {
+        list_clone.invalidate_filter();
+    });
+
+    let loaded = Rc::new(RefCell::new(false));
+    let pacman = ctx.pacman.clone();
+    let name = name.to_string();
+    expander.connect_expanded_notify(move |expander| {
+        if !expander.is_expanded() || *loaded.borrow() {
+            return;
+        }
+        *loaded.borrow_mut() = true;
+
+        let (tx, rx) = mpsc::channel();
+        let pacman = pacman.clone();
+        let name = name.clone();
+        let installed = *installed.borrow();
+        std::thread::spawn(move || {
+            let _ = tx.send(pacman.list_files(&name, installed));
+        });
+
+        let list = list.clone();
+        let status = status.clone();
+        let loaded = loaded.clone();
+        glib::idle_add_local(move || match rx.try_recv() {
+            Ok(Ok(files)) => {
+                status.set_text(&format!("{} files", files.len()));
+                for path in files {
+                    let label = gtk::Label::new(Some(&path));
+                    label.set_xalign(0.0);
+                    label.set_selectable(true);
+                    label.add_css_class("monospace");
+                    list.append(&label);
+                }
+                glib::ControlFlow::Break
+            }
+            Ok(Err(err)) => {
+                status.set_text(&format!("Could not list files: {err}"));
+                *loaded.borrow_mut() = false;
+                glib::ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => glib::ControlFlow::Break,
+        });
+    });
+
+    expander
 }
 
 fn fallback_details(summary: &PackageSummary) -> PackageDetails {
@@ -271,5 +704,7 @@ fn fallback_details(summary: &PackageSummary) -> PackageDetails {
         home: None,
         screenshots: Vec::new(),
         icon_name: None,
+        aur: AurMetadata::default(),
+        permissions: Vec::new(),
     }
 }
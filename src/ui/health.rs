@@ -0,0 +1,299 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::mpsc;
+
+use gtk::gdk;
+use gtk::prelude::*;
+use libadwaita as adw;
+use adw::prelude::*;
+
+use crate::core::diff::{line_diff, DiffLine};
+use crate::core::health::{check, read_pacnew_pair, HealthCategory, HealthItem};
+use crate::ui::widgets::accessible::set_icon_label;
+use crate::ui::AppContext;
+
+#[derive(Clone)]
+pub struct HealthPage {
+    pub root: gtk::Box,
+    refresh_button: gtk::Button,
+    status: gtk::Label,
+    groups: Vec<(HealthCategory, adw::PreferencesGroup)>,
+    rows: Rc<RefCell<Vec<(adw::PreferencesGroup, adw::ActionRow)>>>,
+}
+
+impl HealthPage {
+    pub fn new() -> Self {
+        let root = gtk::Box::new(gtk::Orientation::Vertical, 12);
+        root.add_css_class("page-root");
+        root.set_margin_top(12);
+        root.set_margin_bottom(12);
+        root.set_margin_start(12);
+        root.set_margin_end(12);
+        root.set_hexpand(true);
+        root.set_vexpand(true);
+
+        let title = gtk::Label::new(Some("System Health"));
+        title.add_css_class("title-2");
+        title.set_xalign(0.0);
+        root.append(&title);
+
+        let info = gtk::Label::new(Some(
+            "Read-only checks for leftovers and problems that often follow upgrades.",
+        ));
+        info.add_css_class("dim-label");
+        info.set_wrap(true);
+        info.set_xalign(0.0);
+        root.append(&info);
+
+        let controls = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+        controls.add_css_class("page-controls");
+        let status = gtk::Label::new(Some("Not checked yet"));
+        status.add_css_class("dim-label");
+        status.set_xalign(0.0);
+        status.set_hexpand(true);
+        let refresh_button = gtk::Button::with_label("Run Checks");
+        refresh_button.add_css_class("suggested-action");
+        controls.append(&status);
+        controls.append(&refresh_button);
+        root.append(&controls);
+
+        let content = adw::PreferencesPage::new();
+        let mut groups = Vec::new();
+        for category in HealthCategory::all() {
+            let group = adw::PreferencesGroup::new();
+            group.set_title(category.label());
+            group.set_description(Some(category.description()));
+            content.add(&group);
+            groups.push((*category, group));
+        }
+
+        let scroller = gtk::ScrolledWindow::new();
+        scroller.add_css_class("content-scroller");
+        scroller.set_vexpand(true);
+        scroller.set_child(Some(&content));
+        root.append(&scroller);
+
+        Self {
+            root,
+            refresh_button,
+            status,
+            groups,
+            rows: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    pub fn bind(&self, ctx: AppContext) {
+        let page = self.clone();
+        self.refresh_button.connect_clicked(move |_| {
+            page.refresh(ctx.clone());
+        });
+    }
+
+    pub fn refresh(&self, ctx: AppContext) {
+        self.refresh_button.set_sensitive(false);
+        self.status.set_text("Running checks...");
+
+        let (tx, rx) = mpsc::channel();
+        let runner = ctx.runner.clone();
+        std::thread::spawn(move || {
+            let results: Vec<(HealthCategory, Result<Vec<HealthItem>, String>)> =
+                HealthCategory::all()
+                    .iter()
+                    .map(|category| {
+                        let result = check(&runner, *category).map_err(|err| err.to_string());
+                        (*category, result)
+                    })
+                    .collect();
+            let _ = tx.send(results);
+        });
+
+        let page = self.clone();
+        glib::idle_add_local(move || match rx.try_recv() {
+            Ok(results) => {
+                page.render(results, &ctx);
+                page.refresh_button.set_sensitive(true);
+                glib::ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                page.refresh_button.set_sensitive(true);
+                glib::ControlFlow::Break
+            }
+        });
+    }
+
+    fn render(
+        &self,
+        results: Vec<(HealthCategory, Result<Vec<HealthItem>, String>)>,
+        ctx: &AppContext,
+    ) {
+        for (group, row) in self.rows.borrow_mut().drain(..) {
+            group.remove(&row);
+        }
+
+        let mut issues = 0;
+        for (category, result) in results {
+            let Some((_, group)) = self.groups.iter().find(|(c, _)| *c == category) else {
+                continue;
+            };
+            let rows = match result {
+                Ok(items) if items.is_empty() => vec![message_row("Nothing to report", "")],
+                Ok(items) => {
+                    if category != HealthCategory::ForeignPackages {
+                        issues += items.len();
+                    }
+                    items
+                        .iter()
+                        .map(|item| {
+                            let row = item_row(item);
+                            if item.title.ends_with(".pacnew") {
+                                row.add_suffix(&diff_button(&item.title, ctx));
+                            }
+                            row
+                        })
+                        .collect()
+                }
+                Err(err) => vec![message_row("Check failed", &err)],
+            };
+            for row in rows {
+                group.add(&row);
+                self.rows.borrow_mut().push((group.clone(), row));
+            }
+        }
+
+        if issues == 0 {
+            self.status.set_text("No problems found");
+        } else {
+            self.status.set_text(&format!("{issues} item(s) need attention"));
+        }
+    }
+}
+
+fn item_row(item: &HealthItem) -> adw::ActionRow {
+    let row = adw::ActionRow::new();
+    row.set_title(&item.title);
+    row.set_subtitle(&item.detail);
+    row.set_activatable(false);
+
+    let copy = gtk::Button::from_icon_name("edit-copy-symbolic");
+    set_icon_label(&copy, "Copy");
+    copy.set_valign(gtk::Align::Center);
+    copy.add_css_class("flat");
+    let text = item.title.clone();
+    copy.connect_clicked(move |_| {
+        if let Some(display) = gdk::Display::default() {
+            display.clipboard().set_text(&text);
+        }
+    });
+    row.add_suffix(&copy);
+    row
+}
+
+fn diff_button(pacnew: &str, ctx: &AppContext) -> gtk::Button {
+    let button = gtk::Button::with_label("Diff");
+    button.set_valign(gtk::Align::Center);
+    button.add_css_class("flat");
+    let pacnew = pacnew.to_string();
+    let ctx = ctx.clone();
+    button.connect_clicked(move |button| {
+        button.set_sensitive(false);
+        let (tx, rx) = mpsc::channel();
+        let runner = ctx.runner.clone();
+        let path = pacnew.clone();
+        let escalation = ctx.settings.lock().unwrap().escalation;
+        std::thread::spawn(move || {
+            let result = read_pacnew_pair(&runner, &path, escalation)
+                .map(|(old, new)| line_diff(&old, &new))
+                .map_err(|err| err.to_string());
+            let _ = tx.send(result);
+        });
+
+        let button = button.clone();
+        let pacnew = pacnew.clone();
+        glib::idle_add_local(move || match rx.try_recv() {
+            Ok(result) => {
+                button.set_sensitive(true);
+                show_diff_window(&button, &pacnew, result);
+                glib::ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                button.set_sensitive(true);
+                glib::ControlFlow::Break
+            }
+        });
+    });
+    button
+}
+
+fn show_diff_window(source: &gtk::Button, pacnew: &str, result: Result<Vec<DiffLine>, String>) {
+    let buffer = gtk::TextBuffer::new(None);
+    let added = buffer.create_tag(Some("added"), &[("foreground", &"#2ec27e")]);
+    let removed = buffer.create_tag(Some("removed"), &[("foreground", &"#e01b24")]);
+    let text = match &result {
+        Ok(lines) => lines
+            .iter()
+            .map(DiffLine::prefixed)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Err(err) => format!("Failed to read {pacnew}: {err}"),
+    };
+    if let Ok(lines) = &result {
+        for line in lines {
+            let mut end = buffer.end_iter();
+            let tag = match line {
+                DiffLine::Added(_) => added.as_ref(),
+                DiffLine::Removed(_) => removed.as_ref(),
+                DiffLine::Same(_) => None,
+            };
+            let text = format!("{}\n", line.prefixed());
+            match tag {
+                Some(tag) => buffer.insert_with_tags(&mut end, &text, &[tag]),
+                None => buffer.insert(&mut end, &text),
+            }
+        }
+    } else {
+        buffer.set_text(&text);
+    }
+
+    let view = gtk::TextView::with_buffer(&buffer);
+    view.set_editable(false);
+    view.set_monospace(true);
+    let scroller = gtk::ScrolledWindow::new();
+    scroller.set_vexpand(true);
+    scroller.set_child(Some(&view));
+
+    let copy = gtk::Button::from_icon_name("edit-copy-symbolic");
+    set_icon_label(&copy, "Copy");
+    copy.connect_clicked(move |_| {
+        if let Some(display) = gdk::Display::default() {
+            display.clipboard().set_text(&text);
+        }
+    });
+    let header = adw::HeaderBar::new();
+    header.pack_start(&copy);
+
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    content.append(&header);
+    content.append(&scroller);
+
+    let window = adw::Window::new();
+    window.set_title(Some(pacnew));
+    window.set_modal(true);
+    window.set_default_size(900, 600);
+    window.set_content(Some(&content));
+    if let Some(parent) = source.root().and_downcast::<gtk::Window>() {
+        window.set_transient_for(Some(&parent));
+    }
+    window.present();
+}
+
+fn message_row(title: &str, subtitle: &str) -> adw::ActionRow {
+    let row = adw::ActionRow::new();
+    row.set_title(title);
+    if !subtitle.is_empty() {
+        row.set_subtitle(subtitle);
+    }
+    row.set_activatable(false);
+    row
+}
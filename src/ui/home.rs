@@ -1,12 +1,29 @@
-use std::sync::mpsc;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, OnceLock};
 use std::time::Duration;
 
 use gtk::prelude::*;
 use glib::ControlFlow;
 
-use crate::core::cache::find_logo_path;
+use crate::core::cache::{
+    favorite_keys, find_logo_path, load_curated_apps, load_favorites, FavoriteKeys,
+};
+use crate::core::error::ProviderError;
+use crate::core::models::{
+    ActionKind, CuratedApp, PackageSource, PackageSummary, SearchMode, TransactionAction,
+};
 use crate::ui::widgets::card;
-use crate::ui::AppContext;
+use crate::ui::{AppContext, Generation, UiHandles};
+
+const CATEGORIES: [(&str, &str); 5] = [
+    ("Graphics", "Graphics"),
+    ("Development", "Development"),
+    ("Media", "AudioVideo"),
+    ("Games", "Game"),
+    ("Utilities", "Utility"),
+];
 
 #[derive(Clone)]
 pub struct HomePage {
@@ -15,6 +32,22 @@ pub struct HomePage {
     pub open_updates_btn: gtk::Button,
     pub open_installed_btn: gtk::Button,
     summary_label: gtk::Label,
+    summary_spinner: gtk::Spinner,
+    favorites_section: gtk::Box,
+    favorites_status: gtk::Label,
+    favorites: gtk::FlowBox,
+    install_favorites: gtk::Button,
+    favorite_packages: Rc<RefCell<Vec<PackageSummary>>>,
+    featured: gtk::FlowBox,
+    popular: gtk::FlowBox,
+    category_buttons: Vec<(gtk::Button, &'static str, &'static str)>,
+    category_section: gtk::Box,
+    category_title: gtk::Label,
+    category_status: gtk::Label,
+    category_results: gtk::FlowBox,
+    summary_generation: Generation,
+    favorites_generation: Generation,
+    category_generation: Generation,
 }
 
 impl HomePage {
@@ -41,7 +74,11 @@ impl HomePage {
         summary_label.set_xalign(0.0);
         summary_label.set_wrap(true);
         summary_label.add_css_class("dim-label");
-        root.append(&summary_label);
+        let summary_spinner = gtk::Spinner::new();
+        let summary_row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        summary_row.append(&summary_spinner);
+        summary_row.append(&summary_label);
+        root.append(&summary_row);
 
         let quick_actions = gtk::Box::new(gtk::Orientation::Horizontal, 8);
         quick_actions.add_css_class("page-controls");
@@ -55,13 +92,69 @@ impl HomePage {
         root.append(&quick_actions);
 
         let category_row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
-        for label in ["Graphics", "Development", "Media", "Games", "Utilities"] {
-            let chip = gtk::Label::new(Some(label));
+        let mut category_buttons = Vec::new();
+        for (label, category) in CATEGORIES {
+            let chip = gtk::Button::with_label(label);
             chip.add_css_class("pill");
             category_row.append(&chip);
+            category_buttons.push((chip, label, category));
         }
         root.append(&category_row);
 
+        let category_section = gtk::Box::new(gtk::Orientation::Vertical, 8);
+        category_section.set_visible(false);
+        let category_title = gtk::Label::new(None);
+        category_title.add_css_class("title-2");
+        category_title.set_xalign(0.0);
+        let category_status = gtk::Label::new(None);
+        category_status.add_css_class("dim-label");
+        category_status.set_xalign(0.0);
+        category_status.set_wrap(true);
+        let category_results = gtk::FlowBox::new();
+        category_results.set_valign(gtk::Align::Start);
+        category_results.set_min_children_per_line(1);
+        category_results.set_max_children_per_line(3);
+        category_results.set_column_spacing(12);
+        category_results.set_row_spacing(12);
+        category_results.set_homogeneous(true);
+        category_results.set_selection_mode(gtk::SelectionMode::None);
+        let category_scroller = gtk::ScrolledWindow::new();
+        category_scroller.add_css_class("content-scroller");
+        category_scroller.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        category_scroller.set_min_content_height(320);
+        category_scroller.set_child(Some(&category_results));
+        category_section.append(&category_title);
+        category_section.append(&category_status);
+        category_section.append(&category_scroller);
+        root.append(&category_section);
+
+        let favorites_section = gtk::Box::new(gtk::Orientation::Vertical, 8);
+        favorites_section.set_visible(false);
+        let favorites_header = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+        let favorites_title = gtk::Label::new(Some("Favorites"));
+        favorites_title.add_css_class("title-2");
+        favorites_title.set_xalign(0.0);
+        favorites_title.set_hexpand(true);
+        let install_favorites = gtk::Button::with_label("Install All Favorites");
+        favorites_header.append(&favorites_title);
+        favorites_header.append(&install_favorites);
+        let favorites_status = gtk::Label::new(None);
+        favorites_status.add_css_class("dim-label");
+        favorites_status.set_xalign(0.0);
+        favorites_status.set_wrap(true);
+        let favorites = gtk::FlowBox::new();
+        favorites.set_valign(gtk::Align::Start);
+        favorites.set_min_children_per_line(1);
+        favorites.set_max_children_per_line(3);
+        favorites.set_column_spacing(12);
+        favorites.set_row_spacing(12);
+        favorites.set_homogeneous(true);
+        favorites.set_selection_mode(gtk::SelectionMode::None);
+        favorites_section.append(&favorites_header);
+        favorites_section.append(&favorites_status);
+        favorites_section.append(&favorites);
+        root.append(&favorites_section);
+
         let title = gtk::Label::new(Some("Featured"));
         title.add_css_class("title-2");
         title.set_xalign(0.0);
@@ -92,42 +185,325 @@ impl HomePage {
         popular_grid.set_selection_mode(gtk::SelectionMode::None);
         root.append(&popular_grid);
 
-        // Lightweight placeholders so the page doesn't look empty.
-        let placeholder = card::build_card(
-            &crate::core::models::PackageSummary {
-                name: "Discover Apps".to_string(),
-                summary: "Search and install applications from repo and AUR."
-                    .to_string(),
-                version: String::from("-"),
-                source: crate::core::models::PackageSource::Repo,
-                installed: false,
-                origin: None,
-            },
-            || {},
-            || {},
-        );
-        featured.insert(&placeholder, -1);
-
         Self {
             root,
             open_search_btn,
             open_updates_btn,
             open_installed_btn,
             summary_label,
+            summary_spinner,
+            favorites_section,
+            favorites_status,
+            favorites,
+            install_favorites,
+            favorite_packages: Rc::new(RefCell::new(Vec::new())),
+            featured,
+            popular: popular_grid,
+            category_buttons,
+            category_section,
+            category_title,
+            category_status,
+            category_results,
+            summary_generation: Generation::default(),
+            favorites_generation: Generation::default(),
+            category_generation: Generation::default(),
         }
     }
 
-    pub fn bind(&self, ctx: AppContext) {
-        refresh_summary(self.summary_label.clone(), ctx.clone());
+    pub fn bind(&self, ctx: AppContext, handles: UiHandles) {
+        for (button, label, category) in &self.category_buttons {
+            let page = self.clone();
+            let ctx = ctx.clone();
+            let handles = handles.clone();
+            let label = *label;
+            let category = *category;
+            button.connect_clicked(move |_| {
+                page.browse_category(label, category, ctx.clone(), handles.clone());
+            });
+        }
+
+        let packages = self.favorite_packages.clone();
+        let queue = handles.queue.clone();
+        self.install_favorites.connect_clicked(move |_| {
+            let actions = packages
+                .borrow()
+                .iter()
+                .filter(|pkg| !pkg.installed)
+                .map(|pkg| TransactionAction {
+                    name: pkg.name.clone(),
+                    source: pkg.source,
+                    kind: ActionKind::Install,
+                    origin: pkg.origin.clone(),
+                    delete_data: false,
+                    unused_runtimes: false,
+                })
+                .collect();
+            queue.add_install_many(actions);
+        });
+
+        // Both sections resolve against the same installed list, fetched by whichever asks first.
+        let installed = SharedInstalled::default();
+        self.load_favorites_with(ctx.clone(), handles.clone(), installed.clone());
+        self.load_curated(ctx.clone(), handles.clone(), installed);
+
         let summary = self.summary_label.clone();
+        let spinner = self.summary_spinner.clone();
+        let generation = self.summary_generation.clone();
+        refresh_summary(summary.clone(), spinner.clone(), &generation, ctx.clone());
         glib::timeout_add_local(Duration::from_secs(900), move || {
-            refresh_summary(summary.clone(), ctx.clone());
+            refresh_summary(summary.clone(), spinner.clone(), &generation, ctx.clone());
             ControlFlow::Continue
         });
     }
+
+    pub fn load_favorites(&self, ctx: AppContext, handles: UiHandles) {
+        self.load_favorites_with(ctx, handles, SharedInstalled::default());
+    }
+
+    fn load_favorites_with(&self, ctx: AppContext, handles: UiHandles, installed: SharedInstalled) {
+        let current = self.favorites_generation.start();
+        let ctx_thread = ctx.clone();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let favorites = load_favorites();
+            let installed = installed.get_or_init(|| InstalledNames::load(&ctx_thread));
+            let resolved = resolve_curated(&ctx_thread, &favorites, installed);
+            let missing: Vec<CuratedApp> = favorites
+                .into_iter()
+                .filter(|fav| {
+                    !resolved
+                        .iter()
+                        .any(|pkg| pkg.name == fav.name && pkg.source == fav.source)
+                })
+                .collect();
+            let _ = tx.send((resolved, missing));
+        });
+
+        let page = self.clone();
+        glib::idle_add_local(move || match rx.try_recv() {
+            _ if !page.favorites_generation.is_current(current) => ControlFlow::Break,
+            Ok((resolved, missing)) => {
+                clear_flowbox(&page.favorites);
+                page.favorites_section
+                    .set_visible(!resolved.is_empty() || !missing.is_empty());
+                if missing.is_empty() {
+                    page.favorites_status.set_visible(false);
+                } else {
+                    let names: Vec<&str> = missing.iter().map(|fav| fav.name.as_str()).collect();
+                    page.favorites_status.set_text(&format!(
+                        "No longer available: {}",
+                        names.join(", ")
+                    ));
+                    page.favorites_status.set_visible(true);
+                }
+                page.install_favorites
+                    .set_sensitive(resolved.iter().any(|pkg| !pkg.installed));
+                let favorites: FavoriteKeys = resolved
+                    .iter()
+                    .map(|pkg| (pkg.name.clone(), pkg.source))
+                    .collect();
+                for pkg in &resolved {
+                    page.favorites
+                        .insert(&package_card(pkg.clone(), &favorites, &ctx, &handles), -1);
+                }
+                *page.favorite_packages.borrow_mut() = resolved;
+                ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => ControlFlow::Break,
+        });
+    }
+
+    fn load_curated(&self, ctx: AppContext, handles: UiHandles, installed: SharedInstalled) {
+        let ctx_thread = ctx.clone();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let curated = load_curated_apps();
+            let installed = installed.get_or_init(|| InstalledNames::load(&ctx_thread));
+            let featured = resolve_curated(&ctx_thread, &curated.featured, installed);
+            let popular = resolve_curated(&ctx_thread, &curated.popular, installed);
+            let _ = tx.send((featured, popular));
+        });
+
+        let featured = self.featured.clone();
+        let popular = self.popular.clone();
+        glib::idle_add_local(move || match rx.try_recv() {
+            Ok((featured_pkgs, popular_pkgs)) => {
+                let favorites = favorite_keys();
+                clear_flowbox(&featured);
+                for pkg in featured_pkgs {
+                    featured.insert(&package_card(pkg, &favorites, &ctx, &handles), -1);
+                }
+                clear_flowbox(&popular);
+                for pkg in popular_pkgs {
+                    popular.insert(&package_card(pkg, &favorites, &ctx, &handles), -1);
+                }
+                ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => ControlFlow::Break,
+        });
+    }
+
+    fn browse_category(&self, label: &str, category: &str, ctx: AppContext, handles: UiHandles) {
+        self.category_section.set_visible(true);
+        self.category_title.set_text(label);
+        self.category_status.set_text(&format!("Loading {label} apps..."));
+        clear_flowbox(&self.category_results);
+        let current = self.category_generation.start();
+
+        let category = category.to_string();
+        let appstream = ctx.appstream.clone();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let components = appstream.components_in_category(&category);
+            let _ = tx.send(components);
+        });
+
+        let page = self.clone();
+        let label = label.to_string();
+        glib::idle_add_local(move || match rx.try_recv() {
+            _ if !page.category_generation.is_current(current) => ControlFlow::Break,
+            Ok(components) => {
+                let mut seen = HashSet::new();
+                let packages: Vec<PackageSummary> = components
+                    .iter()
+                    .map(|comp| comp.to_package_summary())
+                    .filter(|pkg| seen.insert((pkg.source, pkg.name.clone())))
+                    .collect();
+                page.render_category(&label, packages, &ctx, &handles);
+                ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => ControlFlow::Break,
+        });
+    }
+
+    fn render_category(
+        &self,
+        label: &str,
+        packages: Vec<PackageSummary>,
+        ctx: &AppContext,
+        handles: &UiHandles,
+    ) {
+        clear_flowbox(&self.category_results);
+        if packages.is_empty() {
+            self.category_status.set_text(&format!(
+                "No AppStream data found for {label}. Install appstream metadata (e.g. archlinux-appstream-data) to browse categories."
+            ));
+            return;
+        }
+
+        self.category_status
+            .set_text(&format!("{} apps in {label}", packages.len()));
+        let favorites = favorite_keys();
+        for pkg in packages {
+            self.category_results
+                .insert(&package_card(pkg, &favorites, ctx, handles), -1);
+        }
+    }
+}
+
+fn package_card(
+    pkg: PackageSummary,
+    favorites: &FavoriteKeys,
+    ctx: &AppContext,
+    handles: &UiHandles,
+) -> gtk::Box {
+    let queue = handles.queue.clone();
+    let handles_for_details = handles.clone();
+    let ctx_for_details = ctx.clone();
+    let pkg_for_action = pkg.clone();
+    let pkg_for_details = pkg.clone();
+    card::build_card(
+        &pkg,
+        favorites,
+        move || {
+            queue.add_install(
+                pkg_for_action.name.clone(),
+                pkg_for_action.source,
+                pkg_for_action.origin.clone(),
+            );
+        },
+        move || {
+            crate::ui::details::show_details(
+                &ctx_for_details,
+                &handles_for_details,
+                pkg_for_details.clone(),
+            );
+        },
+    )
+}
+
+// Listed once per load and shared by every curated section resolved from it.
+struct InstalledNames {
+    pacman: HashSet<String>,
+    flatpak: HashSet<String>,
+}
+
+type SharedInstalled = Arc<OnceLock<InstalledNames>>;
+
+impl InstalledNames {
+    fn load(ctx: &AppContext) -> Self {
+        let names = |packages: Result<Vec<PackageSummary>, ProviderError>| {
+            packages
+                .unwrap_or_default()
+                .into_iter()
+                .map(|pkg| pkg.name)
+                .collect()
+        };
+        Self {
+            pacman: names(ctx.pacman.list_installed()),
+            flatpak: names(ctx.flatpak.list_installed()),
+        }
+    }
+}
+
+fn resolve_curated(
+    ctx: &AppContext,
+    apps: &[CuratedApp],
+    installed: &InstalledNames,
+) -> Vec<PackageSummary> {
+    let settings = ctx.settings.lock().unwrap().clone();
+    let mut resolved = Vec::new();
+    for app in apps {
+        if !settings.source_enabled(app.source) {
+            continue;
+        }
+        let results = match app.source {
+            PackageSource::Repo => ctx.pacman.search(&app.name, SearchMode::Exact),
+            PackageSource::Aur if settings.offline => continue,
+            PackageSource::Aur => ctx.aur.search(&app.name, SearchMode::Exact),
+            PackageSource::Flatpak => ctx.flatpak.search(&app.name, SearchMode::Exact),
+        };
+        let Some(mut pkg) = results.ok().and_then(|found| found.into_iter().next()) else {
+            continue;
+        };
+        pkg.installed = match pkg.source {
+            PackageSource::Flatpak => installed.flatpak.contains(&pkg.name),
+            _ => installed.pacman.contains(&pkg.name),
+        };
+        resolved.push(pkg);
+    }
+    resolved
+}
+
+fn clear_flowbox(flowbox: &gtk::FlowBox) {
+    while let Some(child) = flowbox.first_child() {
+        flowbox.remove(&child);
+    }
 }
 
-fn refresh_summary(summary: gtk::Label, ctx: AppContext) {
+fn refresh_summary(
+    summary: gtk::Label,
+    spinner: gtk::Spinner,
+    generation: &Generation,
+    ctx: AppContext,
+) {
+    let generation = generation.clone();
+    let current = generation.start();
+    spinner.set_visible(true);
+    spinner.start();
     let (tx, rx) = mpsc::channel();
     std::thread::spawn(move || {
         let pacman_count = ctx.pacman.list_installed().map(|v| v.len()).unwrap_or(0);
@@ -136,10 +512,13 @@ fn refresh_summary(summary: gtk::Label, ctx: AppContext) {
     });
 
     glib::idle_add_local(move || match rx.try_recv() {
+        _ if !generation.is_current(current) => ControlFlow::Break,
         Ok((pacman_count, flatpak_count)) => {
             summary.set_text(&format!(
                 "Installed packages: {pacman_count} (Pacman/AUR), {flatpak_count} Flatpak apps"
             ));
+            spinner.stop();
+            spinner.set_visible(false);
             ControlFlow::Break
         }
         Err(mpsc::TryRecvError::Empty) => ControlFlow::Continue,
@@ -1,23 +1,115 @@
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::sync::mpsc;
 
+use gtk::gio;
 use gtk::prelude::*;
+use libadwaita as adw;
 
-use crate::core::models::PackageSummary;
+use crate::core::aur_rpc::format_date;
+use crate::core::error::ProviderError;
+use crate::core::fuzzy::fuzzy_score;
+use crate::core::models::{PackageSource, PackageSummary, TransactionAction};
+use crate::core::package_list::{package_list_from_json, package_list_to_json, PackageList};
+use crate::core::runner::command_exists;
+use crate::core::updates::UpdateItem;
 use crate::ui::details;
-use crate::ui::{AppContext, UiHandles};
+use crate::ui::widgets::accessible::set_icon_label;
+use crate::ui::{
+    restore_source_filter, save_filter, selected_filter_label, source_filter_index,
+    sync_source_filter, AppContext, Generation, UiHandles,
+};
+
+const SOURCE_FILTERS: [&str; 4] = ["All", "Pacman", "AUR", "Flatpak"];
+
+type UpdateMap = HashMap<(PackageSource, String), TransactionAction>;
+
+// Names are lowercased once per refresh so filtering on each keystroke does not allocate
+// for every installed package.
+#[derive(Default)]
+struct PackageIndex {
+    entries: Vec<(String, PackageSummary)>,
+}
+
+impl PackageIndex {
+    fn new(packages: Vec<PackageSummary>) -> Self {
+        let entries = packages
+            .into_iter()
+            .map(|pkg| (pkg.name.to_lowercase(), pkg))
+            .collect();
+        Self { entries }
+    }
+
+    // `query` must already be lowercase.
+    fn matching<'a>(&'a self, query: &'a str) -> impl Iterator<Item = &'a PackageSummary> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(name, _)| query.is_empty() || name.contains(query))
+            .map(|(_, pkg)| pkg)
+    }
+
+    // Best match first; packages that do not match at all are dropped.
+    fn ranked<'a>(&'a self, query: &str) -> Vec<&'a PackageSummary> {
+        let mut scored: Vec<(i64, &PackageSummary)> = self
+            .entries
+            .iter()
+            .filter_map(|(name, pkg)| Some((fuzzy_score(query, name)?, pkg)))
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, pkg)| pkg).collect()
+    }
+}
+
+// Checked packages are remembered by key so a search or filter re-render keeps them.
+#[derive(Clone)]
+struct Selection {
+    selected: Rc<RefCell<HashSet<(PackageSource, String)>>>,
+    checks: Rc<RefCell<Vec<gtk::CheckButton>>>,
+    remove_button: gtk::Button,
+}
+
+impl Selection {
+    fn update_label(&self) {
+        let count = self.selected.borrow().len();
+        self.remove_button.set_label(&format!("Remove Selected ({count})"));
+        self.remove_button.set_sensitive(count > 0);
+    }
+
+    fn set_all(&self, active: bool) {
+        if !active {
+            self.selected.borrow_mut().clear();
+        }
+        let checks = self.checks.borrow().clone();
+        for check in checks {
+            check.set_active(active);
+        }
+        self.update_label();
+    }
+}
 
 #[derive(Clone)]
 pub struct InstalledPage {
     pub root: gtk::Box,
     list: gtk::ListBox,
+    loading: gtk::Spinner,
     search: gtk::SearchEntry,
     filter: gtk::DropDown,
+    reason_filter: gtk::DropDown,
+    sort: gtk::DropDown,
     update_all: gtk::Button,
     refresh_button: gtk::Button,
-    all: Rc<RefCell<Vec<PackageSummary>>>,
+    export_button: gtk::Button,
+    import_button: gtk::Button,
+    select_all_button: gtk::Button,
+    select_none_button: gtk::Button,
+    unused_runtimes_button: gtk::Button,
+    selection: Selection,
+    all: Rc<RefCell<PackageIndex>>,
+    explicit: Rc<RefCell<HashSet<String>>>,
+    updates: Rc<RefCell<UpdateMap>>,
     connected: Rc<std::cell::Cell<bool>>,
+    generation: Generation,
 }
 
 impl InstalledPage {
@@ -42,22 +134,57 @@ impl InstalledPage {
         search.set_placeholder_text(Some("Search installed packages"));
         search.set_hexpand(true);
 
-        let filter = gtk::DropDown::from_strings(&["All", "Pacman", "AUR", "Flatpak"]);
+        let filter = gtk::DropDown::from_strings(&SOURCE_FILTERS);
         filter.set_selected(0);
 
+        let reason_filter =
+            gtk::DropDown::from_strings(&["All", "Explicitly installed", "Dependencies"]);
+        reason_filter.set_selected(0);
+        reason_filter.set_tooltip_text(Some("Filter by install reason"));
+
+        let sort = gtk::DropDown::from_strings(&["Name", "Recently Installed"]);
+        sort.set_selected(0);
+        sort.set_tooltip_text(Some("Sort packages"));
+
         let update_all = gtk::Button::with_label("Update All");
         update_all.add_css_class("suggested-action");
         let refresh_button = gtk::Button::from_icon_name("view-refresh-symbolic");
-        refresh_button.set_tooltip_text(Some("Refresh installed"));
+        set_icon_label(&refresh_button, "Refresh installed");
+        let export_button = gtk::Button::from_icon_name("document-save-symbolic");
+        set_icon_label(&export_button, "Export package list");
+        let import_button = gtk::Button::from_icon_name("document-open-symbolic");
+        set_icon_label(&import_button, "Import and install a package list");
 
         controls.append(&search);
         controls.append(&filter);
+        controls.append(&reason_filter);
+        controls.append(&sort);
         controls.append(&update_all);
+        controls.append(&export_button);
+        controls.append(&import_button);
         controls.append(&refresh_button);
         root.append(&controls);
 
+        let selection_bar = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+        selection_bar.add_css_class("page-controls");
+        let select_all_button = gtk::Button::with_label("Select All");
+        let select_none_button = gtk::Button::with_label("Select None");
+        let remove_selected = gtk::Button::with_label("Remove Selected (0)");
+        remove_selected.add_css_class("destructive-action");
+        remove_selected.set_sensitive(false);
+        selection_bar.append(&select_all_button);
+        selection_bar.append(&select_none_button);
+        selection_bar.append(&remove_selected);
+        let unused_runtimes_button = gtk::Button::with_label("Remove Unused Runtimes");
+        unused_runtimes_button.set_tooltip_text(Some("Review and queue `flatpak uninstall --unused`"));
+        unused_runtimes_button.set_hexpand(true);
+        unused_runtimes_button.set_halign(gtk::Align::End);
+        selection_bar.append(&unused_runtimes_button);
+        root.append(&selection_bar);
+
         let header_row = gtk::Box::new(gtk::Orientation::Horizontal, 12);
         header_row.add_css_class("table-header");
+        header_row.append(&header_label("", false, 3));
         header_row.append(&header_label("Package", true, 0));
         header_row.append(&header_label("Version", false, 16));
         header_row.append(&header_label("Source", false, 10));
@@ -74,61 +201,129 @@ impl InstalledPage {
         scroller.set_vexpand(true);
         scroller.set_child(Some(&list));
 
-        root.append(&scroller);
+        // Shown over the (possibly stale) list until a refresh delivers packages.
+        let loading = gtk::Spinner::new();
+        loading.set_size_request(32, 32);
+        loading.set_halign(gtk::Align::Center);
+        loading.set_valign(gtk::Align::Center);
+        loading.set_visible(false);
+        let overlay = gtk::Overlay::new();
+        overlay.set_vexpand(true);
+        overlay.set_child(Some(&scroller));
+        overlay.add_overlay(&loading);
+
+        root.append(&overlay);
 
         Self {
             root,
             list,
+            loading,
             search,
             filter,
+            reason_filter,
+            sort,
             update_all,
             refresh_button,
-            all: Rc::new(RefCell::new(Vec::new())),
+            export_button,
+            import_button,
+            select_all_button,
+            select_none_button,
+            unused_runtimes_button,
+            selection: Selection {
+                selected: Rc::new(RefCell::new(HashSet::new())),
+                checks: Rc::new(RefCell::new(Vec::new())),
+                remove_button: remove_selected,
+            },
+            all: Rc::new(RefCell::new(PackageIndex::default())),
+            explicit: Rc::new(RefCell::new(HashSet::new())),
+            updates: Rc::new(RefCell::new(HashMap::new())),
             connected: Rc::new(std::cell::Cell::new(false)),
+            generation: Generation::default(),
         }
     }
 
     pub fn refresh(&self, ctx: AppContext, handles: UiHandles) {
+        self.loading.set_visible(true);
+        self.loading.start();
         let page = self.clone();
         let all_ref = self.all.clone();
+        let settings = ctx.settings.lock().unwrap().clone();
+        sync_source_filter(&self.filter, &SOURCE_FILTERS, &settings);
+        if !self.connected.get() {
+            restore_source_filter(&self.filter, &SOURCE_FILTERS, settings.installed_source_filter);
+            self.reason_filter.set_selected(settings.installed_reason_filter);
+        }
+        self.unused_runtimes_button.set_visible(settings.enable_flatpak);
+        let generation = self.generation.clone();
+        let current = generation.start();
         let (tx, rx) = mpsc::channel();
         let ctx_thread = ctx.clone();
         std::thread::spawn(move || {
             let mut installed = ctx_thread.pacman.list_installed().unwrap_or_default();
-            let mut flatpaks = ctx_thread.flatpak.list_installed().unwrap_or_default();
-            installed.append(&mut flatpaks);
-            let _ = tx.send(installed);
+            // Foreign packages (pacman -Qm) are not in any sync repo, so they came from the AUR.
+            // With the AUR disabled they stay pacman packages and are removed with pacman.
+            if settings.enable_aur {
+                let foreign = ctx_thread.pacman.list_foreign().unwrap_or_default();
+                for pkg in &mut installed {
+                    if foreign.contains(&pkg.name) {
+                        pkg.source = PackageSource::Aur;
+                    }
+                }
+            }
+            let dates = ctx_thread.pacman.install_dates().unwrap_or_default();
+            for pkg in &mut installed {
+                pkg.install_date = dates.get(&pkg.name).copied();
+            }
+            let explicit = ctx_thread.pacman.list_explicit_names().unwrap_or_default();
+            if settings.enable_flatpak {
+                let mut flatpaks = ctx_thread.flatpak.list_installed().unwrap_or_default();
+                installed.append(&mut flatpaks);
+            }
+            let _ = tx.send((installed, explicit));
         });
 
-        let list = self.list.clone();
         let search = self.search.clone();
         let filter = self.filter.clone();
+        let reason_filter = self.reason_filter.clone();
+        let sort = self.sort.clone();
         let update_all = self.update_all.clone();
         let refresh_button = self.refresh_button.clone();
+        let export_button = self.export_button.clone();
+        let import_button = self.import_button.clone();
+        let select_all_button = self.select_all_button.clone();
+        let select_none_button = self.select_none_button.clone();
+        let unused_runtimes_button = self.unused_runtimes_button.clone();
+        let selection = self.selection.clone();
+        let explicit_ref = self.explicit.clone();
         let connected = self.connected.clone();
         glib::idle_add_local(move || {
+            if !generation.is_current(current) {
+                return glib::ControlFlow::Break;
+            }
             match rx.try_recv() {
-                Ok(packages) => {
-                    *all_ref.borrow_mut() = packages.clone();
-                    render_list(&list, &packages, &handles, &ctx, 0, "");
+                Ok((packages, explicit)) => {
+                    // Forget selections for packages that are no longer installed.
+                    let installed: HashSet<(PackageSource, String)> = packages
+                        .iter()
+                        .map(|pkg| (pkg.source, pkg.name.clone()))
+                        .collect();
+                    selection
+                        .selected
+                        .borrow_mut()
+                        .retain(|key| installed.contains(key));
+                    selection.update_label();
+                    *all_ref.borrow_mut() = PackageIndex::new(packages);
+                    *explicit_ref.borrow_mut() = explicit;
+                    page.loading.stop();
+                    page.loading.set_visible(false);
+                    page.render(&handles, &ctx);
                     if !connected.get() {
                         connected.set(true);
                         let handles_for_search = handles.clone();
                         let ctx_for_search = ctx.clone();
-                        let all_for_search = all_ref.clone();
-                        let list_for_search = list.clone();
-                        let filter_for_search = filter.clone();
-                        search.connect_search_changed(move |entry| {
-                            let query = entry.text().to_string().to_lowercase();
-                            let items = all_for_search.borrow();
-                            render_list(
-                                &list_for_search,
-                                &items,
-                                &handles_for_search,
-                                &ctx_for_search,
-                                filter_for_search.selected(),
-                                &query,
-                            );
+                        let page_for_search = page.clone();
+                        search.connect_search_changed(move |_| {
+                            page_for_search.render(&handles_for_search, &ctx_for_search);
                         });
 
                         let handles_for_btn = handles.clone();
@@ -143,34 +338,219 @@ impl InstalledPage {
                             page_for_refresh.refresh(ctx_for_refresh.clone(), handles_for_refresh.clone());
                         });
 
-                        let all_for_filter = all_ref.clone();
-                        let list_for_filter = list.clone();
+                        let ctx_for_export = ctx.clone();
+                        let toasts_for_export = handles.toasts.clone();
+                        export_button.connect_clicked(move |_| {
+                            export_package_list(&ctx_for_export, &toasts_for_export);
+                        });
+
+                        let handles_for_import = handles.clone();
+                        import_button.connect_clicked(move |_| {
+                            import_package_list(&handles_for_import);
+                        });
+
+                        let selection_for_all = selection.clone();
+                        select_all_button.connect_clicked(move |_| {
+                            selection_for_all.set_all(true);
+                        });
+
+                        let selection_for_none = selection.clone();
+                        select_none_button.connect_clicked(move |_| {
+                            selection_for_none.set_all(false);
+                        });
+
+                        let handles_for_unused = handles.clone();
+                        unused_runtimes_button.connect_clicked(move |_| {
+                            handles_for_unused.queue.add_remove_unused_runtimes();
+                        });
+
+                        let selection_for_remove = selection.clone();
+                        let handles_for_remove = handles.clone();
+                        selection.remove_button.connect_clicked(move |_| {
+                            let mut packages: Vec<(String, PackageSource)> = selection_for_remove
+                                .selected
+                                .borrow()
+                                .iter()
+                                .map(|(source, name)| (name.clone(), *source))
+                                .collect();
+                            packages.sort_by(|a, b| a.0.cmp(&b.0));
+                            handles_for_remove.queue.add_remove_many(packages);
+                            selection_for_remove.set_all(false);
+                        });
+
                         let handles_for_filter = handles.clone();
                         let ctx_for_filter = ctx.clone();
-                        let search_for_filter = search.clone();
-                        filter.connect_selected_notify(move |f| {
-                            let query = search_for_filter.text().to_string().to_lowercase();
-                            let items = all_for_filter.borrow();
-                            render_list(
-                                &list_for_filter,
-                                &items,
-                                &handles_for_filter,
-                                &ctx_for_filter,
-                                f.selected(),
-                                &query,
-                            );
+                        let page_for_filter = page.clone();
+                        filter.connect_selected_notify(move |filter| {
+                            save_filter(&ctx_for_filter, |settings| {
+                                settings.installed_source_filter =
+                                    source_filter_index(filter, &SOURCE_FILTERS);
+                            });
+                            page_for_filter.render(&handles_for_filter, &ctx_for_filter);
+                        });
+
+                        let handles_for_reason = handles.clone();
+                        let ctx_for_reason = ctx.clone();
+                        let page_for_reason = page.clone();
+                        reason_filter.connect_selected_notify(move |filter| {
+                            save_filter(&ctx_for_reason, |settings| {
+                                settings.installed_reason_filter = filter.selected();
+                            });
+                            page_for_reason.render(&handles_for_reason, &ctx_for_reason);
+                        });
+
+                        let handles_for_sort = handles.clone();
+                        let ctx_for_sort = ctx.clone();
+                        let page_for_sort = page.clone();
+                        sort.connect_selected_notify(move |_| {
+                            page_for_sort.render(&handles_for_sort, &ctx_for_sort);
                         });
                     }
                     glib::ControlFlow::Break
                 }
                 Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
-                Err(mpsc::TryRecvError::Disconnected) => glib::ControlFlow::Break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    page.loading.stop();
+                    page.loading.set_visible(false);
+                    glib::ControlFlow::Break
+                }
             }
         });
     }
+
+    // Update checks are networked, so rows take their Update buttons from the Updates page's
+    // results instead of running a check of their own.
+    pub fn set_updates(&self, items: &[UpdateItem], handles: &UiHandles, ctx: &AppContext) {
+        *self.updates.borrow_mut() = items
+            .iter()
+            .map(|item| ((item.action.source, item.action.name.clone()), item.action.clone()))
+            .collect();
+        if !self.all.borrow().entries.is_empty() {
+            self.render(handles, ctx);
+        }
+    }
+
+    fn render(&self, handles: &UiHandles, ctx: &AppContext) {
+        let query = self.search.text().to_string().to_lowercase();
+        render_list(
+            &self.list,
+            &self.all.borrow(),
+            (&self.explicit.borrow(), &self.updates.borrow(), &self.selection),
+            handles,
+            ctx,
+            (
+                &selected_filter_label(&self.filter),
+                self.reason_filter.selected(),
+                self.sort.selected() == 1,
+            ),
+            &query,
+        );
+    }
+}
+
+// A failed query aborts the export rather than silently writing a partial list. Flatpak is
+// only skipped when it is not installed at all.
+fn collect_package_list(ctx: &AppContext) -> Result<PackageList, ProviderError> {
+    let foreign = ctx.pacman.list_foreign()?;
+    let mut repo: Vec<String> = ctx
+        .pacman
+        .list_explicit_names()?
+        .into_iter()
+        .filter(|name| !foreign.contains(name))
+        .collect();
+    let mut aur: Vec<String> = foreign.into_iter().collect();
+    let mut flatpak: Vec<String> = if command_exists("flatpak") {
+        ctx.flatpak
+            .list_installed()?
+            .into_iter()
+            .map(|pkg| pkg.name)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    repo.sort();
+    aur.sort();
+    flatpak.sort();
+    Ok(PackageList { repo, aur, flatpak })
+}
+
+fn export_package_list(ctx: &AppContext, toasts: &adw::ToastOverlay) {
+    let dialog = gtk::FileDialog::new();
+    dialog.set_title("Export Package List");
+    dialog.set_initial_name(Some("aurora-packages.json"));
+    let ctx = ctx.clone();
+    let toasts = toasts.clone();
+    dialog.save(None::<&gtk::Window>, gio::Cancellable::NONE, move |res| {
+        let Some(path) = res.ok().and_then(|file| file.path()) else {
+            return;
+        };
+        let (tx, rx) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let result = collect_package_list(&ctx)
+                .map_err(anyhow::Error::from)
+                .and_then(|list| {
+                    std::fs::write(&path, package_list_to_json(&list)?)?;
+                    Ok(list.count())
+                })
+                .map_err(|err| err.to_string());
+            let _ = tx.send(result);
+        });
+        let toasts = toasts.clone();
+        glib::idle_add_local(move || match rx.try_recv() {
+            Ok(result) => {
+                let message = match result {
+                    Ok(count) => format!("Exported {count} packages"),
+                    Err(err) => format!("Could not export package list: {err}"),
+                };
+                toasts.add_toast(adw::Toast::new(&message));
+                glib::ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => glib::ControlFlow::Break,
+        });
+    });
+}
+
+fn import_package_list(handles: &UiHandles) {
+    let dialog = gtk::FileDialog::new();
+    dialog.set_title("Import Package List");
+    let handles = handles.clone();
+    dialog.open(None::<&gtk::Window>, gio::Cancellable::NONE, move |res| {
+        let Some(path) = res.ok().and_then(|file| file.path()) else {
+            return;
+        };
+        let result = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| package_list_from_json(&data));
+        let (list, rejected) = match result {
+            Ok(imported) => imported,
+            Err(err) => {
+                handles.toasts.add_toast(adw::Toast::new(&format!(
+                    "Could not import package list: {err}"
+                )));
+                return;
+            }
+        };
+        if !rejected.is_empty() {
+            handles.log_drawer.set_visible(true);
+            handles.log_drawer.append_line(&format!(
+                "Package list import skipped invalid entries: {}",
+                rejected.join(", ")
+            ));
+        }
+        handles.queue.add_install_many(list.install_actions());
+    });
 }
 
-fn build_row(pkg: PackageSummary, handles: &UiHandles, ctx: &AppContext) -> gtk::ListBoxRow {
+fn build_row(
+    pkg: PackageSummary,
+    update: Option<TransactionAction>,
+    show_date: bool,
+    selection: &Selection,
+    handles: &UiHandles,
+    ctx: &AppContext,
+) -> gtk::ListBoxRow {
     let row = gtk::ListBoxRow::new();
     row.add_css_class("package-row");
     let content = gtk::Box::new(gtk::Orientation::Horizontal, 8);
@@ -180,12 +560,34 @@ fn build_row(pkg: PackageSummary, handles: &UiHandles, ctx: &AppContext) -> gtk:
     content.set_margin_start(8);
     content.set_margin_end(8);
 
+    let key = (pkg.source, pkg.name.clone());
+    let check = gtk::CheckButton::new();
+    check.set_active(selection.selected.borrow().contains(&key));
+    check.set_margin_end(2);
+    let selection_for_check = selection.clone();
+    check.connect_toggled(move |check| {
+        if check.is_active() {
+            selection_for_check.selected.borrow_mut().insert(key.clone());
+        } else {
+            selection_for_check.selected.borrow_mut().remove(&key);
+        }
+        selection_for_check.update_label();
+    });
+    selection.checks.borrow_mut().push(check.clone());
+    content.append(&check);
+
     let name_col = gtk::Box::new(gtk::Orientation::Horizontal, 0);
     name_col.set_hexpand(true);
     let name = gtk::Label::new(Some(&pkg.name));
     name.add_css_class("title-4");
     name.set_xalign(0.0);
     name_col.append(&name);
+    if let Some(date) = pkg.install_date.filter(|_| show_date) {
+        let installed_on = gtk::Label::new(Some(&format_date(date)));
+        installed_on.add_css_class("dim-label");
+        installed_on.set_margin_start(12);
+        name_col.append(&installed_on);
+    }
     content.append(&name_col);
 
     let version = gtk::Label::new(Some(&pkg.version));
@@ -195,15 +597,15 @@ fn build_row(pkg: PackageSummary, handles: &UiHandles, ctx: &AppContext) -> gtk:
     content.append(&version);
 
     let source_badge = gtk::Label::new(Some(match pkg.source {
-        crate::core::models::PackageSource::Repo => "Pacman",
-        crate::core::models::PackageSource::Aur => "AUR",
-        crate::core::models::PackageSource::Flatpak => "Flatpak",
+        PackageSource::Repo => "Pacman",
+        PackageSource::Aur => "AUR",
+        PackageSource::Flatpak => "Flatpak",
     }));
     source_badge.add_css_class("pill");
     source_badge.set_width_chars(9);
     content.append(&source_badge);
 
-    let status_badge = gtk::Label::new(Some("Installed"));
+    let status_badge = gtk::Label::new(Some(if update.is_some() { "Update" } else { "Installed" }));
     status_badge.add_css_class("pill-secondary");
     status_badge.set_width_chars(9);
     content.append(&status_badge);
@@ -215,6 +617,15 @@ fn build_row(pkg: PackageSummary, handles: &UiHandles, ctx: &AppContext) -> gtk:
     let remove_btn = gtk::Button::with_label("Remove");
     remove_btn.add_css_class("destructive-action");
     actions.append(&details_btn);
+    if let Some(action) = update {
+        let update_btn = gtk::Button::with_label("Update");
+        update_btn.add_css_class("suggested-action");
+        let queue = handles.queue.clone();
+        update_btn.connect_clicked(move |_| {
+            queue.add_upgrade_packages(vec![action.clone()]);
+        });
+        actions.append(&update_btn);
+    }
     actions.append(&remove_btn);
     content.append(&actions);
 
@@ -249,29 +660,98 @@ fn header_label(text: &str, expand: bool, width_chars: i32) -> gtk::Label {
 
 fn render_list(
     list: &gtk::ListBox,
-    packages: &[PackageSummary],
+    index: &PackageIndex,
+    (explicit, updates, selection): (&HashSet<String>, &UpdateMap, &Selection),
     handles: &UiHandles,
     ctx: &AppContext,
-    filter_idx: u32,
+    (source_filter, reason_idx, by_install_date): (&str, u32, bool),
     query: &str,
 ) {
     while let Some(child) = list.first_child() {
         list.remove(&child);
     }
+    selection.checks.borrow_mut().clear();
+    let fuzzy = ctx.settings.lock().unwrap().fuzzy_search;
+    let mut packages: Vec<&PackageSummary> = if fuzzy && !query.is_empty() {
+        index.ranked(query)
+    } else {
+        index.matching(query).collect()
+    };
+    if by_install_date {
+        // Newest first; Flatpak apps have no recorded date and go last.
+        packages.sort_by_key(|pkg| std::cmp::Reverse(pkg.install_date));
+    }
     for pkg in packages {
-        if !query.is_empty() && !pkg.name.to_lowercase().contains(query) {
+        let matches_filter = match source_filter {
+            "Pacman" => pkg.source == PackageSource::Repo,
+            "AUR" => pkg.source == PackageSource::Aur,
+            "Flatpak" => pkg.source == PackageSource::Flatpak,
+            _ => true,
+        };
+        if !matches_filter {
             continue;
         }
-        let matches_filter = match filter_idx {
-            1 => pkg.source == crate::core::models::PackageSource::Repo,
-            2 => pkg.source == crate::core::models::PackageSource::Aur,
-            3 => pkg.source == crate::core::models::PackageSource::Flatpak,
+        // Flatpak apps have no install reason; treat them as explicitly installed.
+        let is_explicit = pkg.source == PackageSource::Flatpak
+            || explicit.contains(&pkg.name);
+        let matches_reason = match reason_idx {
+            1 => is_explicit,
+            2 => !is_explicit,
             _ => true,
         };
-        if !matches_filter {
+        if !matches_reason {
             continue;
         }
-        let row = build_row(pkg.clone(), handles, ctx);
+        let update = updates.get(&(pkg.source, pkg.name.clone())).cloned();
+        let row = build_row(pkg.clone(), update, by_install_date, selection, handles, ctx);
         list.append(&row);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: String) -> PackageSummary {
+        PackageSummary {
+            name,
+            summary: String::new(),
+            version: String::from("1.0-1"),
+            source: PackageSource::Repo,
+            installed: true,
+            origin: None,
+            popularity: None,
+            group_size: None,
+            install_date: None,
+        }
+    }
+
+    #[test]
+    fn index_filters_thousands_of_packages() {
+        let index = PackageIndex::new(
+            (0..4000)
+                .map(|i| package(format!("Lib-Package-{i:04}")))
+                .collect(),
+        );
+        assert_eq!(index.matching("").count(), 4000);
+        assert_eq!(index.matching("package-012").count(), 10);
+        assert_eq!(index.matching("lib-package-3999").count(), 1);
+
+        let queries = ["l", "li", "lib", "lib-", "lib-p", "lib-pa", "lib-pac", "lib-pack"];
+        for query in queries {
+            assert_eq!(index.matching(query).count(), 4000);
+        }
+    }
+
+    #[test]
+    fn fuzzy_ranking_puts_best_match_first() {
+        let index = PackageIndex::new(
+            ["firefox-ublock-origin", "thunderbird", "firefox"]
+                .into_iter()
+                .map(|name| package(name.to_string()))
+                .collect(),
+        );
+        let names: Vec<&str> = index.ranked("frfx").iter().map(|pkg| pkg.name.as_str()).collect();
+        assert_eq!(names, ["firefox", "firefox-ublock-origin"]);
+    }
+}
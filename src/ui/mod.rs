@@ -1,6 +1,6 @@
-use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 use std::process::Command;
 use std::rc::Rc;
 use std::sync::mpsc;
@@ -14,20 +14,37 @@ use gtk::gdk;
 use libadwaita as adw;
 use adw::prelude::*;
 
-use crate::core::appstream::AppStreamClient;
-use crate::core::cache::{ensure_cache_dirs, load_settings};
+use crate::core::ansi::strip_ansi;
+use crate::core::cache::{
+    custom_theme_path, ensure_cache_dirs, load_custom_palette, load_last_transaction, load_settings,
+    save_custom_palette, save_last_transaction, save_settings,
+};
+use crate::core::error::ProviderError;
+use crate::core::health::{format_age, pacman_lock_age, PACMAN_LOCK};
 use crate::core::models::{
-    ActionKind, PackageSource, Settings, TerminalMode, ThemeMode, TransactionAction,
-    TransactionQueue,
+    ActionKind, AurHelperKind, CustomPalette, PackageSource, PackageSummary, SearchMode, Settings, TerminalMode,
+    ThemeMode, TransactionAction, TransactionQueue, UnusedRuntime,
+};
+use crate::core::news::{
+    blocking_news, fetch_news, last_system_upgrade, unread_news, NewsItem,
+};
+use crate::core::providers::PacmanProvider;
+use crate::core::runner::{command_exists, CommandSpec, LogEvent};
+use crate::core::self_update::check_self_update;
+use crate::core::size::format_size;
+use crate::core::transactions::{
+    inverse_action, is_auth_cancelled, is_auth_failure, is_mirror_failure, parse_conflict_path, parse_helper_rejection,
+    pkgbuild_commands, pkgbuild_package_list_command, pkgbuild_srcinfo_command, plan_transactions,
+    probe_sudo_wiring, prompts_for_sudo, refresh_mirrors_command,
+    srcinfo_dependencies, sync_files_db_command, validate_pkgbuild_dir, version_changes, with_overwrite, TransactionPlan,
+    VersionChange,
 };
-use crate::core::providers::aur::Aur;
-use crate::core::providers::flatpak::Flatpak;
-use crate::core::providers::pacman::Pacman;
-use crate::core::providers::{AurProvider, FlatpakProvider, PacmanProvider};
-use crate::core::runner::{CommandRunner, LogEvent};
-use crate::core::transactions::{plan_transactions, TransactionPlan};
+pub use crate::core::context::AppContext;
+use widgets::accessible::set_icon_label;
+use widgets::plan_tree;
 
 pub mod details;
+pub mod health;
 pub mod home;
 pub mod installed;
 pub mod search;
@@ -35,18 +52,6 @@ pub mod settings;
 pub mod updates;
 pub mod widgets;
 
-#[derive(Clone)]
-pub struct AppContext {
-    pub pacman: Arc<dyn PacmanProvider>,
-    pub aur: Arc<dyn AurProvider>,
-    pub flatpak: Arc<dyn FlatpakProvider>,
-    pub appstream: Arc<AppStreamClient>,
-    pub settings: Arc<Mutex<Settings>>,
-    pub queue: Arc<Mutex<TransactionQueue>>,
-    pub runner: Arc<CommandRunner>,
-    pub transaction_in_progress: Arc<Mutex<bool>>,
-}
-
 #[derive(Clone)]
 pub struct UiHandles {
     pub nav_view: adw::NavigationView,
@@ -59,15 +64,18 @@ pub struct UiHandles {
 pub struct QueueController {
     ctx: AppContext,
     button: gtk::Button,
+    panel: widgets::queue_panel::QueuePanel,
     log_drawer: widgets::log_drawer::LogDrawer,
     parent: adw::ApplicationWindow,
     toasts: adw::ToastOverlay,
+    express: Rc<Cell<bool>>,
 }
 
 impl QueueController {
     pub fn new(
         ctx: AppContext,
         button: gtk::Button,
+        panel: widgets::queue_panel::QueuePanel,
         log_drawer: widgets::log_drawer::LogDrawer,
         parent: adw::ApplicationWindow,
         toasts: adw::ToastOverlay,
@@ -75,15 +83,28 @@ impl QueueController {
         Self {
             ctx,
             button,
+            panel,
             log_drawer,
             parent,
             toasts,
+            express: Rc::new(Cell::new(false)),
         }
     }
 
+    pub fn set_express(&self, enabled: bool) {
+        self.express.set(enabled);
+    }
+
+    pub fn set_panel_docked(&self, docked: bool) {
+        self.panel.set_docked(docked);
+    }
+
     fn update_label(&self) {
-        let len = self.ctx.queue.lock().unwrap().len();
-        self.button.set_label(&format!("Queue ({len})"));
+        let queue = self.ctx.queue.lock().unwrap();
+        let summary = queue_summary(&queue);
+        self.button.set_label(&format!("Queue ({})", queue.len()));
+        self.button.set_tooltip_text(Some(&summary));
+        self.panel.set_queue(&queue, &summary);
     }
 
     fn toast(&self, message: &str) {
@@ -92,31 +113,283 @@ impl QueueController {
     }
 
     pub fn add_install(&self, name: String, source: PackageSource, origin: Option<String>) {
-        let mut queue = self.ctx.queue.lock().unwrap();
-        queue.push(TransactionAction {
+        let action = TransactionAction {
             name,
             source,
             kind: ActionKind::Install,
             origin,
+            delete_data: false,
+            unused_runtimes: false,
+        };
+        if action.source == PackageSource::Flatpak && action.origin.is_none() {
+            self.resolve_flatpak_origin(action);
+            return;
+        }
+        self.queue_install(action);
+    }
+
+    fn resolve_flatpak_origin(&self, action: TransactionAction) {
+        let flatpak = self.ctx.flatpak.clone();
+        let app_id = action.name.clone();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(flatpak.remotes_for(&app_id).unwrap_or_default());
+        });
+
+        let controller = self.clone();
+        glib::idle_add_local(move || match rx.try_recv() {
+            Ok(remotes) => {
+                if remotes.len() > 1 {
+                    controller.choose_flatpak_remote(action.clone(), remotes);
+                } else {
+                    controller.queue_install(TransactionAction {
+                        origin: remotes.into_iter().next(),
+                        ..action.clone()
+                    });
+                }
+                ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => ControlFlow::Break,
+        });
+    }
+
+    fn choose_flatpak_remote(&self, action: TransactionAction, remotes: Vec<String>) {
+        let dialog = adw::MessageDialog::new(
+            Some(&self.parent),
+            Some("Choose a remote"),
+            Some(&format!(
+                "{} is available from several Flatpak remotes. Pick the one to install from.",
+                action.name
+            )),
+        );
+        dialog.add_response("cancel", "Cancel");
+        for remote in &remotes {
+            dialog.add_response(remote, remote);
+        }
+        dialog.set_default_response(remotes.first().map(String::as_str));
+        dialog.set_close_response("cancel");
+
+        let controller = self.clone();
+        dialog.connect_response(None, move |d: &adw::MessageDialog, response| {
+            if response != "cancel" {
+                controller.queue_install(TransactionAction {
+                    origin: Some(response.to_string()),
+                    ..action.clone()
+                });
+            }
+            d.close();
         });
+        dialog.present();
+    }
+
+    fn queue_install(&self, action: TransactionAction) {
+        if self.express.get() {
+            self.install_now(action);
+            return;
+        }
+        let mut queue = self.ctx.queue.lock().unwrap();
+        queue.push(action);
         drop(queue);
         self.update_label();
         self.toast("Added to queue");
     }
 
+    pub fn remove_action(&self, action: &TransactionAction) {
+        self.ctx.queue.lock().unwrap().remove(action);
+        self.update_label();
+    }
+
+    pub fn clear(&self) {
+        self.ctx.queue.lock().unwrap().clear();
+        self.update_label();
+    }
+
     pub fn add_remove(&self, name: String, source: PackageSource) {
+        if source == PackageSource::Flatpak {
+            self.confirm_flatpak_removal(name);
+            return;
+        }
+        self.queue_remove(name, source, false);
+    }
+
+    fn queue_remove(&self, name: String, source: PackageSource, delete_data: bool) {
         let mut queue = self.ctx.queue.lock().unwrap();
         queue.push(TransactionAction {
             name,
             source,
             kind: ActionKind::Remove,
             origin: None,
+            delete_data,
+            unused_runtimes: false,
         });
         drop(queue);
         self.update_label();
         self.toast("Added to queue");
     }
 
+    // Deleting app data cannot be undone by reinstalling, so it needs a second checkbox.
+    fn confirm_flatpak_removal(&self, name: String) {
+        let dialog = adw::MessageDialog::new(
+            Some(&self.parent),
+            Some(&format!("Remove {name}?")),
+            Some("The app's settings and files are kept unless you also delete its data."),
+        );
+        let delete_data = gtk::CheckButton::with_label("Also delete app data");
+        let confirm = gtk::CheckButton::with_label("I understand this data cannot be recovered");
+        confirm.set_margin_start(24);
+        confirm.set_visible(false);
+        let extra = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        extra.append(&delete_data);
+        extra.append(&confirm);
+        dialog.set_extra_child(Some(&extra));
+        dialog.add_response("cancel", "Cancel");
+        dialog.add_response("remove", "Remove");
+        dialog.set_response_appearance("remove", adw::ResponseAppearance::Destructive);
+        dialog.set_close_response("cancel");
+
+        let dialog_clone = dialog.clone();
+        let confirm_clone = confirm.clone();
+        delete_data.connect_toggled(move |delete_data| {
+            confirm_clone.set_active(false);
+            confirm_clone.set_visible(delete_data.is_active());
+            dialog_clone.set_response_enabled("remove", !delete_data.is_active());
+        });
+        let dialog_clone = dialog.clone();
+        confirm.connect_toggled(move |confirm| {
+            dialog_clone.set_response_enabled("remove", confirm.is_active());
+        });
+
+        let controller = self.clone();
+        dialog.connect_response(None, move |d: &adw::MessageDialog, response| {
+            if response == "remove" {
+                controller.queue_remove(name.clone(), PackageSource::Flatpak, delete_data.is_active());
+            }
+            d.close();
+        });
+        dialog.present();
+    }
+
+    pub fn add_remove_unused_runtimes(&self) {
+        self.review_unused_runtimes(|controller| {
+            let mut queue = controller.ctx.queue.lock().unwrap();
+            queue.push(TransactionAction {
+                name: "unused runtimes".to_string(),
+                source: PackageSource::Flatpak,
+                kind: ActionKind::Remove,
+                origin: None,
+                delete_data: false,
+                unused_runtimes: true,
+            });
+            drop(queue);
+            controller.update_label();
+            controller.toast("Added to queue");
+        });
+    }
+
+    pub fn remove_unused_runtimes_now(&self) {
+        self.review_unused_runtimes(|controller| {
+            controller.run_commands(vec![controller.ctx.flatpak.remove_unused()]);
+        });
+    }
+
+    fn review_unused_runtimes(&self, on_confirm: impl Fn(&Self) + 'static) {
+        let flatpak = self.ctx.flatpak.clone();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(flatpak.unused_runtimes());
+        });
+
+        let controller = self.clone();
+        let on_confirm = Rc::new(on_confirm);
+        glib::idle_add_local(move || match rx.try_recv() {
+            Ok(Ok(runtimes)) if runtimes.is_empty() => {
+                controller.toast("No unused Flatpak runtimes");
+                ControlFlow::Break
+            }
+            Ok(Ok(runtimes)) => {
+                controller.confirm_unused_runtimes(&runtimes, on_confirm.clone());
+                ControlFlow::Break
+            }
+            Ok(Err(err)) => {
+                controller.toast(&format!("Could not list unused runtimes: {err}"));
+                ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => ControlFlow::Break,
+        });
+    }
+
+    fn confirm_unused_runtimes(&self, runtimes: &[UnusedRuntime], on_confirm: Rc<dyn Fn(&Self)>) {
+        let total: u64 = runtimes.iter().filter_map(|runtime| runtime.size).sum();
+        let lines: Vec<String> = runtimes
+            .iter()
+            .map(|runtime| match runtime.size {
+                Some(bytes) => {
+                    format!("{}//{}  ({})", runtime.id, runtime.branch, format_size(bytes))
+                }
+                None => format!("{}//{}", runtime.id, runtime.branch),
+            })
+            .collect();
+        let dialog = adw::MessageDialog::new(
+            Some(&self.parent),
+            Some(&format!("Remove {} unused runtime(s)?", runtimes.len())),
+            Some(&format!(
+                "No installed app depends on these anymore. This frees about {}.\n\n{}",
+                format_size(total),
+                lines.join("\n")
+            )),
+        );
+        dialog.add_response("cancel", "Cancel");
+        dialog.add_response("remove", "Remove");
+        dialog.set_response_appearance("remove", adw::ResponseAppearance::Destructive);
+        dialog.set_close_response("cancel");
+
+        let controller = self.clone();
+        dialog.connect_response(None, move |d: &adw::MessageDialog, response| {
+            if response == "remove" {
+                on_confirm(&controller);
+            }
+            d.close();
+        });
+        dialog.present();
+    }
+
+    pub fn add_remove_many(&self, packages: Vec<(String, PackageSource)>) {
+        let mut queue = self.ctx.queue.lock().unwrap();
+        let added = packages
+            .into_iter()
+            .filter(|(name, source)| {
+                queue.push(TransactionAction {
+                    name: name.clone(),
+                    source: *source,
+                    kind: ActionKind::Remove,
+                    origin: None,
+                    delete_data: false,
+                    unused_runtimes: false,
+                })
+            })
+            .count();
+        drop(queue);
+        self.update_label();
+        self.toast(&format!("Queued {added} removal(s)"));
+    }
+
+    pub fn add_reinstall(&self, name: String, source: PackageSource, origin: Option<String>) {
+        let mut queue = self.ctx.queue.lock().unwrap();
+        let added = queue.push(TransactionAction {
+            name,
+            source,
+            kind: ActionKind::Reinstall,
+            origin,
+            delete_data: false,
+            unused_runtimes: false,
+        });
+        drop(queue);
+        self.update_label();
+        self.toast(if added { "Reinstall queued" } else { "Reinstall already queued" });
+    }
+
     pub fn add_upgrade_repo(&self) {
         let mut queue = self.ctx.queue.lock().unwrap();
         queue.push(TransactionAction {
@@ -124,6 +397,8 @@ impl QueueController {
             source: PackageSource::Repo,
             kind: ActionKind::Upgrade,
             origin: None,
+            delete_data: false,
+            unused_runtimes: false,
         });
         drop(queue);
         self.update_label();
@@ -157,31 +432,157 @@ impl QueueController {
         }
     }
 
+    pub fn add_install_many(&self, actions: Vec<TransactionAction>) {
+        if actions.is_empty() {
+            self.toast("Nothing to install");
+            return;
+        }
+        let mut queue = self.ctx.queue.lock().unwrap();
+        let added = actions
+            .into_iter()
+            .filter(|action| queue.push(action.clone()))
+            .count();
+        drop(queue);
+        self.update_label();
+        self.toast(&format!("Queued {added} package(s)"));
+    }
+
     pub fn add_upgrade_all(&self) {
+        let settings = self.ctx.settings.lock().unwrap().clone();
         let mut queue = self.ctx.queue.lock().unwrap();
-        queue.push(TransactionAction {
-            name: String::from("system"),
-            source: PackageSource::Repo,
-            kind: ActionKind::Upgrade,
-            origin: None,
-        });
-        queue.push(TransactionAction {
-            name: String::from("aur"),
-            source: PackageSource::Aur,
-            kind: ActionKind::Upgrade,
-            origin: None,
-        });
-        queue.push(TransactionAction {
-            name: String::from("flatpak"),
-            source: PackageSource::Flatpak,
-            kind: ActionKind::Upgrade,
-            origin: None,
-        });
+        let upgrades = [
+            ("system", PackageSource::Repo),
+            ("aur", PackageSource::Aur),
+            ("flatpak", PackageSource::Flatpak),
+        ];
+        for (name, source) in upgrades {
+            if settings.source_enabled(source) {
+                queue.push(TransactionAction {
+                    name: String::from(name),
+                    source,
+                    kind: ActionKind::Upgrade,
+                    origin: None,
+                    delete_data: false,
+                    unused_runtimes: false,
+                });
+            }
+        }
         drop(queue);
         self.update_label();
         self.toast("All updates queued");
     }
 
+    // Skips the queue and the review dialog; run_plan still enforces the transaction lock.
+    pub fn install_now(&self, action: TransactionAction) -> bool {
+        let mut single = TransactionQueue::default();
+        single.push(action);
+        let plan = plan_transactions(&single, &self.ctx.settings.lock().unwrap());
+        run_plan(
+            plan,
+            &self.ctx,
+            &self.log_drawer,
+            &self.parent,
+            &self.toasts,
+        )
+    }
+
+    pub fn run_commands(&self, commands: Vec<CommandSpec>) -> bool {
+        run_plan(
+            TransactionPlan {
+                commands,
+                actions: Vec::new(),
+            },
+            &self.ctx,
+            &self.log_drawer,
+            &self.parent,
+            &self.toasts,
+        )
+    }
+
+    // makepkg --packagelist sources the PKGBUILD, so it runs off the main thread like the build.
+    pub fn build_pkgbuild(&self, dir: PathBuf) {
+        if let Err(err) = validate_pkgbuild_dir(&dir) {
+            self.toast(&err.to_string());
+            return;
+        }
+        let dir = dir.to_string_lossy().to_string();
+        let runner = self.ctx.runner.clone();
+        let list_command = pkgbuild_package_list_command(&dir);
+        let srcinfo_command = pkgbuild_srcinfo_command(&dir);
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let result = runner.run_capture(&list_command).and_then(|list| {
+                let srcinfo = runner.run_capture(&srcinfo_command)?;
+                Ok((list, srcinfo_dependencies(&srcinfo)))
+            });
+            let _ = tx.send(result);
+        });
+
+        let controller = self.clone();
+        glib::idle_add_local(move || match rx.try_recv() {
+            Ok(Ok((output, deps))) => {
+                let packages: Vec<String> = output
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                if packages.is_empty() {
+                    controller.toast("The PKGBUILD does not produce any packages");
+                    return ControlFlow::Break;
+                }
+                let settings = controller.ctx.settings.lock().unwrap().clone();
+                controller.run_commands(pkgbuild_commands(&dir, &packages, &deps, &settings));
+                ControlFlow::Break
+            }
+            Ok(Err(err)) => {
+                controller.toast("Failed to read the PKGBUILD package list");
+                controller.log_drawer.set_visible(true);
+                controller
+                    .log_drawer
+                    .append_line(&format!("makepkg failed to read the PKGBUILD in {dir}: {err}"));
+                ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => ControlFlow::Break,
+        });
+    }
+
+    pub fn undo_last_transaction(&self) {
+        let last = load_last_transaction();
+        if last.is_empty() {
+            self.toast("No completed transaction to undo");
+            return;
+        }
+        let mut queued = 0;
+        let mut skipped = 0;
+        {
+            let mut queue = self.ctx.queue.lock().unwrap();
+            for action in &last {
+                match inverse_action(action) {
+                    Some(inverse) => {
+                        if queue.push(inverse) {
+                            queued += 1;
+                        }
+                    }
+                    None => skipped += 1,
+                }
+            }
+        }
+        self.update_label();
+        if skipped > 0 {
+            self.log_drawer.set_visible(true);
+            self.log_drawer.append_line(&format!(
+                "Undo: skipped {skipped} upgrade action(s); upgrades cannot be undone"
+            ));
+        }
+        if queued == 0 {
+            self.toast("Nothing in the last transaction can be undone");
+            return;
+        }
+        self.show_review_dialog();
+    }
+
     pub fn show_review_dialog(&self) {
         let queue = self.ctx.queue.lock().unwrap().clone();
         if queue.is_empty() {
@@ -196,14 +597,216 @@ impl QueueController {
             return;
         }
 
+        let check_pending = queue.has_repo_installs();
+        let offline = self.ctx.settings.lock().unwrap().offline;
+        let check_news = queue.has_system_upgrade() && !offline;
+        let removals = queue.removal_names();
+        if !check_pending && !check_news && removals.is_empty() {
+            self.present_review_dialog(queue, 0, Vec::new(), Vec::new(), None, HashMap::new());
+            return;
+        }
+
+        let repo_installs: Vec<String> = queue
+            .actions
+            .iter()
+            .filter(|action| {
+                action.source == PackageSource::Repo
+                    && matches!(action.kind, ActionKind::Install | ActionKind::Reinstall)
+            })
+            .map(|action| action.name.clone())
+            .collect();
+
+        let pacman = self.ctx.pacman.clone();
+        let (last_seen, acknowledged) = {
+            let settings = self.ctx.settings.lock().unwrap();
+            (settings.news_last_seen, settings.news_acknowledged)
+        };
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let pending = if check_pending {
+                pacman.count_pending_updates().unwrap_or(0)
+            } else {
+                0
+            };
+            // "Upgrade & Install" is offered whenever updates are pending, and it upgrades the
+            // system too, so the news gate needs the feed in that case as well. Offline or a
+            // feed error just means no news is shown.
+            let items = if check_news || (pending > 0 && !offline) {
+                fetch_news().unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            let blocking = if items.is_empty() {
+                Vec::new()
+            } else {
+                blocking_news(&items, acknowledged, last_system_upgrade().unwrap_or(0))
+            };
+            let news = if check_news {
+                unread_news(items, last_seen)
+            } else {
+                Vec::new()
+            };
+            let removal_preview = (!removals.is_empty()).then(|| {
+                pacman
+                    .removal_preview(&removals)
+                    .map_err(|err| err.to_string())
+            });
+            let groups: HashMap<String, Vec<String>> = repo_installs
+                .into_iter()
+                .filter_map(|name| {
+                    let members = pacman.group_members(&name).ok()?;
+                    (!members.is_empty()).then_some((name, members))
+                })
+                .collect();
+            let _ = tx.send((pending, news, blocking, removal_preview, groups));
+        });
+
+        let controller = self.clone();
+        glib::idle_add_local(move || match rx.try_recv() {
+            Ok((pending, news, blocking, removal_preview, groups)) => {
+                controller.present_review_dialog(
+                    queue.clone(),
+                    pending,
+                    news,
+                    blocking,
+                    removal_preview,
+                    groups,
+                );
+                ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => ControlFlow::Break,
+        });
+    }
+
+    fn present_review_dialog(
+        &self,
+        queue: TransactionQueue,
+        pending_updates: usize,
+        news: Vec<NewsItem>,
+        blocking_news: Vec<NewsItem>,
+        removal_preview: Option<Result<Vec<String>, String>>,
+        groups: HashMap<String, Vec<String>>,
+    ) {
         let content = gtk::Box::new(gtk::Orientation::Vertical, 8);
-        for action in &queue.actions {
-            let row = gtk::Label::new(Some(&format!(
-                "{:?} {} ({:?})",
-                action.kind, action.name, action.source
-            )));
-            row.set_xalign(0.0);
-            content.append(&row);
+        let threshold = self.ctx.settings.lock().unwrap().removal_warning_threshold as usize;
+        let mut confirm_removal = None;
+        match &removal_preview {
+            Some(Ok(removed)) if removed.len() > threshold => {
+                let warning = gtk::Label::new(Some(&format!(
+                    "This will remove {} packages, including dependencies no longer needed",
+                    removed.len()
+                )));
+                warning.add_css_class("heading");
+                warning.add_css_class("error");
+                warning.set_xalign(0.0);
+                warning.set_wrap(true);
+                content.append(&warning);
+                let names = gtk::Label::new(Some(&removed.join(", ")));
+                names.add_css_class("error");
+                names.set_xalign(0.0);
+                names.set_wrap(true);
+                names.set_selectable(true);
+                content.append(&names);
+                let confirm =
+                    gtk::CheckButton::with_label("I understand these packages will be removed");
+                content.append(&confirm);
+                content.append(&gtk::Separator::new(gtk::Orientation::Horizontal));
+                confirm_removal = Some(confirm);
+            }
+            Some(Err(_)) => {
+                let caution = gtk::Label::new(Some(
+                    "Could not preview the removal. Removing packages also removes dependencies nothing else needs; check the logs before confirming prompts.",
+                ));
+                caution.add_css_class("warning");
+                caution.set_xalign(0.0);
+                caution.set_wrap(true);
+                content.append(&caution);
+                content.append(&gtk::Separator::new(gtk::Orientation::Horizontal));
+            }
+            _ => {}
+        }
+        if !news.is_empty() {
+            let news_title = gtk::Label::new(Some("Unread Arch Linux news"));
+            news_title.add_css_class("heading");
+            news_title.set_xalign(0.0);
+            content.append(&news_title);
+            for item in &news {
+                let link = gtk::LinkButton::with_label(&item.link, &item.title);
+                link.set_halign(gtk::Align::Start);
+                content.append(&link);
+            }
+            content.append(&gtk::Separator::new(gtk::Orientation::Horizontal));
+        }
+        let sections = [
+            (PackageSource::Repo, "Pacman"),
+            (PackageSource::Aur, "AUR"),
+            (PackageSource::Flatpak, "Flatpak"),
+        ];
+        let mut download_labels = Vec::new();
+        let mut removal_labels = Vec::new();
+        for (source, title) in sections {
+            let actions: Vec<&TransactionAction> = queue
+                .actions
+                .iter()
+                .filter(|action| action.source == source)
+                .collect();
+            if actions.is_empty() {
+                continue;
+            }
+            let header = gtk::Label::new(Some(&format!("{title} ({})", actions.len())));
+            header.add_css_class("heading");
+            header.set_xalign(0.0);
+            content.append(&header);
+            for action in actions {
+                let row = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+                row.set_margin_start(12);
+                let label = gtk::Label::new(Some(&format!("{:?} {}", action.kind, action.name)));
+                label.set_xalign(0.0);
+                label.set_hexpand(true);
+                if action.kind == ActionKind::Remove {
+                    label.add_css_class("error");
+                }
+                row.append(&label);
+                if action.delete_data {
+                    let data = gtk::Label::new(Some("deletes app data"));
+                    data.add_css_class("error");
+                    row.append(&data);
+                }
+                if let Some(members) = groups.get(&action.name) {
+                    let expands = gtk::Label::new(Some(&format!(
+                        "group of {} packages",
+                        members.len()
+                    )));
+                    expands.add_css_class("dim-label");
+                    expands.set_tooltip_text(Some(&members.join(", ")));
+                    row.append(&expands);
+                    content.append(&row);
+                    continue;
+                }
+                let sized = match (action.source, action.kind) {
+                    (PackageSource::Flatpak, _) => None,
+                    (_, ActionKind::Remove) => Some(&mut removal_labels),
+                    (PackageSource::Repo, _) => Some(&mut download_labels),
+                    _ => None,
+                };
+                if let Some(labels) = sized {
+                    let size = gtk::Label::new(Some("calculating\u{2026}"));
+                    size.add_css_class("dim-label");
+                    size.add_css_class("numeric");
+                    row.append(&size);
+                    labels.push((action.name.clone(), size));
+                }
+                content.append(&row);
+            }
+        }
+        if self.ctx.settings.lock().unwrap().review_dependency_tree {
+            content.append(&gtk::Separator::new(gtk::Orientation::Horizontal));
+            let header = gtk::Label::new(Some("Dependencies"));
+            header.add_css_class("heading");
+            header.set_xalign(0.0);
+            content.append(&header);
+            content.append(&plan_tree::build(&queue, &self.ctx));
         }
 
         let scroller = gtk::ScrolledWindow::new();
@@ -212,29 +815,95 @@ impl QueueController {
         scroller.set_max_content_height(420);
         scroller.set_child(Some(&content));
 
+        let extra = gtk::Box::new(gtk::Orientation::Vertical, 8);
+        extra.append(&scroller);
+        if !download_labels.is_empty() || !removal_labels.is_empty() {
+            let total = gtk::Label::new(Some("Calculating sizes\u{2026}"));
+            total.add_css_class("heading");
+            total.set_xalign(1.0);
+            extra.append(&total);
+            load_review_sizes(&self.ctx, download_labels, removal_labels, total);
+        }
+
+        let body = if pending_updates > 0 {
+            format!(
+                "Your system has {pending_updates} pending updates; installing without upgrading may break things. Upgrade & Install runs a full system upgrade together with the queued repo installs."
+            )
+        } else if !news.is_empty() {
+            String::from("Read the news below for manual interventions before upgrading.")
+        } else {
+            String::from("Confirm before executing.")
+        };
         let dialog = adw::MessageDialog::new(
             Some(&self.parent),
             Some("Review Transactions"),
-            Some("Confirm before executing."),
+            Some(&body),
         );
-        dialog.set_extra_child(Some(&scroller));
+        dialog.set_extra_child(Some(&extra));
         dialog.add_response("cancel", "Cancel");
+        if pending_updates > 0 {
+            dialog.add_response("upgrade", "Upgrade & Install");
+            dialog.set_response_appearance("upgrade", adw::ResponseAppearance::Suggested);
+        }
         dialog.add_response("execute", "Execute");
         dialog.set_response_appearance("execute", adw::ResponseAppearance::Suggested);
+        if let Some(confirm) = &confirm_removal {
+            dialog.set_response_appearance("execute", adw::ResponseAppearance::Destructive);
+            for response in ["execute", "upgrade"] {
+                if dialog.has_response(response) {
+                    dialog.set_response_enabled(response, false);
+                }
+            }
+            let dialog_clone = dialog.clone();
+            confirm.connect_toggled(move |confirm| {
+                for response in ["execute", "upgrade"] {
+                    if dialog_clone.has_response(response) {
+                        dialog_clone.set_response_enabled(response, confirm.is_active());
+                    }
+                }
+            });
+        }
 
         let ctx = self.ctx.clone();
         let log_drawer = self.log_drawer.clone();
         let parent = self.parent.clone();
-        let button = self.button.clone();
+        let controller = self.clone();
         let toasts = self.toasts.clone();
 
+        let newest_news = news.iter().map(|item| item.published).max();
+
         dialog.connect_response(None, move |d: &adw::MessageDialog, resp| {
-            if resp == "execute" {
-                let plan = plan_transactions(&queue, &ctx.settings.lock().unwrap());
-                let started = run_plan(plan, &ctx, &log_drawer, &parent, &toasts);
-                if started {
-                    ctx.queue.lock().unwrap().clear();
-                    button.set_label("Queue (0)");
+            if resp == "execute" || resp == "upgrade" {
+                if let Some(published) = newest_news {
+                    let mut settings = ctx.settings.lock().unwrap();
+                    settings.news_last_seen = settings.news_last_seen.max(published);
+                    let _ = save_settings(&settings);
+                }
+                let upgrades = resp == "upgrade" || queue.has_system_upgrade();
+                let mut queue = queue.clone();
+                if resp == "upgrade" {
+                    // Queued repo installs are planned as targets of this upgrade.
+                    queue.push(TransactionAction {
+                        name: "system".to_string(),
+                        source: PackageSource::Repo,
+                        kind: ActionKind::Upgrade,
+                        origin: None,
+                        delete_data: false,
+                        unused_runtimes: false,
+                    });
+                }
+                let execute = clone!(@strong ctx, @strong log_drawer, @strong parent,
+                    @strong toasts, @strong controller => move || {
+                    let plan = plan_transactions(&queue, &ctx.settings.lock().unwrap());
+                    let started = run_plan(plan, &ctx, &log_drawer, &parent, &toasts);
+                    if started {
+                        controller.clear();
+                    }
+                });
+                if blocking_news.is_empty() || !upgrades {
+                    execute();
+                } else {
+                    show_news_gate(&parent, &blocking_news, ctx.clone(), execute);
                 }
             }
             d.close();
@@ -243,30 +912,80 @@ impl QueueController {
     }
 }
 
+// "3 installs, 1 removal, system upgrade": whole-system upgrades are named rather than counted.
+fn queue_summary(queue: &TransactionQueue) -> String {
+    if queue.is_empty() {
+        return String::from("Queue is empty");
+    }
+    let count = |kind: ActionKind| queue.actions.iter().filter(|action| action.kind == kind).count();
+    let mut updates = 0;
+    let mut full_upgrades = Vec::new();
+    for action in queue.actions.iter().filter(|action| action.kind == ActionKind::Upgrade) {
+        let full = match action.source {
+            PackageSource::Repo => action.name.is_empty() || action.name == "system",
+            PackageSource::Aur => true,
+            PackageSource::Flatpak => matches!(action.name.as_str(), "" | "flatpak" | "all"),
+        };
+        if !full {
+            updates += 1;
+            continue;
+        }
+        let label = match action.source {
+            PackageSource::Repo => "system upgrade",
+            PackageSource::Aur => "AUR upgrade",
+            PackageSource::Flatpak => "Flatpak update",
+        };
+        if !full_upgrades.contains(&label) {
+            full_upgrades.push(label);
+        }
+    }
+
+    let counted = [
+        (count(ActionKind::Install), "install", "installs"),
+        (count(ActionKind::Reinstall), "reinstall", "reinstalls"),
+        (updates, "update", "updates"),
+        (count(ActionKind::Remove), "removal", "removals"),
+    ];
+    let mut parts: Vec<String> = counted
+        .into_iter()
+        .filter(|(n, _, _)| *n > 0)
+        .map(|(n, one, many)| format!("{n} {}", if n == 1 { one } else { many }))
+        .collect();
+    parts.extend(full_upgrades.into_iter().map(str::to_string));
+    parts.join(", ")
+}
+
 pub fn build_ui(app: &adw::Application) {
     let _ = ensure_cache_dirs();
 
     let settings = load_settings();
     let initial_theme = settings.theme;
+    let queue_panel_docked = settings.queue_panel_docked;
+    let (width, height) = settings.window_size();
+    let maximized = settings.window_maximized;
     let settings_arc = Arc::new(Mutex::new(settings));
-    let ctx = AppContext {
-        pacman: Arc::new(Pacman::default()),
-        aur: Arc::new(Aur::new(settings_arc.clone())),
-        flatpak: Arc::new(Flatpak::default()),
-        appstream: Arc::new(AppStreamClient::default()),
-        settings: settings_arc,
-        queue: Arc::new(Mutex::new(TransactionQueue::default())),
-        runner: Arc::new(CommandRunner::default()),
-        transaction_in_progress: Arc::new(Mutex::new(false)),
-    };
+    let ctx = AppContext::new(settings_arc);
 
     let window = adw::ApplicationWindow::builder()
         .application(app)
         .title("Aurora")
-        .default_width(1200)
-        .default_height(800)
+        .default_width(width)
+        .default_height(height)
+        .maximized(maximized)
         .build();
 
+    let ctx_for_close = ctx.clone();
+    window.connect_close_request(move |window| {
+        let mut settings = ctx_for_close.settings.lock().unwrap();
+        settings.window_maximized = window.is_maximized();
+        // The default size is the unmaximized size, so it is kept while maximized.
+        let (width, height) = window.default_size();
+        settings.window_width = width;
+        settings.window_height = height;
+        let _ = save_settings(&settings);
+        glib::Propagation::Proceed
+    });
+
     if let Some(display) = gdk::Display::default() {
         let icon_theme = gtk::IconTheme::for_display(&display);
         icon_theme.add_search_path("assets/icons");
@@ -277,21 +996,38 @@ pub fn build_ui(app: &adw::Application) {
     header.add_css_class("aurora-header");
 
     let queue_button = gtk::Button::with_label("Queue (0)");
+    queue_button.set_tooltip_text(Some("Queue is empty"));
     queue_button.add_css_class("suggested-action");
     queue_button.add_css_class("queue-button");
     header.pack_end(&queue_button);
 
+    let undo_button = gtk::Button::from_icon_name("edit-undo-symbolic");
+    set_icon_label(&undo_button, "Undo last transaction");
+    header.pack_end(&undo_button);
+
+    let express_button = gtk::ToggleButton::new();
+    express_button.set_icon_name("media-seek-forward-symbolic");
+    set_icon_label(&express_button, "Express install: run installs immediately without review");
+    header.pack_end(&express_button);
+
+    let pkgbuild_button = gtk::Button::from_icon_name("folder-open-symbolic");
+    set_icon_label(&pkgbuild_button, "Build and install from a local PKGBUILD");
+    header.pack_end(&pkgbuild_button);
+
     let sidebar = gtk::ListBox::new();
     sidebar.add_css_class("navigation-sidebar");
     sidebar.add_css_class("aurora-nav");
     sidebar.set_selection_mode(gtk::SelectionMode::Single);
     sidebar.set_activate_on_single_click(true);
 
-    sidebar.append(&build_nav_row("go-home-symbolic", "Home"));
-    sidebar.append(&build_nav_row("system-search-symbolic", "Search"));
-    sidebar.append(&build_nav_row("drive-harddisk-symbolic", "Installed"));
-    sidebar.append(&build_nav_row("software-update-available-symbolic", "Updates"));
-    sidebar.append(&build_nav_row("emblem-system-symbolic", "Settings"));
+    let (updates_nav_row, updates_nav_label) =
+        build_nav_row("software-update-available-symbolic", "Updates");
+    sidebar.append(&build_nav_row("go-home-symbolic", "Home").0);
+    sidebar.append(&build_nav_row("system-search-symbolic", "Search").0);
+    sidebar.append(&build_nav_row("drive-harddisk-symbolic", "Installed").0);
+    sidebar.append(&updates_nav_row);
+    sidebar.append(&build_nav_row("dialog-warning-symbolic", "System Health").0);
+    sidebar.append(&build_nav_row("emblem-system-symbolic", "Settings").0);
 
     let sidebar_root = gtk::Box::new(gtk::Orientation::Vertical, 12);
     sidebar_root.add_css_class("sidebar-root");
@@ -339,12 +1075,14 @@ pub fn build_ui(app: &adw::Application) {
     let search_page = search::SearchPage::new();
     let installed_page = installed::InstalledPage::new();
     let updates_page = updates::UpdatesPage::new();
+    let health_page = health::HealthPage::new();
     let settings_page = settings::SettingsPage::new();
 
     stack.add_named(&home_page.root, Some("home"));
     stack.add_named(&search_page.root, Some("search"));
     stack.add_named(&installed_page.root, Some("installed"));
     stack.add_named(&updates_page.root, Some("updates"));
+    stack.add_named(&health_page.root, Some("health"));
     stack.add_named(&settings_page.root, Some("settings"));
     stack.set_visible_child_name("home");
 
@@ -370,12 +1108,15 @@ pub fn build_ui(app: &adw::Application) {
     split.set_content(Some(&content_page));
     sidebar.select_row(sidebar.row_at_index(0).as_ref());
 
-    let log_drawer = widgets::log_drawer::LogDrawer::new();
+    let log_drawer = widgets::log_drawer::LogDrawer::new(ctx.settings.clone());
     let toast_overlay = adw::ToastOverlay::new();
+    let queue_panel = widgets::queue_panel::QueuePanel::new();
+    queue_panel.set_docked(queue_panel_docked);
 
     let queue_controller = QueueController::new(
         ctx.clone(),
         queue_button.clone(),
+        queue_panel.clone(),
         log_drawer.clone(),
         window.clone(),
         toast_overlay.clone(),
@@ -388,10 +1129,17 @@ pub fn build_ui(app: &adw::Application) {
         toasts: toast_overlay.clone(),
     };
 
+    let self_update_banner = adw::Banner::new("");
+    self_update_banner.set_button_label(Some("Update Aurora"));
+
     let toolbar_view = adw::ToolbarView::new();
     toolbar_view.add_css_class("aurora-toolbar");
     toolbar_view.add_top_bar(&header);
-    toolbar_view.set_content(Some(&split));
+    toolbar_view.add_top_bar(&self_update_banner);
+    let workspace = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    workspace.append(&split);
+    workspace.append(queue_panel.widget());
+    toolbar_view.set_content(Some(&workspace));
     toolbar_view.set_vexpand(true);
 
     let content_overlay = gtk::Overlay::new();
@@ -447,24 +1195,65 @@ pub fn build_ui(app: &adw::Application) {
         queue_controller.show_review_dialog();
     }));
 
+    queue_panel.connect_execute(clone!(@strong queue_controller => move || {
+        queue_controller.show_review_dialog();
+    }));
+
+    queue_panel.connect_clear(clone!(@strong queue_controller => move || {
+        queue_controller.clear();
+    }));
+
+    queue_panel.connect_remove(clone!(@strong queue_controller => move |action| {
+        queue_controller.remove_action(&action);
+    }));
+
+    undo_button.connect_clicked(clone!(@strong queue_controller => move |_| {
+        queue_controller.undo_last_transaction();
+    }));
+
+    express_button.connect_toggled(clone!(@strong queue_controller => move |button| {
+        queue_controller.set_express(button.is_active());
+    }));
+
+    pkgbuild_button.connect_clicked(clone!(@strong queue_controller, @strong window => move |_| {
+        let dialog = gtk::FileDialog::new();
+        dialog.set_title("Choose a PKGBUILD Directory");
+        let controller = queue_controller.clone();
+        dialog.select_folder(Some(&window), gtk::gio::Cancellable::NONE, move |res| {
+            if let Some(dir) = res.ok().and_then(|folder| folder.path()) {
+                controller.build_pkgbuild(dir);
+            }
+        });
+    }));
+
+    let installed_for_updates = installed_page.clone();
     let ctx_for_sidebar = ctx.clone();
     let stack_for_sidebar = stack.clone();
     let handles_for_sidebar = handles.clone();
     let nav_for_sidebar = nav_view.clone();
     let main_page_for_sidebar = main_page.clone();
+    let health_for_sidebar = health_page.clone();
+    let home_for_sidebar = home_page.clone();
     sidebar.connect_row_selected(move |_, row| {
         if let Some(row) = row {
             let _ = nav_for_sidebar.pop_to_page(&main_page_for_sidebar);
             let index = row.index();
             match index {
-                0 => stack_for_sidebar.set_visible_child_name("home"),
+                0 => {
+                    stack_for_sidebar.set_visible_child_name("home");
+                    home_for_sidebar.load_favorites(ctx_for_sidebar.clone(), handles_for_sidebar.clone());
+                }
                 1 => stack_for_sidebar.set_visible_child_name("search"),
                 2 => {
                     stack_for_sidebar.set_visible_child_name("installed");
                     installed_page.refresh(ctx_for_sidebar.clone(), handles_for_sidebar.clone());
                 }
                 3 => stack_for_sidebar.set_visible_child_name("updates"),
-                4 => stack_for_sidebar.set_visible_child_name("settings"),
+                4 => {
+                    stack_for_sidebar.set_visible_child_name("health");
+                    health_for_sidebar.refresh(ctx_for_sidebar.clone());
+                }
+                5 => stack_for_sidebar.set_visible_child_name("settings"),
                 _ => {}
             }
         }
@@ -479,44 +1268,130 @@ pub fn build_ui(app: &adw::Application) {
     }));
 
     updates_page.bind(ctx.clone());
-    settings_page.bind(ctx.clone());
+    health_page.bind(ctx.clone());
+    settings_page.bind(ctx.clone(), handles.clone());
     search_page.bind_search(ctx.clone(), handles.clone(), stack.clone());
-    home_page.bind(ctx.clone());
-
-    let updates_page_refresh = updates_page.clone();
-    let ctx_updates = ctx.clone();
-    let toasts_updates = handles.toasts.clone();
-    updates_page_refresh.refresh(ctx_updates.clone(), Some(toasts_updates.clone()));
-    glib::timeout_add_local(Duration::from_secs(1800), move || {
-        updates_page_refresh.refresh(ctx_updates.clone(), Some(toasts_updates.clone()));
-        ControlFlow::Continue
+    home_page.bind(ctx.clone(), handles.clone());
+
+    updates_page.connect_updates_changed(clone!(@strong installed_for_updates, @strong ctx,
+        @strong handles => move |items| {
+        installed_for_updates.set_updates(items, &handles, &ctx);
+    }));
+
+    updates_page.connect_count_changed(move |count| {
+        if count > 0 {
+            updates_nav_label.set_label(&format!("Updates ({count})"));
+        } else {
+            updates_nav_label.set_label("Updates");
+        }
+    });
+
+    updates_page.refresh(ctx.clone(), Some(handles.toasts.clone()));
+    schedule_update_poll(updates_page.clone(), ctx.clone(), handles.toasts.clone());
+    check_for_self_update(&self_update_banner, &ctx, &handles);
+    // Planning reads the cached result, so the helpers are probed once, off the main loop.
+    std::thread::spawn(|| {
+        for helper in [AurHelperKind::Yay, AurHelperKind::Paru] {
+            probe_sudo_wiring(helper);
+        }
     });
 
     window.present();
 }
 
-fn run_search(query: String, ctx: AppContext, search_page: search::SearchPage, handles: UiHandles) {
+fn check_for_self_update(banner: &adw::Banner, ctx: &AppContext, handles: &UiHandles) {
+    if ctx.settings.lock().unwrap().offline {
+        return;
+    }
+    let (tx, rx) = mpsc::channel();
+    let runner = ctx.runner.clone();
+    std::thread::spawn(move || {
+        // Network or pacman failures just mean no banner.
+        let _ = tx.send(check_self_update(&runner).ok().flatten());
+    });
+
+    let banner = banner.clone();
+    let queue = handles.queue.clone();
+    glib::idle_add_local(move || match rx.try_recv() {
+        Ok(Some(update)) => {
+            banner.set_title(&format!(
+                "Aurora {} is available (installed: {})",
+                update.available, update.installed
+            ));
+            let queue = queue.clone();
+            banner.connect_button_clicked(move |banner| {
+                queue.add_upgrade_packages(vec![TransactionAction {
+                    name: update.package.clone(),
+                    source: PackageSource::Aur,
+                    kind: ActionKind::Install,
+                    origin: None,
+                    delete_data: false,
+                    unused_runtimes: false,
+                }]);
+                banner.set_revealed(false);
+            });
+            banner.set_revealed(true);
+            ControlFlow::Break
+        }
+        Ok(None) => ControlFlow::Break,
+        Err(mpsc::TryRecvError::Empty) => ControlFlow::Continue,
+        Err(mpsc::TryRecvError::Disconnected) => ControlFlow::Break,
+    });
+}
+
+fn run_search(
+    query: String,
+    mode: SearchMode,
+    ctx: AppContext,
+    search_page: search::SearchPage,
+    handles: UiHandles,
+) {
+    let generation = search_page.generation();
+    let current = generation.start();
     let (tx, rx) = std::sync::mpsc::channel();
     let ctx_thread = ctx.clone();
     std::thread::spawn(move || {
-        let mut pacman_results = ctx_thread.pacman.search(&query).unwrap_or_default();
-        let mut aur = ctx_thread.aur.search(&query).unwrap_or_default();
-        let mut flatpak = ctx_thread.flatpak.search(&query).unwrap_or_default();
+        let mut notices = Vec::new();
+        if mode == SearchMode::File {
+            let result = file_owner_results(&ctx_thread, &query);
+            let files_db_missing = matches!(result, Err(ProviderError::FilesDatabaseMissing));
+            let results = if files_db_missing {
+                Vec::new()
+            } else {
+                search_source(result, "File", &mut notices)
+            };
+            let _ = tx.send((results, notices, files_db_missing));
+            return;
+        }
+        let mut pacman_results =
+            search_source(ctx_thread.pacman.search(&query, mode), "Repo", &mut notices);
+        let group = group_result(&ctx_thread, &query);
+        let settings = ctx_thread.settings.lock().unwrap().clone();
+        let mut aur = if settings.offline || !settings.enable_aur {
+            Vec::new()
+        } else {
+            search_source(ctx_thread.aur.search(&query, mode), "AUR", &mut notices)
+        };
+        let mut flatpak = if settings.enable_flatpak {
+            search_source(ctx_thread.flatpak.search(&query, mode), "Flatpak", &mut notices)
+        } else {
+            Vec::new()
+        };
 
-        let pacman_installed: HashSet<String> = ctx_thread
-            .pacman
-            .list_installed()
-            .unwrap_or_default()
-            .into_iter()
-            .map(|pkg| pkg.name)
+        let pacman_names: Vec<String> = pacman_results
+            .iter()
+            .chain(aur.iter())
+            .map(|pkg| pkg.name.clone())
             .collect();
-        let flatpak_installed: HashSet<String> = ctx_thread
+        let pacman_installed = ctx_thread
+            .pacman
+            .installed_among(&pacman_names)
+            .unwrap_or_default();
+        let flatpak_names: Vec<String> = flatpak.iter().map(|pkg| pkg.name.clone()).collect();
+        let flatpak_installed = ctx_thread
             .flatpak
-            .list_installed()
-            .unwrap_or_default()
-            .into_iter()
-            .map(|pkg| pkg.name)
-            .collect();
+            .installed_among(&flatpak_names)
+            .unwrap_or_default();
 
         for pkg in &mut pacman_results {
             pkg.installed = pacman_installed.contains(&pkg.name);
@@ -528,8 +1403,7 @@ fn run_search(query: String, ctx: AppContext, search_page: search::SearchPage, h
             pkg.installed = flatpak_installed.contains(&pkg.name);
         }
 
-        let mut dedup: HashMap<(PackageSource, String), crate::core::models::PackageSummary> =
-            HashMap::new();
+        let mut dedup: HashMap<(PackageSource, String), PackageSummary> = HashMap::new();
         for pkg in pacman_results
             .into_iter()
             .chain(aur.into_iter())
@@ -538,20 +1412,169 @@ fn run_search(query: String, ctx: AppContext, search_page: search::SearchPage, h
             let key = (pkg.source, pkg.name.clone());
             dedup.insert(key, pkg);
         }
+        if let Some(group) = group {
+            dedup
+                .entry((group.source, group.name.clone()))
+                .or_insert(group);
+        }
+
+        let mut results: Vec<_> = dedup.into_values().collect();
+        results.sort_by(|a, b| a.name.cmp(&b.name));
+        let _ = tx.send((results, notices, false));
+    });
+
+    glib::idle_add_local(move || match rx.try_recv() {
+        _ if !generation.is_current(current) => ControlFlow::Break,
+        Ok((_, _, true)) => {
+            search_page.show_message(
+                "The files database has not been downloaded yet, so files cannot be looked up.",
+            );
+            let toast = adw::Toast::new("File lookups need the pacman files database");
+            toast.set_button_label(Some("Download"));
+            toast.set_timeout(0);
+            let ctx = ctx.clone();
+            let handles_for_sync = handles.clone();
+            toast.connect_button_clicked(move |_| {
+                let cmd = sync_files_db_command(&ctx.settings.lock().unwrap());
+                handles_for_sync.queue.run_commands(vec![cmd]);
+            });
+            handles.toasts.add_toast(toast);
+            ControlFlow::Break
+        }
+        Ok((results, notices, _)) => {
+            search_page.set_results(results, mode, &ctx, &handles);
+            for notice in notices {
+                handles.toasts.add_toast(adw::Toast::new(&notice));
+            }
+            ControlFlow::Break
+        }
+        Err(std::sync::mpsc::TryRecvError::Empty) => ControlFlow::Continue,
+        Err(std::sync::mpsc::TryRecvError::Disconnected) => ControlFlow::Break,
+    });
+}
+
+// -Ss never lists groups, so a query that names one gets an entry of its own.
+fn group_result(ctx: &AppContext, query: &str) -> Option<PackageSummary> {
+    let name = query.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+    let members = ctx.pacman.group_members(name).ok()?;
+    if members.is_empty() {
+        return None;
+    }
+    Some(PackageSummary {
+        name: name.to_string(),
+        summary: format!("Package group: {}", members.join(", ")),
+        version: String::new(),
+        source: PackageSource::Repo,
+        installed: false,
+        origin: None,
+        popularity: None,
+        group_size: Some(members.len()),
+        install_date: None,
+    })
+}
+
+fn file_owner_results(ctx: &AppContext, path: &str) -> Result<Vec<PackageSummary>, ProviderError> {
+    let owners = ctx.pacman.owner_of_file(path)?;
+    let installed = ctx.pacman.installed_among(&owners).unwrap_or_default();
+    Ok(owners
+        .into_iter()
+        .map(|name| PackageSummary {
+            installed: installed.contains(&name),
+            name,
+            summary: format!("Provides {path}"),
+            version: String::new(),
+            source: PackageSource::Repo,
+            origin: None,
+            popularity: None,
+            group_size: None,
+            install_date: None,
+        })
+        .collect())
+}
+
+fn search_source(
+    result: Result<Vec<PackageSummary>, ProviderError>,
+    label: &str,
+    notices: &mut Vec<String>,
+) -> Vec<PackageSummary> {
+    match result {
+        Ok(results) => results,
+        // pacman and the AUR helpers exit 1 without output when nothing matches.
+        Err(ProviderError::CommandFailed {
+            code: Some(1),
+            stderr,
+            ..
+        }) if stderr.is_empty() => Vec::new(),
+        Err(ProviderError::NotInstalled(program)) if program == "flatpak" => Vec::new(),
+        Err(err) if err.is_not_installed() => {
+            notices.push(format!("{err}; {label} results are unavailable"));
+            Vec::new()
+        }
+        Err(err) => {
+            notices.push(format!("{label} search failed: {err}"));
+            Vec::new()
+        }
+    }
+}
+
+// Unread news posted since the last -Syu often announces a manual intervention, so the
+// upgrade waits for an explicit acknowledgement.
+fn show_news_gate(
+    parent: &adw::ApplicationWindow,
+    items: &[NewsItem],
+    ctx: AppContext,
+    on_continue: impl Fn() + 'static,
+) {
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 12);
+    for item in items {
+        let title = gtk::Label::new(Some(&item.title));
+        title.add_css_class("heading");
+        title.set_xalign(0.0);
+        title.set_wrap(true);
+        content.append(&title);
+        if !item.summary.is_empty() {
+            let summary = gtk::Label::new(Some(&item.summary));
+            summary.set_xalign(0.0);
+            summary.set_wrap(true);
+            summary.set_selectable(true);
+            content.append(&summary);
+        }
+        if !item.link.is_empty() {
+            let link = gtk::LinkButton::with_label(&item.link, "Read on archlinux.org");
+            link.set_halign(gtk::Align::Start);
+            content.append(&link);
+        }
+    }
+    let scroller = gtk::ScrolledWindow::new();
+    scroller.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+    scroller.set_min_content_height(200);
+    scroller.set_max_content_height(480);
+    scroller.set_child(Some(&content));
 
-        let mut results: Vec<_> = dedup.into_values().collect();
-        results.sort_by(|a, b| a.name.cmp(&b.name));
-        let _ = tx.send(results);
-    });
-
-    glib::idle_add_local(move || match rx.try_recv() {
-        Ok(results) => {
-            search_page.set_results(results, &ctx, &handles);
-            ControlFlow::Break
+    let dialog = adw::MessageDialog::new(
+        Some(parent),
+        Some("Read the Arch News Before Upgrading"),
+        Some("These news items were posted since your last system upgrade and may require manual intervention."),
+    );
+    dialog.set_extra_child(Some(&scroller));
+    dialog.add_response("cancel", "Not Now");
+    dialog.add_response("continue", "I've Read This, Continue");
+    dialog.set_response_appearance("continue", adw::ResponseAppearance::Destructive);
+    dialog.set_default_response(Some("cancel"));
+    dialog.set_close_response("cancel");
+    let newest = items.iter().map(|item| item.published).max().unwrap_or(0);
+    dialog.connect_response(Some("continue"), move |_, _| {
+        {
+            let mut settings = ctx.settings.lock().unwrap();
+            settings.news_acknowledged = settings.news_acknowledged.max(newest);
+            let _ = save_settings(&settings);
         }
-        Err(std::sync::mpsc::TryRecvError::Empty) => ControlFlow::Continue,
-        Err(std::sync::mpsc::TryRecvError::Disconnected) => ControlFlow::Break,
+        on_continue();
     });
+    dialog.present();
 }
 
 fn run_plan(
@@ -582,10 +1605,7 @@ fn run_plan(
             *ctx.transaction_in_progress.lock().unwrap() = false;
             toasts.add_toast(adw::Toast::new("Failed to check package manager status"));
             log_drawer.set_visible(true);
-            log_drawer.append_line(
-                &format!("Failed to check active package managers: {err}"),
-                ctx.runner.log_limit,
-            );
+            log_drawer.append_line(&format!("Failed to check active package managers: {err}"));
             return false;
         }
     };
@@ -596,30 +1616,67 @@ fn run_plan(
             "Another package manager process is already running",
         ));
         log_drawer.set_visible(true);
-        log_drawer.append_line(
-            &format!(
-                "Refusing to start: active package manager process detected: {}",
-                active_managers.join(", ")
-            ),
-            ctx.runner.log_limit,
-        );
+        log_drawer.append_line(&format!(
+            "Refusing to start: active package manager process detected: {}",
+            active_managers.join(", ")
+        ));
         return false;
     }
 
-    if Path::new("/var/lib/pacman/db.lck").exists() {
+    if let Some(age) = pacman_lock_age() {
         *ctx.transaction_in_progress.lock().unwrap() = false;
         toasts.add_toast(adw::Toast::new("Pacman lock file present"));
         log_drawer.set_visible(true);
-        log_drawer.append_line(
-            "Refusing to start because /var/lib/pacman/db.lck exists. Use the Clear Lock button in Logs.",
-            ctx.runner.log_limit,
-        );
+        log_drawer.append_line(&format!(
+            "Refusing to start because {PACMAN_LOCK} exists (created {} ago). \
+             Use the Clear Lock button in Logs.",
+            format_age(age)
+        ));
+        return false;
+    }
+
+    let escalation = ctx.settings.lock().unwrap().escalation;
+    let needs_escalation = plan
+        .commands
+        .iter()
+        .any(|cmd| cmd.program == escalation.as_str());
+    if needs_escalation && !command_exists(escalation.as_str()) {
+        *ctx.transaction_in_progress.lock().unwrap() = false;
+        toasts.add_toast(adw::Toast::new(&format!(
+            "{} is not installed",
+            escalation.as_str()
+        )));
+        log_drawer.set_visible(true);
+        log_drawer.append_line(&format!(
+            "Refusing to start: {} was not found in PATH. Install it or change Privilege Escalation in Settings.",
+            escalation.as_str()
+        ));
         return false;
     }
 
     log_drawer.clear();
     log_drawer.set_visible(true);
 
+    if ctx.settings.lock().unwrap().dry_run {
+        log_drawer.append_line("Dry run: the following commands would be executed");
+        for cmd in &plan.commands {
+            log_drawer.append_line(&format!("$ {}", cmd.display_line()));
+        }
+        *ctx.transaction_in_progress.lock().unwrap() = false;
+        toasts.add_toast(adw::Toast::new("Dry run complete; queue kept"));
+        return false;
+    }
+
+    // Snapshot installed versions so the summary can list what the transaction changed.
+    let touches_pacman = plan
+        .actions
+        .iter()
+        .any(|action| action.source != PackageSource::Flatpak);
+    let version_baseline: Rc<RefCell<Option<BTreeMap<String, String>>>> =
+        Rc::new(RefCell::new(None));
+    let pacman = ctx.pacman.clone();
+    let baseline = version_baseline.clone();
+    let executed_actions = plan.actions;
     let commands = Rc::new(RefCell::new(plan.commands));
     let ctx_clone = ctx.clone();
     let log_drawer = log_drawer.clone();
@@ -628,6 +1685,7 @@ fn run_plan(
     let prompt_open = Rc::new(RefCell::new(false));
     let lock_hint_shown = Rc::new(RefCell::new(false));
     let in_progress = ctx_clone.transaction_in_progress.clone();
+    let outcomes: Rc<RefCell<Vec<CommandOutcome>>> = Rc::new(RefCell::new(Vec::new()));
 
     let next: Rc<RefCell<Option<Box<dyn Fn()>>>> = Rc::new(RefCell::new(None));
     let next_clone = next.clone();
@@ -636,39 +1694,53 @@ fn run_plan(
         let mut cmds = commands.borrow_mut();
         if cmds.is_empty() {
             *in_progress.lock().unwrap() = false;
-            let dialog = adw::MessageDialog::new(
-                Some(&parent),
-                Some("Transactions complete"),
-                Some("All actions finished."),
-            );
-            dialog.add_response("ok", "OK");
-            dialog.connect_response(None, |d: &adw::MessageDialog, _| d.close());
-            dialog.present();
+            if !executed_actions.is_empty() {
+                let _ = save_last_transaction(&executed_actions);
+            }
+            let before = version_baseline.borrow().clone();
+            with_changes_since(pacman.clone(), before, clone!(@strong parent,
+                @strong outcomes => move |changes| {
+                show_summary_dialog(&parent, &outcomes.borrow(), &[], &changes, None, None);
+            }));
             toasts.add_toast(adw::Toast::new("Transactions complete"));
             return;
         }
         let cmd = cmds.remove(0);
+        drop(cmds);
+        let failed_cmd = cmd.clone();
         let command_trace = format!("$ {}", cmd.display_line());
         let (tx, rx) = mpsc::channel();
         let (input_tx, input_rx) = mpsc::channel();
         let runner = ctx_clone.runner.clone();
-        let log_limit = runner.log_limit;
-        log_drawer.append_line(&command_trace, log_limit);
+        log_drawer.append_line(&command_trace);
 
-        let (terminal_mode, terminal_emulator) = {
+        let (terminal_mode, terminal_emulator, prompt_timeout, needs_tty) = {
             let settings = ctx_clone.settings.lock().unwrap();
-            (settings.terminal_mode, settings.terminal_emulator)
+            let needs_tty = prompts_for_sudo(&cmd, &settings);
+            let prompt_timeout = (settings.prompt_timeout_secs > 0).then(|| {
+                (
+                    settings.prompt_timeout_secs,
+                    settings.prompt_timeout_response.clone(),
+                )
+            });
+            (settings.terminal_mode, settings.terminal_emulator, prompt_timeout, needs_tty)
+        };
+        // sudo reads the password from a tty, which the integrated log cannot provide.
+        let terminal_mode = if needs_tty && terminal_mode == TerminalMode::Integrated {
+            log_drawer.append_line(
+                "sudo needs a terminal for its password prompt; using an external terminal",
+            );
+            TerminalMode::External
+        } else {
+            terminal_mode
         };
 
         let start_result = match terminal_mode {
             TerminalMode::External => {
-                log_drawer.append_line(
-                    &format!(
-                        "Launching command in external terminal ({})",
-                        terminal_emulator.label()
-                    ),
-                    log_limit,
-                );
+                log_drawer.append_line(&format!(
+                    "Launching command in external terminal ({})",
+                    terminal_emulator.label()
+                ));
                 runner.run_external_terminal(cmd, terminal_emulator, tx)
             }
             TerminalMode::Integrated => runner.run_streaming(cmd, tx, Some(input_rx)),
@@ -677,7 +1749,7 @@ fn run_plan(
         if let Err(err) = start_result {
             *in_progress.lock().unwrap() = false;
             toasts.add_toast(adw::Toast::new("Failed to start command"));
-            log_drawer.append_line(&format!("Failed to start command: {err}"), log_limit);
+            log_drawer.append_line(&format!("Failed to start command: {err}"));
             return;
         }
         let next_inner = next_clone.clone();
@@ -687,21 +1759,44 @@ fn run_plan(
         let prompt_open = prompt_open.clone();
         let lock_hint_shown = lock_hint_shown.clone();
         let in_progress = in_progress.clone();
+        let commands = commands.clone();
+        let outcomes = outcomes.clone();
+        let pacman = pacman.clone();
+        let version_baseline = version_baseline.clone();
+        let command_log: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
+        let conflicts: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
+        let rejection: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+        let mirror_failed = Rc::new(Cell::new(false));
+        let refresh = refresh_mirrors_command(&ctx_clone.settings.lock().unwrap());
         let allow_prompt_dialog = terminal_mode == TerminalMode::Integrated;
         glib::idle_add_local(move || match rx.try_recv() {
             Ok(event) => {
                 match event {
-                    LogEvent::Line(line) => {
+                    LogEvent::Line(raw) => {
+                        log_drawer.append_line(&raw);
+                        let line = strip_ansi(&raw);
                         if allow_prompt_dialog && should_prompt(&line) && !*prompt_open.borrow() {
                             *prompt_open.borrow_mut() = true;
                             show_prompt_dialog(
                                 &parent,
                                 &line,
+                                &parse_provider_options(&command_log.borrow()),
                                 input_tx.clone(),
                                 prompt_open.clone(),
+                                &log_drawer,
+                                prompt_timeout.clone(),
                             );
                         }
-                        log_drawer.append_line(&line, log_limit);
+                        command_log.borrow_mut().push(line.clone());
+                        if let Some(path) = parse_conflict_path(&line) {
+                            conflicts.borrow_mut().push(path);
+                        }
+                        if let Some(reason) = parse_helper_rejection(&line) {
+                            *rejection.borrow_mut() = Some(reason.to_string());
+                        }
+                        if is_mirror_failure(&line) {
+                            mirror_failed.set(true);
+                        }
                         if !*lock_hint_shown.borrow() {
                             let lower = line.to_lowercase();
                             if lower.contains("unable to lock database")
@@ -709,20 +1804,134 @@ fn run_plan(
                                 || lower.contains("/var/lib/pacman/db.lck")
                             {
                                 *lock_hint_shown.borrow_mut() = true;
-                                log_drawer.append_line(
-                                    "Hint: pacman lock detected. If no package manager is running, remove it with: sudo rm -f /var/lib/pacman/db.lck",
-                                    log_limit,
-                                );
+                                let age = pacman_lock_age()
+                                    .map(|age| format!(" It was created {} ago.", format_age(age)))
+                                    .unwrap_or_default();
+                                log_drawer.append_line(&format!(
+                                    "Hint: pacman lock detected.{age} If no package manager is running, use the Clear Lock button in Logs.",
+                                ));
                                 toasts.add_toast(adw::Toast::new("Pacman lock file detected"));
                             }
                         }
                     }
+                    LogEvent::Finished(code) if is_auth_cancelled(&failed_cmd, code) => {
+                        *in_progress.lock().unwrap() = false;
+                        log_drawer.append_line("Authentication cancelled; the command was not run");
+                        let remaining = commands.borrow().len();
+                        if remaining > 0 {
+                            log_drawer.append_line(&format!(
+                                "Skipped {remaining} remaining command(s)"
+                            ));
+                        }
+                        toasts.add_toast(adw::Toast::new("Authentication cancelled"));
+                        return ControlFlow::Break;
+                    }
                     LogEvent::Finished(code) => {
+                        outcomes.borrow_mut().push(CommandOutcome {
+                            command: failed_cmd.display_line(),
+                            code,
+                            log: command_log.take(),
+                        });
                         if code != 0 {
                             *in_progress.lock().unwrap() = false;
-                            toasts.add_toast(adw::Toast::new(&format!(
-                                "Command failed ({code})"
-                            )));
+                            // A refused command would be refused again, so no retry is offered.
+                            let blocked = rejection.borrow_mut().take();
+                            let overwrite = match &blocked {
+                                Some(reason) => {
+                                    let message =
+                                        format!("Aurora blocked an unsafe command: {reason}");
+                                    log_drawer.append_line(&message);
+                                    toasts.add_toast(adw::Toast::new(&message));
+                                    None
+                                }
+                                None if is_auth_failure(&failed_cmd, code) => {
+                                    let message = "Authorization failed; the command was not run";
+                                    log_drawer.append_line(message);
+                                    toasts.add_toast(adw::Toast::new(message));
+                                    None
+                                }
+                                None => {
+                                    toasts.add_toast(adw::Toast::new(&format!(
+                                        "Command failed ({code})"
+                                    )));
+                                    with_overwrite(&failed_cmd, &conflicts.borrow())
+                                }
+                            };
+                            let paths = conflicts.borrow().clone();
+                            if let Some(retry) = overwrite {
+                                let commands = commands.clone();
+                                let next_retry = next_inner.clone();
+                                let in_progress = in_progress.clone();
+                                let toasts = toasts.clone();
+                                show_overwrite_dialog(&parent, &paths, move || {
+                                    if !claim_transaction(&in_progress, &toasts) {
+                                        return;
+                                    }
+                                    commands.borrow_mut().insert(0, retry.clone());
+                                    if let Some(next) = &*next_retry.borrow() {
+                                        next();
+                                    }
+                                });
+                            } else {
+                                let skipped: Vec<String> = commands
+                                    .borrow()
+                                    .iter()
+                                    .map(|cmd| cmd.display_line())
+                                    .collect();
+                                // The queue is already cleared, so the failed command and the
+                                // ones after it are kept here for a retry.
+                                let retry: Box<dyn Fn()> = Box::new(clone!(@strong commands,
+                                    @strong outcomes, @strong next_inner, @strong in_progress,
+                                    @strong toasts, @strong failed_cmd => move || {
+                                    if !claim_transaction(&in_progress, &toasts) {
+                                        return;
+                                    }
+                                    outcomes.borrow_mut().pop();
+                                    commands.borrow_mut().insert(0, failed_cmd.clone());
+                                    if let Some(next) = &*next_inner.borrow() {
+                                        next();
+                                    }
+                                }));
+                                // Offered only when reflector can actually pick new mirrors.
+                                let refresh_mirrors = (blocked.is_none()
+                                    && mirror_failed.get()
+                                    && command_exists("reflector"))
+                                .then(|| {
+                                    Box::new(clone!(@strong commands, @strong outcomes,
+                                        @strong next_inner, @strong in_progress,
+                                        @strong toasts, @strong failed_cmd, @strong refresh => move || {
+                                        if !claim_transaction(&in_progress, &toasts) {
+                                            return;
+                                        }
+                                        outcomes.borrow_mut().pop();
+                                        let mut commands = commands.borrow_mut();
+                                        commands.insert(0, failed_cmd.clone());
+                                        commands.insert(0, refresh.clone());
+                                        drop(commands);
+                                        if let Some(next) = &*next_inner.borrow() {
+                                            next();
+                                        }
+                                    })) as Box<dyn Fn()>
+                                });
+                                if mirror_failed.get() && refresh_mirrors.is_none() {
+                                    log_drawer.append_line(
+                                        "Hint: a mirror failed to serve a package. Install reflector to let Aurora pick new mirrors and retry.",
+                                    );
+                                }
+                                let retry = blocked.is_none().then_some(retry);
+                                let before = version_baseline.borrow().clone();
+                                with_changes_since(pacman.clone(), before, clone!(@strong parent,
+                                    @strong outcomes => move |changes| {
+                                    show_summary_dialog(
+                                        &parent,
+                                        &outcomes.borrow(),
+                                        &skipped,
+                                        &changes,
+                                        retry,
+                                        refresh_mirrors,
+                                    );
+                                }));
+                            }
                         } else if let Some(next) = &*next_inner.borrow() {
                             next();
                         } else {
@@ -741,15 +1950,331 @@ fn run_plan(
         });
     }) as Box<dyn Fn()>);
 
-    {
+    let start = move || {
         let next_ref = next.borrow();
         if let Some(next_fn) = next_ref.as_ref() {
             next_fn();
         }
+    };
+    if touches_pacman {
+        // The snapshot has to be taken before the first command changes anything.
+        installed_versions(ctx.pacman.clone(), move |versions| {
+            *baseline.borrow_mut() = versions;
+            start();
+        });
+    } else {
+        start();
+    }
+    true
+}
+
+type SizeLabels = Vec<(String, gtk::Label)>;
+
+// Sizes come from pacman's databases, so the dialog is shown first and filled in afterwards.
+fn load_review_sizes(
+    ctx: &AppContext,
+    downloads: SizeLabels,
+    removals: SizeLabels,
+    total: gtk::Label,
+) {
+    let pacman = ctx.pacman.clone();
+    let download_names: Vec<String> = downloads.iter().map(|(name, _)| name.clone()).collect();
+    let removal_names: Vec<String> = removals.iter().map(|(name, _)| name.clone()).collect();
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let download_sizes = pacman.download_sizes(&download_names);
+        let removal_sizes = pacman.installed_sizes(&removal_names);
+        let _ = tx.send((download_sizes, removal_sizes));
+    });
+
+    glib::idle_add_local(move || match rx.try_recv() {
+        Ok((download_sizes, removal_sizes)) => {
+            let mut parts = Vec::new();
+            if let Some(bytes) = fill_size_labels(&downloads, &download_sizes) {
+                parts.push(format!("Download: {}", format_size(bytes)));
+            }
+            if let Some(bytes) = fill_size_labels(&removals, &removal_sizes) {
+                parts.push(format!("Frees: {}", format_size(bytes)));
+            }
+            if parts.is_empty() {
+                total.set_text("Sizes unavailable");
+            } else {
+                total.set_text(&parts.join("  \u{2022}  "));
+            }
+            ControlFlow::Break
+        }
+        Err(mpsc::TryRecvError::Empty) => ControlFlow::Continue,
+        Err(mpsc::TryRecvError::Disconnected) => {
+            total.set_text("Sizes unavailable");
+            ControlFlow::Break
+        }
+    });
+}
+
+// Returns the summed size, or None when there were no rows or the lookup failed.
+fn fill_size_labels(
+    labels: &SizeLabels,
+    sizes: &Result<HashMap<String, u64>, ProviderError>,
+) -> Option<u64> {
+    if labels.is_empty() {
+        return None;
+    }
+    let Ok(sizes) = sizes else {
+        for (_, label) in labels {
+            label.set_text("unknown");
+        }
+        return None;
+    };
+    let mut sum = 0;
+    for (name, label) in labels {
+        match sizes.get(name) {
+            Some(bytes) => {
+                sum += bytes;
+                label.set_text(&format_size(*bytes));
+            }
+            None => label.set_text("unknown"),
+        }
+    }
+    Some(sum)
+}
+
+// Lists the installed versions on a worker thread and hands them to `done` on the main loop.
+fn installed_versions(
+    pacman: Arc<dyn PacmanProvider>,
+    done: impl FnOnce(Option<BTreeMap<String, String>>) + 'static,
+) {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let versions = pacman
+            .list_installed()
+            .ok()
+            .map(|installed| installed.into_iter().map(|pkg| (pkg.name, pkg.version)).collect());
+        let _ = tx.send(versions);
+    });
+    let mut done = Some(done);
+    glib::idle_add_local(move || match rx.try_recv() {
+        Ok(versions) => {
+            if let Some(done) = done.take() {
+                done(versions);
+            }
+            ControlFlow::Break
+        }
+        Err(mpsc::TryRecvError::Empty) => ControlFlow::Continue,
+        Err(mpsc::TryRecvError::Disconnected) => ControlFlow::Break,
+    });
+}
+
+fn with_changes_since(
+    pacman: Arc<dyn PacmanProvider>,
+    before: Option<BTreeMap<String, String>>,
+    done: impl FnOnce(Vec<VersionChange>) + 'static,
+) {
+    let Some(before) = before else {
+        done(Vec::new());
+        return;
+    };
+    installed_versions(pacman, move |after| {
+        done(after.map(|after| version_changes(&before, &after)).unwrap_or_default());
+    });
+}
+
+struct CommandOutcome {
+    command: String,
+    code: i32,
+    log: Vec<String>,
+}
+
+fn claim_transaction(in_progress: &Mutex<bool>, toasts: &adw::ToastOverlay) -> bool {
+    let mut running = in_progress.lock().unwrap();
+    if *running {
+        toasts.add_toast(adw::Toast::new(
+            "A transaction is already running. Wait for it to finish.",
+        ));
+        return false;
     }
+    *running = true;
     true
 }
 
+fn show_summary_dialog(
+    parent: &adw::ApplicationWindow,
+    outcomes: &[CommandOutcome],
+    skipped: &[String],
+    changes: &[VersionChange],
+    on_retry: Option<Box<dyn Fn()>>,
+    on_refresh_mirrors: Option<Box<dyn Fn()>>,
+) {
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    for outcome in outcomes {
+        let row = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+        let failed = outcome.code != 0;
+        let icon = gtk::Image::from_icon_name(if failed {
+            "dialog-error-symbolic"
+        } else {
+            "emblem-ok-symbolic"
+        });
+        let label = gtk::Label::new(Some(&outcome.command));
+        label.set_xalign(0.0);
+        label.set_hexpand(true);
+        label.set_ellipsize(gtk::pango::EllipsizeMode::Middle);
+        label.set_tooltip_text(Some(&outcome.command));
+        label.add_css_class("monospace");
+        let status = gtk::Label::new(Some(&if failed {
+            format!("failed ({})", outcome.code)
+        } else {
+            String::from("ok")
+        }));
+        if failed {
+            icon.add_css_class("error");
+            label.add_css_class("error");
+            status.add_css_class("error");
+        } else {
+            status.add_css_class("dim-label");
+        }
+        row.append(&icon);
+        row.append(&label);
+        row.append(&status);
+        if failed {
+            let copy = gtk::Button::from_icon_name("edit-copy-symbolic");
+            set_icon_label(&copy, "Copy log for this command");
+            copy.add_css_class("flat");
+            let mut text = format!("$ {}", outcome.command);
+            for line in &outcome.log {
+                text.push('\n');
+                text.push_str(line);
+            }
+            copy.connect_clicked(move |_| {
+                if let Some(display) = gdk::Display::default() {
+                    display.clipboard().set_text(&text);
+                }
+            });
+            row.append(&copy);
+        }
+        content.append(&row);
+    }
+    for command in skipped {
+        let row = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+        let label = gtk::Label::new(Some(command));
+        label.set_xalign(0.0);
+        label.set_hexpand(true);
+        label.set_ellipsize(gtk::pango::EllipsizeMode::Middle);
+        label.add_css_class("monospace");
+        label.add_css_class("dim-label");
+        let status = gtk::Label::new(Some("not run"));
+        status.add_css_class("dim-label");
+        row.append(&gtk::Image::from_icon_name("media-playback-pause-symbolic"));
+        row.append(&label);
+        row.append(&status);
+        content.append(&row);
+    }
+    if !changes.is_empty() {
+        let title = gtk::Label::new(Some(&format!("Package changes ({})", changes.len())));
+        title.set_xalign(0.0);
+        title.set_margin_top(8);
+        title.add_css_class("heading");
+        content.append(&title);
+        for change in changes {
+            let label = gtk::Label::new(Some(&change.describe()));
+            label.set_xalign(0.0);
+            label.set_selectable(true);
+            label.add_css_class("monospace");
+            if change.after.is_none() {
+                label.add_css_class("dim-label");
+            }
+            content.append(&label);
+        }
+    }
+    let scroller = gtk::ScrolledWindow::new();
+    scroller.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+    scroller.set_min_content_height(80);
+    scroller.set_max_content_height(320);
+    scroller.set_propagate_natural_height(true);
+    scroller.set_child(Some(&content));
+
+    let succeeded = outcomes.iter().filter(|outcome| outcome.code == 0).count();
+    let total = outcomes.len() + skipped.len();
+    let heading = if succeeded == total {
+        "Transactions complete"
+    } else {
+        "Transactions failed"
+    };
+    let dialog = adw::MessageDialog::new(
+        Some(parent),
+        Some(heading),
+        Some(&format!("{succeeded} of {total} command(s) succeeded.")),
+    );
+    dialog.set_extra_child(Some(&scroller));
+    match on_retry {
+        Some(on_retry) => {
+            dialog.add_response("discard", "Discard");
+            dialog.add_response("retry", "Retry Remaining");
+            if on_refresh_mirrors.is_some() {
+                dialog.add_response("mirrors", "Refresh Mirrors and Retry");
+                dialog.set_response_appearance("mirrors", adw::ResponseAppearance::Suggested);
+            } else {
+                dialog.set_response_appearance("retry", adw::ResponseAppearance::Suggested);
+            }
+            dialog.connect_response(None, move |d: &adw::MessageDialog, resp| {
+                d.close();
+                match resp {
+                    "retry" => on_retry(),
+                    "mirrors" => {
+                        if let Some(on_refresh_mirrors) = &on_refresh_mirrors {
+                            on_refresh_mirrors();
+                        }
+                    }
+                    _ => {}
+                }
+            });
+        }
+        None => {
+            dialog.add_response("ok", "OK");
+            dialog.connect_response(None, |d: &adw::MessageDialog, _| d.close());
+        }
+    }
+    dialog.present();
+}
+
+fn show_overwrite_dialog(
+    parent: &adw::ApplicationWindow,
+    paths: &[String],
+    on_retry: impl Fn() + 'static,
+) {
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 4);
+    for path in paths {
+        let label = gtk::Label::new(Some(path));
+        label.set_xalign(0.0);
+        label.set_selectable(true);
+        label.add_css_class("monospace");
+        content.append(&label);
+    }
+    let scroller = gtk::ScrolledWindow::new();
+    scroller.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+    scroller.set_min_content_height(120);
+    scroller.set_max_content_height(320);
+    scroller.set_child(Some(&content));
+
+    let dialog = adw::MessageDialog::new(
+        Some(parent),
+        Some("Conflicting Files"),
+        Some(&format!(
+            "{} file(s) already exist in the filesystem. Retry with --overwrite for the conflicting paths?",
+            paths.len()
+        )),
+    );
+    dialog.set_extra_child(Some(&scroller));
+    dialog.add_response("cancel", "Cancel");
+    dialog.add_response("retry", "Retry with --overwrite");
+    dialog.set_response_appearance("retry", adw::ResponseAppearance::Destructive);
+    dialog.connect_response(None, move |d: &adw::MessageDialog, resp| {
+        if resp == "retry" {
+            on_retry();
+        }
+        d.close();
+    });
+    dialog.present();
+}
+
 fn active_package_managers() -> Result<Vec<String>, String> {
     let mut active = Vec::new();
     let names = ["pacman", "yay", "paru", "pamac", "pkcon", "packagekitd", "aurora-helper"];
@@ -774,51 +2299,141 @@ fn should_prompt(line: &str) -> bool {
         || l.ends_with("?")
 }
 
+// pacman announces ":: There are 3 providers available for java-runtime:" and then lists
+// "1) jre-openjdk  2) jre11-openjdk" under a ":: Repository extra" line per repo.
+fn parse_provider_options(log: &[String]) -> Vec<(u32, String)> {
+    let Some(start) = log
+        .iter()
+        .rposition(|line| line.contains("providers available for"))
+    else {
+        return Vec::new();
+    };
+    let mut options = Vec::new();
+    for line in &log[start + 1..] {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        for pair in tokens.windows(2) {
+            if let Some(number) = pair[0].strip_suffix(')').and_then(|n| n.parse().ok()) {
+                options.push((number, pair[1].to_string()));
+            }
+        }
+    }
+    options
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromptKind {
+    YesNo,
+    Number,
+    // yay/paru menus such as "Packages to cleanBuild? [N]one [A]ll [Ab]ort [I]nstalled ..."
+    Menu,
+    Other,
+}
+
+fn classify_prompt(line: &str) -> PromptKind {
+    let l = line.to_lowercase();
+    if l.contains("[a]ll") || l.contains("[ab]ort") || l.contains("packages to cleanbuild") {
+        PromptKind::Menu
+    } else if l.contains("enter a number") {
+        PromptKind::Number
+    } else if l.contains("[y/n]") || l.contains("[y]") {
+        PromptKind::YesNo
+    } else {
+        PromptKind::Other
+    }
+}
+
+fn quick_responses(kind: PromptKind) -> &'static [(&'static str, &'static str)] {
+    const YES_NO: &[(&str, &str)] = &[("y", "Yes"), ("n", "No")];
+    const MENU: &[(&str, &str)] = &[
+        ("a", "All"),
+        ("n", "None"),
+        ("i", "Installed"),
+        ("no", "NotInstalled"),
+        ("ab", "Abort"),
+    ];
+    const ANY: &[(&str, &str)] = &[
+        ("y", "Yes"),
+        ("n", "No"),
+        ("a", "All"),
+        ("i", "Installed"),
+        ("no", "NotInstalled"),
+        ("ab", "Abort"),
+    ];
+    match kind {
+        PromptKind::YesNo => YES_NO,
+        PromptKind::Number => &[],
+        PromptKind::Menu => MENU,
+        PromptKind::Other => ANY,
+    }
+}
+
+// `providers` holds the numbered choices pacman listed before the prompt; when there are none
+// the dialog falls back to a free-text entry.
 fn show_prompt_dialog(
     parent: &adw::ApplicationWindow,
     prompt: &str,
+    providers: &[(u32, String)],
     input_tx: mpsc::Sender<String>,
     prompt_open: Rc<RefCell<bool>>,
+    log_drawer: &widgets::log_drawer::LogDrawer,
+    timeout: Option<(u32, String)>,
 ) {
     let dialog = adw::MessageDialog::new(
         Some(parent),
         Some("Input Required"),
         Some(prompt),
     );
+    let kind = classify_prompt(prompt);
+    let responses = quick_responses(kind);
     let entry = gtk::Entry::new();
-    entry.set_placeholder_text(Some("Enter response (e.g., y, n, 1)"));
-    dialog.set_extra_child(Some(&entry));
-    dialog.add_response("y", "Yes");
-    dialog.add_response("n", "No");
-    dialog.add_response("a", "All");
-    dialog.add_response("i", "Installed");
-    dialog.add_response("no", "NotInstalled");
-    dialog.add_response("ab", "Abort");
+    entry.set_placeholder_text(Some(match kind {
+        PromptKind::Number => "Enter numbers (e.g., 1 3-5)",
+        PromptKind::Menu => "Enter a choice or numbers (e.g., 1 2, 1-3, ^4)",
+        _ => "Enter response (e.g., y, n, 1)",
+    }));
+    let mut choices: Vec<(u32, gtk::CheckButton)> = Vec::new();
+    if kind == PromptKind::Number && !providers.is_empty() {
+        let list = gtk::Box::new(gtk::Orientation::Vertical, 4);
+        for (number, name) in providers {
+            let radio = gtk::CheckButton::with_label(name);
+            if let Some((_, first)) = choices.first() {
+                radio.set_group(Some(first));
+            } else {
+                // pacman's default is the first provider.
+                radio.set_active(true);
+            }
+            list.append(&radio);
+            choices.push((*number, radio));
+        }
+        dialog.set_extra_child(Some(&list));
+    } else {
+        dialog.set_extra_child(Some(&entry));
+    }
+    for (id, label) in responses {
+        dialog.add_response(id, label);
+    }
     dialog.add_response("cancel", "Cancel");
     dialog.add_response("send", "Send");
     dialog.set_response_appearance("send", adw::ResponseAppearance::Suggested);
-    dialog.set_response_appearance("y", adw::ResponseAppearance::Suggested);
+    if dialog.has_response("y") {
+        dialog.set_response_appearance("y", adw::ResponseAppearance::Suggested);
+    }
     let input_tx_quick = input_tx.clone();
+    let input_tx_timeout = input_tx.clone();
     let prompt_open_quick = prompt_open.clone();
     dialog.connect_response(None, move |d, resp| {
-        let val = match resp {
-            "y" => Some("y"),
-            "n" => Some("n"),
-            "a" => Some("a"),
-            "i" => Some("i"),
-            "no" => Some("no"),
-            "ab" => Some("ab"),
-            _ => None,
-        };
-        if let Some(v) = val {
-            let _ = input_tx_quick.send(v.to_string());
+        if responses.iter().any(|(id, _)| *id == resp) {
+            let _ = input_tx_quick.send(resp.to_string());
             *prompt_open_quick.borrow_mut() = false;
             d.close();
         }
     });
     let prompt_open_send = prompt_open.clone();
     dialog.connect_response(Some("send"), move |d, _| {
-        let text = entry.text().to_string();
+        let text = match choices.iter().find(|(_, radio)| radio.is_active()) {
+            Some((number, _)) => number.to_string(),
+            None => entry.text().to_string(),
+        };
         let _ = input_tx.send(text);
         *prompt_open_send.borrow_mut() = false;
         d.close();
@@ -828,6 +2443,26 @@ fn show_prompt_dialog(
         *prompt_open_cancel.borrow_mut() = false;
         d.close();
     });
+    if let Some((secs, response)) = timeout {
+        // Answer unattended prompts so the child process does not hold the pacman lock forever.
+        let weak_dialog = dialog.downgrade();
+        let input_tx = input_tx_timeout;
+        let log_drawer = log_drawer.clone();
+        glib::timeout_add_local_once(Duration::from_secs(u64::from(secs)), move || {
+            let Some(dialog) = weak_dialog.upgrade() else {
+                return;
+            };
+            if !*prompt_open.borrow() || !dialog.is_visible() {
+                return;
+            }
+            let _ = input_tx.send(response.clone());
+            log_drawer.append_line(&format!(
+                "No response after {secs}s; answered \"{response}\" automatically"
+            ));
+            *prompt_open.borrow_mut() = false;
+            dialog.close();
+        });
+    }
     dialog.present();
 }
 
@@ -865,8 +2500,21 @@ struct ThemePalette {
     pill_secondary_fg: &'static str,
 }
 
+// Gradient pairs that best tell the themes apart: sidebar, accent and card backgrounds.
+pub(crate) fn theme_swatch_colors(theme: ThemeMode) -> [(&'static str, &'static str); 3] {
+    let palette = palette_for_theme(theme);
+    [
+        (palette.sidebar_from, palette.sidebar_to),
+        (palette.nav_selected_from, palette.nav_selected_to),
+        (palette.card_from, palette.card_to),
+    ]
+}
+
 fn palette_for_theme(theme: ThemeMode) -> ThemePalette {
     match theme {
+        ThemeMode::Custom => palette_for_theme(custom_base(
+            load_custom_palette().unwrap_or_default().base,
+        )),
         ThemeMode::System => {
             if adw::StyleManager::default().is_dark() {
                 palette_for_theme(ThemeMode::Dark)
@@ -1118,6 +2766,10 @@ fn themed_css(theme: ThemeMode) -> String {
         .package-card {
             min-height: 248px;
         }
+        flowboxchild:focus-visible > .package-card {
+            outline: 2px solid $PILL_TO$;
+            outline-offset: 2px;
+        }
         .page-root {
             background-color: $PAGE_BG$;
             border-radius: 12px;
@@ -1211,7 +2863,20 @@ fn themed_css(theme: ThemeMode) -> String {
         }
     "#.to_string();
 
-    let replacements = [
+    let overrides = if theme == ThemeMode::Custom {
+        custom_overrides(&load_custom_palette().unwrap_or_default()).0
+    } else {
+        HashMap::new()
+    };
+    for (from, to) in palette_replacements(&palette) {
+        let value = overrides.get(from).map(String::as_str).unwrap_or(to);
+        css = css.replace(from, value);
+    }
+    css
+}
+
+fn palette_replacements(palette: &ThemePalette) -> [(&'static str, &'static str); 27] {
+    [
         ("$TOOLBAR_FROM$", palette.toolbar_from),
         ("$TOOLBAR_TO$", palette.toolbar_to),
         ("$HEADER_BG$", palette.header_bg),
@@ -1239,11 +2904,63 @@ fn themed_css(theme: ThemeMode) -> String {
         ("$PILL_SECONDARY_FROM$", palette.pill_secondary_from),
         ("$PILL_SECONDARY_TO$", palette.pill_secondary_to),
         ("$PILL_SECONDARY_FG$", palette.pill_secondary_fg),
-    ];
-    for (from, to) in replacements {
-        css = css.replace(from, to);
+    ]
+}
+
+fn custom_base(base: ThemeMode) -> ThemeMode {
+    if base == ThemeMode::Custom {
+        ThemeMode::Dark
+    } else {
+        base
+    }
+}
+
+fn custom_overrides(custom: &CustomPalette) -> (HashMap<&'static str, String>, Vec<String>) {
+    let known = palette_replacements(&palette_for_theme(ThemeMode::Dark));
+    let mut overrides = HashMap::new();
+    let mut errors = Vec::new();
+    for (key, value) in &custom.colors {
+        let placeholder = format!("${}$", key.to_uppercase());
+        let Some((from, _)) = known.iter().find(|(from, _)| *from == placeholder) else {
+            errors.push(format!("unknown color key \"{key}\""));
+            continue;
+        };
+        // Only accept values GTK can parse as a color so a typo cannot break the stylesheet.
+        if gdk::RGBA::parse(value.as_str()).is_err() {
+            errors.push(format!("invalid color for {key}: \"{value}\""));
+            continue;
+        }
+        overrides.insert(*from, value.clone());
+    }
+    (overrides, errors)
+}
+
+pub(crate) fn reload_custom_theme(active: ThemeMode) -> Result<usize, String> {
+    let custom = match load_custom_palette() {
+        Ok(custom) => custom,
+        Err(_) if !custom_theme_path().exists() => {
+            let base = palette_for_theme(ThemeMode::Dark);
+            let template = CustomPalette {
+                base: ThemeMode::Dark,
+                colors: palette_replacements(&base)
+                    .iter()
+                    .map(|(from, to)| (from.trim_matches('$').to_lowercase(), to.to_string()))
+                    .collect(),
+            };
+            save_custom_palette(&template).map_err(|err| err.to_string())?;
+            template
+        }
+        Err(err) => return Err(format!("Failed to read custom theme: {err}")),
+    };
+    let (overrides, errors) = custom_overrides(&custom);
+    if active == ThemeMode::Custom {
+        apply_theme(active);
+    }
+    if errors.is_empty() {
+        Ok(overrides.len())
+    } else {
+        Err(errors.join("; "))
     }
-    css
 }
 
 fn setup_css(theme: ThemeMode) {
@@ -1266,12 +2983,104 @@ fn setup_css(theme: ThemeMode) {
     });
 }
 
-fn build_nav_row(icon_name: &str, title: &str) -> gtk::ListBoxRow {
+fn schedule_update_poll(page: updates::UpdatesPage, ctx: AppContext, toasts: adw::ToastOverlay) {
+    // Re-read the interval on every run so changes in Settings apply to the next poll.
+    let minutes = ctx.settings.lock().unwrap().update_check_minutes.max(1);
+    glib::timeout_add_local_once(Duration::from_secs(u64::from(minutes) * 60), move || {
+        page.refresh(ctx.clone(), Some(toasts.clone()));
+        schedule_update_poll(page, ctx, toasts);
+    });
+}
+
+// Disabled sources are left out of the source filter dropdowns, so selections are matched by
+// label rather than by position.
+pub(crate) fn sync_source_filter(filter: &gtk::DropDown, labels: &[&str], settings: &Settings) {
+    let labels: Vec<&str> = labels
+        .iter()
+        .copied()
+        .filter(|label| match *label {
+            "AUR" => settings.enable_aur,
+            "Flatpak" => settings.enable_flatpak,
+            _ => true,
+        })
+        .collect();
+    let current = filter.model().and_downcast::<gtk::StringList>();
+    let unchanged = current.is_some_and(|model| {
+        model.n_items() as usize == labels.len()
+            && labels
+                .iter()
+                .enumerate()
+                .all(|(i, label)| model.string(i as u32).is_some_and(|s| s == *label))
+    });
+    if unchanged {
+        return;
+    }
+    let selected = selected_filter_label(filter);
+    filter.set_model(Some(&gtk::StringList::new(&labels)));
+    let index = labels.iter().position(|label| *label == selected).unwrap_or(0);
+    filter.set_selected(index as u32);
+}
+
+// Selects `labels[index]` if the dropdown currently offers it, otherwise leaves it alone.
+pub(crate) fn restore_source_filter(filter: &gtk::DropDown, labels: &[&str], index: u32) {
+    let Some(label) = labels.get(index as usize) else {
+        return;
+    };
+    let Some(model) = filter.model().and_downcast::<gtk::StringList>() else {
+        return;
+    };
+    let position = (0..model.n_items()).find(|i| model.string(*i).is_some_and(|s| s == *label));
+    if let Some(position) = position {
+        filter.set_selected(position);
+    }
+}
+
+pub(crate) fn source_filter_index(filter: &gtk::DropDown, labels: &[&str]) -> u32 {
+    let selected = selected_filter_label(filter);
+    labels.iter().position(|label| *label == selected).unwrap_or(0) as u32
+}
+
+pub(crate) fn save_filter(ctx: &AppContext, update: impl FnOnce(&mut Settings)) {
+    let mut settings = ctx.settings.lock().unwrap();
+    update(&mut settings);
+    let _ = save_settings(&settings);
+}
+
+// Each refresh takes a new number when it starts. A poller holding an older number belongs
+// to a refresh that has been superseded and must not touch the widgets.
+#[derive(Clone, Default)]
+pub(crate) struct Generation(Rc<Cell<u64>>);
+
+impl Generation {
+    pub fn start(&self) -> u64 {
+        let next = self.0.get().wrapping_add(1);
+        self.0.set(next);
+        next
+    }
+
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.0.get() == generation
+    }
+}
+
+pub(crate) fn selected_filter_label(filter: &gtk::DropDown) -> String {
+    filter
+        .selected_item()
+        .and_downcast::<gtk::StringObject>()
+        .map(|item| item.string().to_string())
+        .unwrap_or_default()
+}
+
+fn build_nav_row(icon_name: &str, title: &str) -> (gtk::ListBoxRow, gtk::Label) {
     let row = gtk::ListBoxRow::new();
     let content = gtk::Box::new(gtk::Orientation::Horizontal, 10);
     content.add_css_class("nav-row");
 
-    let icon = gtk::Image::from_icon_name(icon_name);
+    // The title already names the row; the icon is decoration for assistive tech.
+    let icon = gtk::Image::builder()
+        .icon_name(icon_name)
+        .accessible_role(gtk::AccessibleRole::Presentation)
+        .build();
     icon.set_pixel_size(18);
 
     let label = gtk::Label::new(Some(title));
@@ -1282,7 +3091,8 @@ fn build_nav_row(icon_name: &str, title: &str) -> gtk::ListBoxRow {
     content.append(&icon);
     content.append(&label);
     row.set_child(Some(&content));
-    row
+    row.update_property(&[gtk::accessible::Property::Label(title)]);
+    (row, label)
 }
 
 pub(crate) fn apply_theme(theme: ThemeMode) {
@@ -1295,6 +3105,98 @@ pub(crate) fn apply_theme(theme: ThemeMode) {
         | ThemeMode::Emerald
         | ThemeMode::Sunset
         | ThemeMode::Graphite => manager.set_color_scheme(adw::ColorScheme::ForceDark),
+        ThemeMode::Custom => {
+            match custom_base(load_custom_palette().unwrap_or_default().base) {
+                ThemeMode::System => manager.set_color_scheme(adw::ColorScheme::Default),
+                ThemeMode::Light => manager.set_color_scheme(adw::ColorScheme::ForceLight),
+                _ => manager.set_color_scheme(adw::ColorScheme::ForceDark),
+            }
+        }
     }
     setup_css(theme);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_latest_generation_is_current() {
+        let generation = Generation::default();
+        let first = generation.start();
+        assert!(generation.is_current(first));
+        let second = generation.clone().start();
+        assert!(!generation.is_current(first));
+        assert!(generation.is_current(second));
+    }
+
+    #[test]
+    fn prompts_only_offer_relevant_quick_responses() {
+        let yes_no = classify_prompt(":: Proceed with installation? [Y/n]");
+        assert_eq!(yes_no, PromptKind::YesNo);
+        assert_eq!(quick_responses(yes_no), [("y", "Yes"), ("n", "No")]);
+
+        let provider = classify_prompt("Enter a number (default=1): ");
+        assert_eq!(provider, PromptKind::Number);
+        assert!(quick_responses(provider).is_empty());
+
+        let menu = classify_prompt(
+            "==> Packages to cleanBuild? [N]one [A]ll [Ab]ort [I]nstalled [No]tInstalled or (1 2 3, 1-3, ^4)",
+        );
+        assert_eq!(menu, PromptKind::Menu);
+        assert!(quick_responses(menu).iter().any(|(id, _)| *id == "no"));
+        assert!(!quick_responses(menu).iter().any(|(id, _)| *id == "y"));
+
+        assert_eq!(classify_prompt("Continue?"), PromptKind::Other);
+    }
+
+    #[test]
+    fn provider_lists_become_numbered_options() {
+        let log: Vec<String> = [
+            "resolving dependencies...",
+            ":: There are 3 providers available for java-runtime:",
+            ":: Repository extra",
+            "   1) jre-openjdk  2) jre11-openjdk",
+            ":: Repository community",
+            "   3) jre17-openjdk",
+            "",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+        assert_eq!(
+            parse_provider_options(&log),
+            [
+                (1, String::from("jre-openjdk")),
+                (2, String::from("jre11-openjdk")),
+                (3, String::from("jre17-openjdk")),
+            ]
+        );
+        assert!(parse_provider_options(&log[..1]).is_empty());
+    }
+
+    #[test]
+    fn queue_summary_counts_actions_by_kind() {
+        let action = |name: &str, source, kind| TransactionAction {
+            name: name.to_string(),
+            source,
+            kind,
+            origin: None,
+            delete_data: false,
+            unused_runtimes: false,
+        };
+        let mut queue = TransactionQueue::default();
+        assert_eq!(queue_summary(&queue), "Queue is empty");
+
+        queue.push(action("firefox", PackageSource::Repo, ActionKind::Install));
+        queue.push(action("gimp", PackageSource::Repo, ActionKind::Install));
+        queue.push(action("yay-bin", PackageSource::Aur, ActionKind::Install));
+        queue.push(action("nano", PackageSource::Repo, ActionKind::Remove));
+        queue.push(action("org.gnome.Maps", PackageSource::Flatpak, ActionKind::Upgrade));
+        queue.push(action("system", PackageSource::Repo, ActionKind::Upgrade));
+        assert_eq!(
+            queue_summary(&queue),
+            "3 installs, 1 update, 1 removal, system upgrade"
+        );
+    }
+}
@@ -1,26 +1,50 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
 use std::rc::Rc;
+use std::sync::mpsc;
 
 use gtk::prelude::*;
 
-use crate::core::models::PackageSummary;
+use crate::core::cache::favorite_keys;
+use crate::core::models::{
+    ActionKind, PackageSource, PackageSummary, SearchMode, SearchSort, TransactionAction,
+};
+use crate::ui::widgets::accessible::set_icon_label;
 use crate::ui::widgets::card;
-use crate::ui::{run_search, AppContext, UiHandles};
+use crate::ui::{
+    restore_source_filter, run_search, save_filter, selected_filter_label, source_filter_index,
+    sync_source_filter, AppContext, Generation, UiHandles,
+};
 
+const SOURCE_FILTERS: [&str; 4] = ["All Sources", "Pacman", "AUR", "Flatpak"];
 const INITIAL_RESULT_LIMIT: usize = 90;
 const RESULT_PAGE_SIZE: usize = 90;
 
+// Package names known to be applications, with the query they were looked up for.
+type AppNames = Option<(String, HashSet<String>)>;
+
 #[derive(Clone)]
 pub struct SearchPage {
     pub root: gtk::Box,
     pub entry: gtk::SearchEntry,
+    mode_filter: gtk::DropDown,
     source_filter: gtk::DropDown,
     state_filter: gtk::DropDown,
+    sort_filter: gtk::DropDown,
+    apps_only: gtk::ToggleButton,
     results: gtk::FlowBox,
     status: gtk::Label,
     show_more_btn: gtk::Button,
+    select_toggle: gtk::ToggleButton,
+    queue_selected_btn: gtk::Button,
+    selected: Rc<RefCell<Vec<PackageSummary>>>,
     all_results: Rc<RefCell<Vec<PackageSummary>>>,
+    shown_results: Rc<RefCell<Vec<PackageSummary>>>,
     result_limit: Rc<RefCell<usize>>,
+    result_mode: Rc<Cell<SearchMode>>,
+    app_names: Rc<RefCell<AppNames>>,
+    app_names_pending: Rc<Cell<bool>>,
+    generation: Generation,
 }
 
 impl SearchPage {
@@ -45,14 +69,42 @@ impl SearchPage {
         entry.set_placeholder_text(Some("Search packages"));
         entry.set_hexpand(true);
 
-        let source_filter = gtk::DropDown::from_strings(&["All Sources", "Pacman", "AUR", "Flatpak"]);
+        let mode_labels = SearchMode::all()
+            .iter()
+            .map(|mode| mode.label())
+            .collect::<Vec<_>>();
+        let mode_filter = gtk::DropDown::from_strings(&mode_labels);
+        mode_filter.set_selected(SearchMode::NameDescription.to_index());
+        mode_filter.set_tooltip_text(Some("Search mode"));
+        let source_filter = gtk::DropDown::from_strings(&SOURCE_FILTERS);
         source_filter.set_selected(0);
         let state_filter = gtk::DropDown::from_strings(&["All States", "Installed", "Not Installed"]);
         state_filter.set_selected(0);
+        let sort_labels = SearchSort::all()
+            .iter()
+            .map(|sort| sort.label())
+            .collect::<Vec<_>>();
+        let sort_filter = gtk::DropDown::from_strings(&sort_labels);
+        sort_filter.set_selected(SearchSort::Name.to_index());
+        sort_filter.set_tooltip_text(Some("Sort results"));
+        let apps_only = gtk::ToggleButton::with_label("Apps Only");
+        apps_only.set_tooltip_text(Some("Only show graphical applications"));
+        let select_toggle = gtk::ToggleButton::new();
+        select_toggle.set_icon_name("selection-mode-symbolic");
+        set_icon_label(&select_toggle, "Select several packages");
+        let queue_selected_btn = gtk::Button::with_label("Queue Selected (0)");
+        queue_selected_btn.add_css_class("suggested-action");
+        queue_selected_btn.set_sensitive(false);
+        queue_selected_btn.set_visible(false);
 
         controls.append(&entry);
+        controls.append(&mode_filter);
         controls.append(&source_filter);
         controls.append(&state_filter);
+        controls.append(&sort_filter);
+        controls.append(&apps_only);
+        controls.append(&select_toggle);
+        controls.append(&queue_selected_btn);
         root.append(&controls);
 
         let status = gtk::Label::new(Some("Type a package name to search."));
@@ -87,25 +139,47 @@ impl SearchPage {
         Self {
             root,
             entry,
+            mode_filter,
             source_filter,
             state_filter,
+            sort_filter,
+            apps_only,
             results,
             status,
             show_more_btn,
+            select_toggle,
+            queue_selected_btn,
+            selected: Rc::new(RefCell::new(Vec::new())),
             all_results: Rc::new(RefCell::new(Vec::new())),
+            shown_results: Rc::new(RefCell::new(Vec::new())),
             result_limit: Rc::new(RefCell::new(INITIAL_RESULT_LIMIT)),
+            result_mode: Rc::new(Cell::new(SearchMode::NameDescription)),
+            app_names: Rc::new(RefCell::new(None)),
+            app_names_pending: Rc::new(Cell::new(false)),
+            generation: Generation::default(),
         }
     }
 
     pub fn bind_search(&self, ctx: AppContext, handles: UiHandles, stack: gtk::Stack) {
+        // A snapshot, because the filter handlers save back into the settings.
+        let settings = ctx.settings.lock().unwrap().clone();
+        sync_source_filter(&self.source_filter, &SOURCE_FILTERS, &settings);
+        restore_source_filter(&self.source_filter, &SOURCE_FILTERS, settings.search_source_filter);
+        self.mode_filter.set_selected(settings.search_mode);
+        self.entry.set_placeholder_text(Some(mode_placeholder(self.search_mode())));
+        self.state_filter.set_selected(settings.search_state_filter);
+        self.sort_filter.set_selected(settings.search_sort);
         let debounce: Rc<RefCell<Option<glib::SourceId>>> = Rc::new(RefCell::new(None));
         let entry = self.entry.clone();
         let page = self.clone();
         let ctx_for_search = ctx.clone();
         let handles_for_search = handles.clone();
         entry.connect_search_changed(move |entry| {
-            if let Some(id) = debounce.borrow_mut().take() {
-                let _ = std::panic::catch_unwind(|| id.remove());
+            // The timeout clears the slot before it does any work, so an id still stored here
+            // belongs to a source that has not fired and is safe to remove.
+            let pending = debounce.borrow_mut().take();
+            if let Some(id) = pending {
+                id.remove();
             }
             let query = entry.text().to_string();
             let ctx = ctx_for_search.clone();
@@ -124,17 +198,34 @@ impl SearchPage {
                 }
                 stack.set_visible_child_name("search");
                 page.reset_result_limit();
-                page.status.set_text(&format!("Searching for \"{query}\"..."));
-                run_search(query, ctx.clone(), page.clone(), handles.clone());
+                let mode = page.search_mode();
+                page.status.set_text(&format!(
+                    "Searching for \"{query}\" ({})...",
+                    mode.label()
+                ));
+                run_search(query, mode, ctx.clone(), page.clone(), handles.clone());
                 glib::ControlFlow::Break
             });
             *debounce.borrow_mut() = Some(id);
         });
 
+        let entry = self.entry.clone();
+        let ctx_for_mode = ctx.clone();
+        self.mode_filter.connect_selected_notify(move |filter| {
+            save_filter(&ctx_for_mode, |settings| settings.search_mode = filter.selected());
+            entry.set_placeholder_text(Some(mode_placeholder(SearchMode::from_index(filter.selected()))));
+            if !entry.text().trim().is_empty() {
+                entry.emit_by_name::<()>("search-changed", &[]);
+            }
+        });
+
         let ctx_for_filter = ctx.clone();
         let handles_for_filter = handles.clone();
         let page = self.clone();
-        self.source_filter.connect_selected_notify(move |_| {
+        self.source_filter.connect_selected_notify(move |filter| {
+            save_filter(&ctx_for_filter, |settings| {
+                settings.search_source_filter = source_filter_index(filter, &SOURCE_FILTERS);
+            });
             page.reset_result_limit();
             page.render_filtered(&ctx_for_filter, &handles_for_filter);
         });
@@ -142,11 +233,101 @@ impl SearchPage {
         let ctx_for_state = ctx.clone();
         let handles_for_state = handles.clone();
         let page = self.clone();
-        self.state_filter.connect_selected_notify(move |_| {
+        self.state_filter.connect_selected_notify(move |filter| {
+            save_filter(&ctx_for_state, |settings| settings.search_state_filter = filter.selected());
             page.reset_result_limit();
             page.render_filtered(&ctx_for_state, &handles_for_state);
         });
 
+        let ctx_for_sort = ctx.clone();
+        let handles_for_sort = handles.clone();
+        let page = self.clone();
+        self.sort_filter.connect_selected_notify(move |filter| {
+            save_filter(&ctx_for_sort, |settings| settings.search_sort = filter.selected());
+            page.reset_result_limit();
+            page.render_filtered(&ctx_for_sort, &handles_for_sort);
+        });
+
+        let ctx_for_apps = ctx.clone();
+        let handles_for_apps = handles.clone();
+        let page = self.clone();
+        self.apps_only.connect_toggled(move |_| {
+            page.reset_result_limit();
+            page.render_filtered(&ctx_for_apps, &handles_for_apps);
+        });
+
+        // Enter/Space opens the focused card, Ctrl+Enter queues its install.
+        let keys = gtk::EventControllerKey::new();
+        keys.set_propagation_phase(gtk::PropagationPhase::Capture);
+        let flowbox = self.results.clone();
+        let shown = self.shown_results.clone();
+        let ctx_for_keys = ctx.clone();
+        let handles_for_keys = handles.clone();
+        keys.connect_key_pressed(move |_, key, _, state| {
+            use gtk::gdk::Key;
+            if !matches!(key, Key::Return | Key::KP_Enter | Key::space) {
+                return glib::Propagation::Proceed;
+            }
+            let Some(child) = flowbox
+                .focus_child()
+                .and_downcast::<gtk::FlowBoxChild>()
+            else {
+                return glib::Propagation::Proceed;
+            };
+            // Leave Enter alone when a button inside the card has focus.
+            if !child.has_focus() {
+                return glib::Propagation::Proceed;
+            }
+            let Some(pkg) = shown.borrow().get(child.index() as usize).cloned() else {
+                return glib::Propagation::Proceed;
+            };
+            if state.contains(gtk::gdk::ModifierType::CONTROL_MASK) {
+                handles_for_keys
+                    .queue
+                    .add_install(pkg.name.clone(), pkg.source, pkg.origin.clone());
+            } else {
+                crate::ui::details::show_details(&ctx_for_keys, &handles_for_keys, pkg);
+            }
+            glib::Propagation::Stop
+        });
+        self.results.add_controller(keys);
+
+        let ctx_for_select = ctx.clone();
+        let handles_for_select = handles.clone();
+        let page = self.clone();
+        self.select_toggle.connect_toggled(move |toggle| {
+            let selecting = toggle.is_active();
+            page.results.set_selection_mode(if selecting {
+                gtk::SelectionMode::Multiple
+            } else {
+                gtk::SelectionMode::None
+            });
+            page.queue_selected_btn.set_visible(selecting);
+            page.selected.borrow_mut().clear();
+            page.update_selected_label();
+            page.render_filtered(&ctx_for_select, &handles_for_select);
+        });
+
+        let handles_for_queue = handles.clone();
+        let page = self.clone();
+        self.queue_selected_btn.connect_clicked(move |_| {
+            let actions = page
+                .selected
+                .borrow()
+                .iter()
+                .map(|pkg| TransactionAction {
+                    name: pkg.name.clone(),
+                    source: pkg.source,
+                    kind: ActionKind::Install,
+                    origin: pkg.origin.clone(),
+                    delete_data: false,
+                    unused_runtimes: false,
+                })
+                .collect();
+            handles_for_queue.queue.add_install_many(actions);
+            page.select_toggle.set_active(false);
+        });
+
         let ctx_for_more = ctx.clone();
         let handles_for_more = handles.clone();
         let page = self.clone();
@@ -156,8 +337,27 @@ impl SearchPage {
         });
     }
 
-    pub fn set_results(&self, results: Vec<PackageSummary>, ctx: &AppContext, handles: &UiHandles) {
+    // Shared with run_search so a slow search cannot overwrite the results of a newer one.
+    pub(crate) fn generation(&self) -> Generation {
+        self.generation.clone()
+    }
+
+    pub fn search_mode(&self) -> SearchMode {
+        SearchMode::from_index(self.mode_filter.selected())
+    }
+
+    pub fn set_results(
+        &self,
+        results: Vec<PackageSummary>,
+        mode: SearchMode,
+        ctx: &AppContext,
+        handles: &UiHandles,
+    ) {
+        // Changing the model notifies the filter, whose handler locks the settings again.
+        let settings = ctx.settings.lock().unwrap().clone();
+        sync_source_filter(&self.source_filter, &SOURCE_FILTERS, &settings);
         *self.all_results.borrow_mut() = results;
+        self.result_mode.set(mode);
         self.reset_result_limit();
         self.render_filtered(ctx, handles);
     }
@@ -165,17 +365,29 @@ impl SearchPage {
     fn render_filtered(&self, ctx: &AppContext, handles: &UiHandles) {
         self.clear_results();
 
-        let selected_source = self.source_filter.selected();
+        let apps_only = self.apps_only.is_active();
+        let query = self.entry.text().trim().to_string();
+        let app_names = self.app_names.borrow();
+        let app_names = match &*app_names {
+            Some((looked_up, names)) if *looked_up == query => apps_only.then_some(names),
+            _ if apps_only => {
+                self.status.set_text("Checking which results are applications...");
+                self.load_app_names(query, ctx, handles);
+                return;
+            }
+            _ => None,
+        };
+        let selected_source = selected_filter_label(&self.source_filter);
         let selected_state = self.state_filter.selected();
-        let results: Vec<PackageSummary> = self
+        let mut results: Vec<PackageSummary> = self
             .all_results
             .borrow()
             .iter()
             .cloned()
-            .filter(|pkg| match selected_source {
-                1 => pkg.source == crate::core::models::PackageSource::Repo,
-                2 => pkg.source == crate::core::models::PackageSource::Aur,
-                3 => pkg.source == crate::core::models::PackageSource::Flatpak,
+            .filter(|pkg| match selected_source.as_str() {
+                "Pacman" => pkg.source == crate::core::models::PackageSource::Repo,
+                "AUR" => pkg.source == crate::core::models::PackageSource::Aur,
+                "Flatpak" => pkg.source == crate::core::models::PackageSource::Flatpak,
                 _ => true,
             })
             .filter(|pkg| match selected_state {
@@ -183,50 +395,156 @@ impl SearchPage {
                 2 => !pkg.installed,
                 _ => true,
             })
+            // Flatpak search only returns applications to begin with.
+            .filter(|pkg| {
+                app_names.is_none_or(|names| {
+                    pkg.source == PackageSource::Flatpak || names.contains(&pkg.name)
+                })
+            })
             .collect();
+        sort_results(&mut results, SearchSort::from_index(self.sort_filter.selected()));
 
         if results.is_empty() {
             self.show_more_btn.set_visible(false);
-            self.status.set_text("No results found for selected filters.");
+            self.status.set_text(&format!(
+                "No results found for selected filters ({}).",
+                self.result_mode.get().label()
+            ));
             return;
         }
 
         let total = results.len();
         let limit = *self.result_limit.borrow();
         let shown = total.min(limit);
-        self.status
-            .set_text(&format!("{shown} of {total} results"));
+        let offline_note = if ctx.settings.lock().unwrap().offline {
+            ", AUR skipped in offline mode"
+        } else {
+            ""
+        };
+        self.status.set_text(&format!(
+            "{shown} of {total} results ({}{offline_note})",
+            self.result_mode.get().label()
+        ));
         self.show_more_btn.set_visible(shown < total);
 
+        *self.shown_results.borrow_mut() = results.iter().take(limit).cloned().collect();
+        let favorites = favorite_keys();
         for pkg in results.into_iter().take(limit) {
             let queue = handles.queue.clone();
             let handles_for_details = handles.clone();
             let ctx_for_details = ctx.clone();
             let pkg_for_action = pkg.clone();
             let pkg_for_details = pkg.clone();
-            let row = card::build_card(
-                &pkg,
-                move || {
-                    queue.add_install(
-                        pkg_for_action.name.clone(),
-                        pkg_for_action.source,
-                        pkg_for_action.origin.clone(),
-                    );
-                },
-                move || {
-                    crate::ui::details::show_details(
-                        &ctx_for_details,
-                        &handles_for_details,
-                        pkg_for_details.clone(),
-                    );
-                },
-            );
+            let on_action = move || {
+                queue.add_install(
+                    pkg_for_action.name.clone(),
+                    pkg_for_action.source,
+                    pkg_for_action.origin.clone(),
+                );
+            };
+            let on_details = move || {
+                crate::ui::details::show_details(
+                    &ctx_for_details,
+                    &handles_for_details,
+                    pkg_for_details.clone(),
+                );
+            };
+            if !self.select_toggle.is_active() {
+                let row = card::build_card(&pkg, &favorites, on_action, on_details);
+                self.results.insert(&row, -1);
+                continue;
+            }
+
+            let check = gtk::CheckButton::new();
+            let was_selected = self.is_selected(&pkg);
+            let page = self.clone();
+            let pkg_for_select = pkg.clone();
+            check.connect_toggled(move |check| {
+                let active = check.is_active();
+                {
+                    let pkg = &pkg_for_select;
+                    let mut selected = page.selected.borrow_mut();
+                    selected.retain(|other| other.name != pkg.name || other.source != pkg.source);
+                    if active {
+                        selected.push(pkg.clone());
+                    }
+                }
+                if let Some(child) = check
+                    .ancestor(gtk::FlowBoxChild::static_type())
+                    .and_downcast::<gtk::FlowBoxChild>()
+                {
+                    if active {
+                        page.results.select_child(&child);
+                    } else {
+                        page.results.unselect_child(&child);
+                    }
+                }
+                page.update_selected_label();
+            });
+            let row = card::build_selectable_card(&pkg, &favorites, on_action, on_details, check.clone());
             self.results.insert(&row, -1);
+            if was_selected {
+                check.set_active(true);
+            }
         }
     }
 
+    // Repo and AUR results carry no hint of being graphical. AppStream only lists
+    // applications, and installed packages with a desktop entry come from the local database.
+    fn load_app_names(&self, query: String, ctx: &AppContext, handles: &UiHandles) {
+        if self.app_names_pending.replace(true) {
+            return;
+        }
+        let appstream = ctx.appstream.clone();
+        let pacman = ctx.pacman.clone();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut names = appstream.app_package_names(&query);
+            names.extend(pacman.desktop_app_names().unwrap_or_default());
+            let _ = tx.send((query, names));
+        });
+
+        let page = self.clone();
+        let ctx = ctx.clone();
+        let handles = handles.clone();
+        glib::idle_add_local(move || match rx.try_recv() {
+            Ok(found) => {
+                page.app_names_pending.set(false);
+                *page.app_names.borrow_mut() = Some(found);
+                page.render_filtered(&ctx, &handles);
+                glib::ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                page.app_names_pending.set(false);
+                glib::ControlFlow::Break
+            }
+        });
+    }
+
+    fn is_selected(&self, pkg: &PackageSummary) -> bool {
+        self.selected
+            .borrow()
+            .iter()
+            .any(|other| other.name == pkg.name && other.source == pkg.source)
+    }
+
+    fn update_selected_label(&self) {
+        let count = self.selected.borrow().len();
+        self.queue_selected_btn
+            .set_label(&format!("Queue Selected ({count})"));
+        self.queue_selected_btn.set_sensitive(count > 0);
+    }
+
+    pub fn show_message(&self, text: &str) {
+        self.all_results.borrow_mut().clear();
+        self.clear_results();
+        self.status.set_text(text);
+    }
+
     pub fn clear_results(&self) {
         self.show_more_btn.set_visible(false);
+        self.shown_results.borrow_mut().clear();
         while let Some(child) = self.results.first_child() {
             self.results.remove(&child);
         }
@@ -240,3 +558,38 @@ impl SearchPage {
         *self.result_limit.borrow_mut() += RESULT_PAGE_SIZE;
     }
 }
+
+fn mode_placeholder(mode: SearchMode) -> &'static str {
+    if mode == SearchMode::File {
+        "File path or command, e.g. /usr/bin/vim"
+    } else {
+        "Search packages"
+    }
+}
+
+// Sorts are stable and fall back to the name so equal keys keep a predictable order.
+fn sort_results(results: &mut [PackageSummary], sort: SearchSort) {
+    let source_rank = |source: PackageSource| match source {
+        PackageSource::Repo => 0,
+        PackageSource::Aur => 1,
+        PackageSource::Flatpak => 2,
+    };
+    match sort {
+        SearchSort::Name => results.sort_by(|a, b| a.name.cmp(&b.name)),
+        SearchSort::InstalledFirst => results.sort_by(|a, b| {
+            b.installed
+                .cmp(&a.installed)
+                .then_with(|| a.name.cmp(&b.name))
+        }),
+        SearchSort::Source => results.sort_by(|a, b| {
+            source_rank(a.source)
+                .cmp(&source_rank(b.source))
+                .then_with(|| a.name.cmp(&b.name))
+        }),
+        SearchSort::Popularity => results.sort_by(|a, b| {
+            let a_pop = a.popularity.unwrap_or(-1.0);
+            let b_pop = b.popularity.unwrap_or(-1.0);
+            b_pop.total_cmp(&a_pop).then_with(|| a.name.cmp(&b.name))
+        }),
+    }
+}
@@ -1,20 +1,65 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::mpsc;
+
 use gtk::prelude::*;
+use gtk::{gdk, gio};
 use libadwaita as adw;
 use adw::prelude::*;
 
-use crate::core::cache::{clear_screenshots_cache, save_settings};
-use crate::core::models::{AurHelperKind, TerminalEmulator, TerminalMode, ThemeMode};
-use crate::ui::{apply_theme, AppContext};
+use crate::core::cache::{
+    clear_screenshots_cache, custom_theme_path, log_file_path, save_settings, settings_from_json,
+};
+use crate::core::models::{
+    AurHelperKind, EscalationMethod, Settings, TerminalEmulator, TerminalMode, ThemeMode,
+};
+use crate::core::transactions::{clean_cache_command, validate_env_name};
+use crate::ui::widgets::accessible::set_icon_label;
+use crate::ui::{apply_theme, reload_custom_theme, theme_swatch_colors, AppContext, UiHandles};
 
 #[derive(Clone)]
 pub struct SettingsPage {
     pub root: adw::PreferencesPage,
     theme_row: adw::ComboRow,
+    custom_theme_row: adw::ActionRow,
+    reload_theme: gtk::Button,
     terminal_mode_row: adw::ComboRow,
     terminal_emulator_row: adw::ComboRow,
+    queue_panel_row: adw::SwitchRow,
     helper_row: adw::ComboRow,
+    escalation_row: adw::ComboRow,
     noconfirm_row: adw::SwitchRow,
+    needed_row: adw::SwitchRow,
+    separate_upgrade_row: adw::SwitchRow,
+    enable_aur_row: adw::SwitchRow,
+    enable_flatpak_row: adw::SwitchRow,
+    fuzzy_search_row: adw::SwitchRow,
+    dependency_tree_row: adw::SwitchRow,
+    dry_run_row: adw::SwitchRow,
+    offline_row: adw::SwitchRow,
+    update_interval_row: adw::SpinRow,
+    prompt_timeout_row: adw::SpinRow,
+    prompt_response_row: adw::EntryRow,
+    removal_threshold_row: adw::SpinRow,
+    command_timeout_row: adw::SpinRow,
+    log_limit_row: adw::SpinRow,
+    log_to_file_row: adw::SwitchRow,
+    log_colors_row: adw::SwitchRow,
+    remotes_group: adw::PreferencesGroup,
+    remote_rows: Rc<RefCell<Vec<adw::ActionRow>>>,
+    env_group: adw::PreferencesGroup,
+    env_entry_row: adw::EntryRow,
+    env_all_row: adw::SwitchRow,
+    env_rows: Rc<RefCell<Vec<adw::ActionRow>>>,
+    add_flathub: gtk::Button,
+    refresh_remotes: gtk::Button,
     clear_cache: gtk::Button,
+    keep_versions_row: adw::SpinRow,
+    clean_package_cache: gtk::Button,
+    unused_runtimes_row: adw::ActionRow,
+    remove_unused_runtimes: gtk::Button,
+    export_settings: gtk::Button,
+    import_settings: gtk::Button,
     about_btn: gtk::Button,
 }
 
@@ -34,8 +79,18 @@ impl SettingsPage {
         let theme_row = adw::ComboRow::new();
         theme_row.set_title("Theme");
         theme_row.set_model(Some(&theme_list));
+        theme_row.set_factory(Some(&theme_preview_factory()));
         appearance_group.add(&theme_row);
 
+        let reload_theme = gtk::Button::with_label("Reload");
+        reload_theme.set_valign(gtk::Align::Center);
+        let custom_theme_row = adw::ActionRow::new();
+        custom_theme_row.set_title("Custom Theme");
+        custom_theme_row.set_subtitle(&custom_theme_path().to_string_lossy());
+        custom_theme_row.add_suffix(&reload_theme);
+        custom_theme_row.set_activatable(false);
+        appearance_group.add(&custom_theme_row);
+
         let terminal_mode_labels = TerminalMode::all()
             .iter()
             .map(|mode| mode.label())
@@ -58,6 +113,11 @@ impl SettingsPage {
         terminal_emulator_row.set_model(Some(&terminal_emulator_list));
         appearance_group.add(&terminal_emulator_row);
 
+        let queue_panel_row = adw::SwitchRow::new();
+        queue_panel_row.set_title("Dock Queue Panel");
+        queue_panel_row.set_subtitle("Keep the queue visible beside the pages while adding packages");
+        appearance_group.add(&queue_panel_row);
+
         let group = adw::PreferencesGroup::new();
         group.set_title("General");
 
@@ -66,10 +126,109 @@ impl SettingsPage {
         helper_row.set_title("AUR Helper");
         helper_row.set_model(Some(&list));
 
+        let escalation_labels = EscalationMethod::all()
+            .iter()
+            .map(|method| method.label())
+            .collect::<Vec<_>>();
+        let escalation_list = gtk::StringList::new(&escalation_labels);
+        let escalation_row = adw::ComboRow::new();
+        escalation_row.set_title("Privilege Escalation");
+        escalation_row.set_subtitle("sudo always runs in an external terminal for its password prompt");
+        escalation_row.set_model(Some(&escalation_list));
+
         let noconfirm_row = adw::SwitchRow::new();
         noconfirm_row.set_title("Allow --noconfirm");
         noconfirm_row.set_subtitle("Applies to external terminal mode. Integrated logs are always non-interactive.");
 
+        let needed_row = adw::SwitchRow::new();
+        needed_row.set_title("Skip Up-to-date Packages");
+        needed_row.set_subtitle("Install with --needed so current packages are not reinstalled. Reinstall always reinstalls.");
+
+        let separate_upgrade_row = adw::SwitchRow::new();
+        separate_upgrade_row.set_title("Separate Pacman and AUR Upgrades");
+        separate_upgrade_row.set_subtitle("Run pacman -Syu before the AUR helper instead of letting the helper upgrade both");
+
+        let enable_aur_row = adw::SwitchRow::new();
+        enable_aur_row.set_title("AUR");
+        enable_aur_row.set_subtitle("Search, list and update AUR packages through the AUR helper");
+
+        let enable_flatpak_row = adw::SwitchRow::new();
+        enable_flatpak_row.set_title("Flatpak");
+        enable_flatpak_row.set_subtitle("Search, list and update Flatpak apps");
+
+        let fuzzy_search_row = adw::SwitchRow::new();
+        fuzzy_search_row.set_title("Fuzzy Installed Search");
+        fuzzy_search_row.set_subtitle("Tolerate typos such as \"frfx\" for firefox and list the closest matches first");
+
+        let dependency_tree_row = adw::SwitchRow::new();
+        dependency_tree_row.set_title("Dependency Tree in Review");
+        dependency_tree_row.set_subtitle("Expand each queued action into the packages it pulls in or removes");
+
+        let dry_run_row = adw::SwitchRow::new();
+        dry_run_row.set_title("Dry Run");
+        dry_run_row.set_subtitle("Print planned commands to the logs without executing them");
+
+        let offline_row = adw::SwitchRow::new();
+        offline_row.set_title("Offline Mode");
+        offline_row.set_subtitle("Skip AUR queries, Arch news and screenshot downloads");
+
+        let update_interval_row = adw::SpinRow::with_range(5.0, 1440.0, 5.0);
+        update_interval_row.set_title("Update Check Interval");
+        update_interval_row.set_subtitle("Minutes between background update checks");
+
+        let prompt_timeout_row = adw::SpinRow::with_range(0.0, 3600.0, 10.0);
+        prompt_timeout_row.set_title("Prompt Timeout");
+        prompt_timeout_row.set_subtitle("Seconds before answering an unattended prompt (0 disables)");
+
+        let prompt_response_row = adw::EntryRow::new();
+        prompt_response_row.set_title("Prompt Timeout Response");
+
+        let removal_threshold_row = adw::SpinRow::with_range(0.0, 500.0, 1.0);
+        removal_threshold_row.set_title("Removal Warning Threshold");
+        removal_threshold_row.set_subtitle("Warn before a removal that takes more packages with it than this");
+
+        let command_timeout_row = adw::SpinRow::with_range(5.0, 600.0, 5.0);
+        command_timeout_row.set_title("Command Timeout");
+        command_timeout_row.set_subtitle("Seconds before a search, info or update check is stopped");
+
+        let logs_group = adw::PreferencesGroup::new();
+        logs_group.set_title("Logs");
+        let log_limit_row = adw::SpinRow::with_range(100.0, 100_000.0, 100.0);
+        log_limit_row.set_title("Log Line Limit");
+        log_limit_row.set_subtitle("Lines kept in the log drawer");
+        let log_to_file_row = adw::SwitchRow::new();
+        log_to_file_row.set_title("Save Full Logs to File");
+        log_to_file_row.set_subtitle(&log_file_path().to_string_lossy());
+        let log_colors_row = adw::SwitchRow::new();
+        log_colors_row.set_title("Colorize Logs");
+        log_colors_row.set_subtitle("Render terminal colors; turn off for plain text");
+        logs_group.add(&log_limit_row);
+        logs_group.add(&log_to_file_row);
+        logs_group.add(&log_colors_row);
+
+        let remotes_group = adw::PreferencesGroup::new();
+        remotes_group.set_title("Flatpak Remotes");
+        let remotes_actions = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        let add_flathub = gtk::Button::with_label("Add Flathub");
+        let refresh_remotes = gtk::Button::from_icon_name("view-refresh-symbolic");
+        set_icon_label(&refresh_remotes, "Refresh remotes");
+        refresh_remotes.add_css_class("flat");
+        remotes_actions.append(&add_flathub);
+        remotes_actions.append(&refresh_remotes);
+        remotes_group.set_header_suffix(Some(&remotes_actions));
+
+        let env_group = adw::PreferencesGroup::new();
+        env_group.set_title("Build Environment");
+        env_group.set_description(Some("Variables such as MAKEFLAGS or PACKAGER passed to AUR and PKGBUILD builds"));
+        let env_entry_row = adw::EntryRow::new();
+        env_entry_row.set_title("Add Variable (NAME=value)");
+        env_entry_row.set_show_apply_button(true);
+        let env_all_row = adw::SwitchRow::new();
+        env_all_row.set_title("Apply to All Commands");
+        env_all_row.set_subtitle("Also pass them to Flatpak and other commands that run without privilege escalation");
+        env_group.add(&env_entry_row);
+        env_group.add(&env_all_row);
+
         let cache_group = adw::PreferencesGroup::new();
         cache_group.set_title("Cache");
         let clear_cache = gtk::Button::with_label("Clear screenshots cache");
@@ -77,6 +236,37 @@ impl SettingsPage {
         cache_row.set_title("Screenshots");
         cache_row.add_suffix(&clear_cache);
         cache_row.set_activatable(false);
+        let keep_versions_row = adw::SpinRow::with_range(1.0, 10.0, 1.0);
+        keep_versions_row.set_title("Cached Versions to Keep");
+        keep_versions_row.set_subtitle("Package versions kept per package when cleaning");
+        let clean_package_cache = gtk::Button::with_label("Clean");
+        clean_package_cache.set_valign(gtk::Align::Center);
+        let package_cache_row = adw::ActionRow::new();
+        package_cache_row.set_title("Package Cache");
+        package_cache_row.set_subtitle("/var/cache/pacman/pkg");
+        package_cache_row.add_suffix(&clean_package_cache);
+        package_cache_row.set_activatable(false);
+        let remove_unused_runtimes = gtk::Button::with_label("Review");
+        remove_unused_runtimes.set_valign(gtk::Align::Center);
+        let unused_runtimes_row = adw::ActionRow::new();
+        unused_runtimes_row.set_title("Unused Flatpak Runtimes");
+        unused_runtimes_row.set_subtitle("Runtimes no installed app depends on");
+        unused_runtimes_row.add_suffix(&remove_unused_runtimes);
+        unused_runtimes_row.set_activatable(false);
+
+        let backup_group = adw::PreferencesGroup::new();
+        backup_group.set_title("Backup");
+        let export_settings = gtk::Button::with_label("Export");
+        export_settings.set_valign(gtk::Align::Center);
+        let import_settings = gtk::Button::with_label("Import");
+        import_settings.set_valign(gtk::Align::Center);
+        let backup_row = adw::ActionRow::new();
+        backup_row.set_title("Settings File");
+        backup_row.set_subtitle("Carry your Aurora settings over to another install");
+        backup_row.add_suffix(&export_settings);
+        backup_row.add_suffix(&import_settings);
+        backup_row.set_activatable(false);
+        backup_group.add(&backup_row);
 
         let about_group = adw::PreferencesGroup::new();
         about_group.set_title("About");
@@ -88,28 +278,88 @@ impl SettingsPage {
         about_group.add(&about_row);
 
         group.add(&helper_row);
+        group.add(&escalation_row);
         group.add(&noconfirm_row);
+        group.add(&needed_row);
+        group.add(&separate_upgrade_row);
+        group.add(&enable_aur_row);
+        group.add(&enable_flatpak_row);
+        group.add(&fuzzy_search_row);
+        group.add(&dependency_tree_row);
+        group.add(&dry_run_row);
+        group.add(&offline_row);
+        group.add(&update_interval_row);
+        group.add(&prompt_timeout_row);
+        group.add(&prompt_response_row);
+        group.add(&removal_threshold_row);
+        group.add(&command_timeout_row);
         cache_group.add(&cache_row);
+        cache_group.add(&keep_versions_row);
+        cache_group.add(&package_cache_row);
+        cache_group.add(&unused_runtimes_row);
 
         root.add(&appearance_group);
         root.add(&group);
+        root.add(&logs_group);
+        root.add(&remotes_group);
+        root.add(&env_group);
         root.add(&cache_group);
+        root.add(&backup_group);
         root.add(&about_group);
 
         Self {
             root,
             theme_row,
+            custom_theme_row,
+            reload_theme,
             terminal_mode_row,
             terminal_emulator_row,
+            queue_panel_row,
             helper_row,
+            escalation_row,
             noconfirm_row,
+            needed_row,
+            separate_upgrade_row,
+            enable_aur_row,
+            enable_flatpak_row,
+            fuzzy_search_row,
+            dependency_tree_row,
+            dry_run_row,
+            offline_row,
+            update_interval_row,
+            prompt_timeout_row,
+            prompt_response_row,
+            removal_threshold_row,
+            command_timeout_row,
+            log_limit_row,
+            log_to_file_row,
+            log_colors_row,
+            remotes_group,
+            remote_rows: Rc::new(RefCell::new(Vec::new())),
+            env_group,
+            env_entry_row,
+            env_all_row,
+            env_rows: Rc::new(RefCell::new(Vec::new())),
+            add_flathub,
+            refresh_remotes,
             clear_cache,
+            keep_versions_row,
+            clean_package_cache,
+            unused_runtimes_row,
+            remove_unused_runtimes,
+            export_settings,
+            import_settings,
             about_btn,
         }
     }
 
-    pub fn bind(&self, ctx: AppContext) {
+    pub fn bind(&self, ctx: AppContext, handles: UiHandles) {
         let settings = ctx.settings.lock().unwrap().clone();
+        self.sync_rows(&settings);
+        self.connect_rows(ctx, handles);
+    }
+
+    fn sync_rows(&self, settings: &Settings) {
         self.theme_row.set_selected(settings.theme.to_index());
         self.terminal_mode_row
             .set_selected(settings.terminal_mode.to_index());
@@ -121,7 +371,40 @@ impl SettingsPage {
             AurHelperKind::Yay => self.helper_row.set_selected(0),
             AurHelperKind::Paru => self.helper_row.set_selected(1),
         }
+        self.escalation_row
+            .set_selected(settings.escalation.to_index());
         self.noconfirm_row.set_active(settings.allow_noconfirm);
+        self.queue_panel_row.set_active(settings.queue_panel_docked);
+        self.needed_row.set_active(settings.skip_up_to_date);
+        self.separate_upgrade_row.set_active(settings.separate_aur_upgrade);
+        self.enable_aur_row.set_active(settings.enable_aur);
+        self.enable_flatpak_row.set_active(settings.enable_flatpak);
+        self.unused_runtimes_row.set_visible(settings.enable_flatpak);
+        self.fuzzy_search_row.set_active(settings.fuzzy_search);
+        self.dependency_tree_row.set_active(settings.review_dependency_tree);
+        self.env_all_row.set_active(settings.env_all_commands);
+        self.dry_run_row.set_active(settings.dry_run);
+        self.offline_row.set_active(settings.offline);
+        self.update_interval_row
+            .set_value(f64::from(settings.update_check_minutes));
+        self.prompt_timeout_row
+            .set_value(f64::from(settings.prompt_timeout_secs));
+        self.prompt_response_row
+            .set_text(&settings.prompt_timeout_response);
+        self.prompt_response_row
+            .set_sensitive(settings.prompt_timeout_secs > 0);
+        self.removal_threshold_row
+            .set_value(f64::from(settings.removal_warning_threshold));
+        self.command_timeout_row
+            .set_value(f64::from(settings.command_timeout_secs));
+        self.log_limit_row.set_value(settings.log_limit as f64);
+        self.log_to_file_row.set_active(settings.log_to_file);
+        self.log_colors_row.set_active(settings.log_colors);
+        self.keep_versions_row
+            .set_value(f64::from(settings.cache_keep_versions));
+    }
+
+    fn connect_rows(&self, ctx: AppContext, handles: UiHandles) {
 
         let ctx_clone = ctx.clone();
         self.theme_row
@@ -133,6 +416,18 @@ impl SettingsPage {
                 let _ = save_settings(&settings);
             });
 
+        let ctx_clone = ctx.clone();
+        let custom_theme_row = self.custom_theme_row.clone();
+        self.reload_theme.connect_clicked(move |_| {
+            let theme = ctx_clone.settings.lock().unwrap().theme;
+            let path = custom_theme_path().to_string_lossy().to_string();
+            match reload_custom_theme(theme) {
+                Ok(count) => custom_theme_row
+                    .set_subtitle(&format!("{path} ({count} custom colors loaded)")),
+                Err(err) => custom_theme_row.set_subtitle(&format!("{path}: {err}")),
+            }
+        });
+
         let ctx_clone = ctx.clone();
         let terminal_emulator_row = self.terminal_emulator_row.clone();
         self.terminal_mode_row
@@ -154,6 +449,15 @@ impl SettingsPage {
                 let _ = save_settings(&settings);
             });
 
+        let ctx_clone = ctx.clone();
+        self.escalation_row
+            .connect_selected_notify(move |row: &adw::ComboRow| {
+                let selected = row.selected();
+                let mut settings = ctx_clone.settings.lock().unwrap();
+                settings.escalation = EscalationMethod::from_index(selected);
+                let _ = save_settings(&settings);
+            });
+
         let ctx_clone = ctx.clone();
         self.helper_row
             .connect_selected_notify(move |row: &adw::ComboRow| {
@@ -174,10 +478,264 @@ impl SettingsPage {
             let _ = save_settings(&settings);
         });
 
+        let ctx_clone = ctx.clone();
+        let handles_clone = handles.clone();
+        self.queue_panel_row.connect_active_notify(move |row| {
+            handles_clone.queue.set_panel_docked(row.is_active());
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.queue_panel_docked = row.is_active();
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.needed_row.connect_active_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.skip_up_to_date = row.is_active();
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.separate_upgrade_row.connect_active_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.separate_aur_upgrade = row.is_active();
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.fuzzy_search_row.connect_active_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.fuzzy_search = row.is_active();
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.dependency_tree_row.connect_active_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.review_dependency_tree = row.is_active();
+            let _ = save_settings(&settings);
+        });
+
+        self.render_env_rows(&ctx);
+        let ctx_clone = ctx.clone();
+        let toasts = handles.toasts.clone();
+        let page = self.clone();
+        self.env_entry_row.connect_apply(move |row| {
+            let text = row.text().to_string();
+            let Some((name, value)) = text.split_once('=') else {
+                toasts.add_toast(adw::Toast::new("Enter the variable as NAME=value"));
+                return;
+            };
+            let name = name.trim();
+            if let Err(err) = validate_env_name(name) {
+                toasts.add_toast(adw::Toast::new(&err.to_string()));
+                return;
+            }
+            {
+                let mut settings = ctx_clone.settings.lock().unwrap();
+                settings.build_env.insert(name.to_string(), value.to_string());
+                let _ = save_settings(&settings);
+            }
+            row.set_text("");
+            page.render_env_rows(&ctx_clone);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.env_all_row.connect_active_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.env_all_commands = row.is_active();
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.enable_aur_row.connect_active_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.enable_aur = row.is_active();
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        let unused_runtimes_row = self.unused_runtimes_row.clone();
+        self.enable_flatpak_row.connect_active_notify(move |row| {
+            unused_runtimes_row.set_visible(row.is_active());
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.enable_flatpak = row.is_active();
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.dry_run_row.connect_active_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.dry_run = row.is_active();
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.offline_row.connect_active_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.offline = row.is_active();
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.update_interval_row.connect_value_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.update_check_minutes = row.value() as u32;
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        let prompt_response_row = self.prompt_response_row.clone();
+        self.prompt_timeout_row.connect_value_notify(move |row| {
+            let secs = row.value() as u32;
+            prompt_response_row.set_sensitive(secs > 0);
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.prompt_timeout_secs = secs;
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.prompt_response_row.connect_changed(move |row| {
+            let response = row.text().trim().to_string();
+            if response.is_empty() {
+                return;
+            }
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.prompt_timeout_response = response;
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.removal_threshold_row.connect_value_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.removal_warning_threshold = row.value() as u32;
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.command_timeout_row.connect_value_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.command_timeout_secs = row.value() as u32;
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.log_limit_row.connect_value_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.log_limit = row.value() as usize;
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.log_to_file_row.connect_active_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.log_to_file = row.is_active();
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.log_colors_row.connect_active_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.log_colors = row.is_active();
+            let _ = save_settings(&settings);
+        });
+
+        self.load_remotes(ctx.clone(), handles.clone());
+
+        let page = self.clone();
+        let ctx_clone = ctx.clone();
+        let handles_clone = handles.clone();
+        self.refresh_remotes.connect_clicked(move |_| {
+            page.load_remotes(ctx_clone.clone(), handles_clone.clone());
+        });
+
+        let ctx_clone = ctx.clone();
+        let handles_clone = handles.clone();
+        self.add_flathub.connect_clicked(move |_| {
+            let cmd = ctx_clone
+                .flatpak
+                .add_remote("flathub", "https://dl.flathub.org/repo/flathub.flatpakrepo");
+            handles_clone.queue.run_commands(vec![cmd]);
+        });
+
+        let handles_clone = handles.clone();
+        self.remove_unused_runtimes.connect_clicked(move |_| {
+            handles_clone.queue.remove_unused_runtimes_now();
+        });
+
         self.clear_cache.connect_clicked(move |_| {
             let _ = clear_screenshots_cache();
         });
 
+        let ctx_clone = ctx.clone();
+        self.keep_versions_row.connect_value_notify(move |row| {
+            let mut settings = ctx_clone.settings.lock().unwrap();
+            settings.cache_keep_versions = row.value() as u32;
+            let _ = save_settings(&settings);
+        });
+
+        let ctx_clone = ctx.clone();
+        let handles_clone = handles.clone();
+        self.clean_package_cache.connect_clicked(move |_| {
+            let cmd = clean_cache_command(&ctx_clone.settings.lock().unwrap());
+            handles_clone.queue.run_commands(vec![cmd]);
+        });
+
+        let ctx_clone = ctx.clone();
+        self.export_settings.connect_clicked(move |_| {
+            let dialog = gtk::FileDialog::new();
+            dialog.set_title("Export Settings");
+            dialog.set_initial_name(Some("aurora-settings.json"));
+            let data = serde_json::to_string_pretty(&*ctx_clone.settings.lock().unwrap());
+            dialog.save(None::<&gtk::Window>, gio::Cancellable::NONE, move |res| {
+                if let (Ok(file), Ok(data)) = (res, data) {
+                    if let Some(path) = file.path() {
+                        let _ = std::fs::write(path, data);
+                    }
+                }
+            });
+        });
+
+        let page = self.clone();
+        let ctx_clone = ctx.clone();
+        let toasts = handles.toasts.clone();
+        self.import_settings.connect_clicked(move |_| {
+            let dialog = gtk::FileDialog::new();
+            dialog.set_title("Import Settings");
+            let page = page.clone();
+            let ctx = ctx_clone.clone();
+            let toasts = toasts.clone();
+            dialog.open(None::<&gtk::Window>, gio::Cancellable::NONE, move |res| {
+                let Some(path) = res.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+                let result = std::fs::read_to_string(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|data| settings_from_json(&data));
+                let (settings, ignored) = match result {
+                    Ok(imported) => imported,
+                    Err(err) => {
+                        toasts.add_toast(adw::Toast::new(&format!(
+                            "Could not import settings: {err}"
+                        )));
+                        return;
+                    }
+                };
+                *ctx.settings.lock().unwrap() = settings.clone();
+                let _ = save_settings(&settings);
+                page.sync_rows(&settings);
+                page.render_env_rows(&ctx);
+                apply_theme(settings.theme);
+                if ignored.is_empty() {
+                    toasts.add_toast(adw::Toast::new("Settings imported"));
+                } else {
+                    toasts.add_toast(adw::Toast::new(&format!(
+                        "Settings imported; kept defaults for {}",
+                        ignored.join(", ")
+                    )));
+                }
+            });
+        });
+
         self.about_btn.connect_clicked(move |_| {
             let about = adw::AboutWindow::new();
             about.set_application_name("Aurora");
@@ -189,4 +747,150 @@ impl SettingsPage {
             about.present();
         });
     }
+
+    fn render_env_rows(&self, ctx: &AppContext) {
+        for row in self.env_rows.borrow_mut().drain(..) {
+            self.env_group.remove(&row);
+        }
+        let env = ctx.settings.lock().unwrap().build_env.clone();
+        for (name, value) in env {
+            let row = adw::ActionRow::new();
+            row.set_title(&name);
+            row.set_subtitle(&value);
+            row.set_subtitle_selectable(true);
+            row.set_activatable(false);
+            let remove = gtk::Button::from_icon_name("user-trash-symbolic");
+            set_icon_label(&remove, &format!("Remove {name}"));
+            remove.set_valign(gtk::Align::Center);
+            remove.add_css_class("flat");
+            let page = self.clone();
+            let ctx = ctx.clone();
+            remove.connect_clicked(move |_| {
+                {
+                    let mut settings = ctx.settings.lock().unwrap();
+                    settings.build_env.remove(&name);
+                    let _ = save_settings(&settings);
+                }
+                page.render_env_rows(&ctx);
+            });
+            row.add_suffix(&remove);
+            self.env_group.add(&row);
+            self.env_rows.borrow_mut().push(row);
+        }
+    }
+
+    fn load_remotes(&self, ctx: AppContext, handles: UiHandles) {
+        let (tx, rx) = mpsc::channel();
+        let ctx_thread = ctx.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(ctx_thread.flatpak.list_remotes());
+        });
+
+        let group = self.remotes_group.clone();
+        let rows = self.remote_rows.clone();
+        glib::idle_add_local(move || match rx.try_recv() {
+            Ok(result) => {
+                for row in rows.borrow_mut().drain(..) {
+                    group.remove(&row);
+                }
+                let remotes = match result {
+                    Ok(remotes) => remotes,
+                    Err(err) => {
+                        let row = adw::ActionRow::new();
+                        row.set_title("Failed to list remotes");
+                        row.set_subtitle(&err.to_string());
+                        group.add(&row);
+                        rows.borrow_mut().push(row);
+                        return glib::ControlFlow::Break;
+                    }
+                };
+                if remotes.is_empty() {
+                    let row = adw::ActionRow::new();
+                    row.set_title("No remotes configured");
+                    row.set_subtitle("Flatpak search returns nothing until a remote such as Flathub is added");
+                    group.add(&row);
+                    rows.borrow_mut().push(row);
+                }
+                for remote in remotes {
+                    let row = adw::ActionRow::new();
+                    row.set_title(&remote.name);
+                    row.set_subtitle(&remote.url);
+                    row.set_activatable(false);
+                    let remove = gtk::Button::with_label("Remove");
+                    remove.set_valign(gtk::Align::Center);
+                    remove.add_css_class("destructive-action");
+                    let ctx = ctx.clone();
+                    let handles = handles.clone();
+                    remove.connect_clicked(move |_| {
+                        let cmd = ctx.flatpak.remove_remote(&remote.name);
+                        handles.queue.run_commands(vec![cmd]);
+                    });
+                    row.add_suffix(&remove);
+                    group.add(&row);
+                    rows.borrow_mut().push(row);
+                }
+                glib::ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => glib::ControlFlow::Break,
+        });
+    }
+}
+
+// Theme choices are shown with a strip of their palette gradients next to the name.
+fn theme_preview_factory() -> gtk::SignalListItemFactory {
+    let factory = gtk::SignalListItemFactory::new();
+    factory.connect_setup(|_, item| {
+        let Some(item) = item.downcast_ref::<gtk::ListItem>() else {
+            return;
+        };
+        let row = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+        let swatch = gtk::DrawingArea::new();
+        swatch.set_content_width(54);
+        swatch.set_content_height(16);
+        swatch.set_valign(gtk::Align::Center);
+        let label = gtk::Label::new(None);
+        label.set_xalign(0.0);
+        row.append(&swatch);
+        row.append(&label);
+        item.set_child(Some(&row));
+    });
+    factory.connect_bind(|_, item| {
+        let Some(item) = item.downcast_ref::<gtk::ListItem>() else {
+            return;
+        };
+        let theme = ThemeMode::from_index(item.position());
+        let Some(swatch) = item
+            .child()
+            .and_then(|row| row.first_child())
+            .and_downcast::<gtk::DrawingArea>()
+        else {
+            return;
+        };
+        if let Some(label) = swatch.next_sibling().and_downcast::<gtk::Label>() {
+            label.set_text(theme.label());
+        }
+        let colors = theme_swatch_colors(theme);
+        swatch.set_draw_func(move |_, cr, width, height| {
+            let segment = f64::from(width) / colors.len() as f64;
+            for (index, (from, to)) in colors.iter().enumerate() {
+                let x = segment * index as f64;
+                let gradient = gtk::cairo::LinearGradient::new(x, 0.0, x + segment, f64::from(height));
+                for (offset, color) in [(0.0, from), (1.0, to)] {
+                    let rgba = gdk::RGBA::parse(*color).unwrap_or(gdk::RGBA::BLACK);
+                    gradient.add_color_stop_rgba(
+                        offset,
+                        f64::from(rgba.red()),
+                        f64::from(rgba.green()),
+                        f64::from(rgba.blue()),
+                        f64::from(rgba.alpha()),
+                    );
+                }
+                let _ = cr.set_source(&gradient);
+                cr.rectangle(x, 0.0, segment, f64::from(height));
+                let _ = cr.fill();
+            }
+        });
+    });
+    factory
 }
@@ -1,13 +1,21 @@
 use std::cell::RefCell;
-use std::process::Command;
 use std::rc::Rc;
 use std::sync::mpsc;
 
 use gtk::prelude::*;
 use libadwaita as adw;
 
-use crate::core::models::{ActionKind, AurHelperKind, PackageSource, TransactionAction};
-use crate::ui::AppContext;
+use crate::core::models::{ActionKind, PackageSource, TransactionAction};
+use crate::core::updates::{collect_updates_checked, update_error_note, UpdateItem};
+use crate::ui::{
+    restore_source_filter, save_filter, selected_filter_label, source_filter_index,
+    sync_source_filter, AppContext, Generation,
+};
+
+const SOURCE_FILTERS: [&str; 5] = ["All Sources", "Pacman", "AUR", "Flatpak", "Rebuilds"];
+
+type CountListeners = Rc<RefCell<Vec<Box<dyn Fn(usize)>>>>;
+type UpdateListeners = Rc<RefCell<Vec<Box<dyn Fn(&[UpdateItem])>>>>;
 
 #[derive(Clone)]
 pub struct UpdatesPage {
@@ -15,6 +23,7 @@ pub struct UpdatesPage {
     check_button: gtk::Button,
     select_all_button: gtk::Button,
     clear_selection_button: gtk::Button,
+    select_pattern: gtk::Entry,
     apply_button: gtk::Button,
     apply_selected_button: gtk::Button,
     list: gtk::ListBox,
@@ -22,7 +31,10 @@ pub struct UpdatesPage {
     search: gtk::SearchEntry,
     source_filter: gtk::DropDown,
     rows: Rc<RefCell<Vec<(gtk::CheckButton, TransactionAction, String)>>>,
-    all_updates: Rc<RefCell<Vec<(TransactionAction, String)>>>,
+    all_updates: Rc<RefCell<Vec<UpdateItem>>>,
+    count_listeners: CountListeners,
+    update_listeners: UpdateListeners,
+    generation: Generation,
 }
 
 impl UpdatesPage {
@@ -57,8 +69,7 @@ impl UpdatesPage {
         let search = gtk::SearchEntry::new();
         search.set_placeholder_text(Some("Filter updates"));
         root.append(&search);
-        let source_filter =
-            gtk::DropDown::from_strings(&["All Sources", "Pacman", "AUR", "Flatpak"]);
+        let source_filter = gtk::DropDown::from_strings(&SOURCE_FILTERS);
         source_filter.set_selected(0);
         root.append(&source_filter);
 
@@ -70,9 +81,16 @@ impl UpdatesPage {
         let apply_selected_button = gtk::Button::with_label("Update Selected");
         let apply_button = gtk::Button::with_label("Update All");
         apply_selected_button.add_css_class("suggested-action");
+        let select_pattern = gtk::Entry::new();
+        select_pattern.set_placeholder_text(Some("Select matching…"));
+        select_pattern.set_tooltip_text(Some(
+            "Select updates whose name matches a regex or glob, e.g. ^python- or lib*",
+        ));
+        select_pattern.set_width_chars(18);
         buttons.append(&check_button);
         buttons.append(&select_all_button);
         buttons.append(&clear_selection_button);
+        buttons.append(&select_pattern);
         buttons.append(&apply_selected_button);
         buttons.append(&apply_button);
         root.append(&buttons);
@@ -99,6 +117,7 @@ impl UpdatesPage {
             check_button,
             select_all_button,
             clear_selection_button,
+            select_pattern,
             apply_button,
             apply_selected_button,
             list,
@@ -107,11 +126,16 @@ impl UpdatesPage {
             source_filter,
             rows: Rc::new(RefCell::new(Vec::new())),
             all_updates: Rc::new(RefCell::new(Vec::new())),
+            count_listeners: Rc::new(RefCell::new(Vec::new())),
+            update_listeners: Rc::new(RefCell::new(Vec::new())),
+            generation: Generation::default(),
         }
     }
 
     pub fn bind(&self, ctx: AppContext) {
         self.refresh(ctx.clone(), None);
+        let saved_filter = ctx.settings.lock().unwrap().updates_source_filter;
+        restore_source_filter(&self.source_filter, &SOURCE_FILTERS, saved_filter);
 
         let rows_for_select_all = self.rows.clone();
         self.select_all_button.connect_clicked(move |_| {
@@ -127,44 +151,28 @@ impl UpdatesPage {
             }
         });
 
-        let list = self.list.clone();
-        let status = self.status.clone();
-        let rows = self.rows.clone();
-        let all_updates = self.all_updates.clone();
-        let search = self.search.clone();
-        let source_filter = self.source_filter.clone();
-        self.check_button.connect_clicked(move |_| {
-            let list = list.clone();
-            let status = status.clone();
-            let rows = rows.clone();
-            let all_updates = all_updates.clone();
-            let search = search.clone();
-            let source_filter = source_filter.clone();
-            let ctx = ctx.clone();
-            let (tx, rx) = mpsc::channel();
-            std::thread::spawn(move || {
-                let items = collect_updates(&ctx);
-                let _ = tx.send(items);
-            });
+        // Rows only exist for updates that pass the source filter, so the two combine.
+        let rows_for_pattern = self.rows.clone();
+        let status_for_pattern = self.status.clone();
+        self.select_pattern.connect_activate(move |entry| {
+            let pattern = entry.text().trim().to_string();
+            if pattern.is_empty() {
+                return;
+            }
+            let matcher = NameMatcher::new(&pattern);
+            let mut selected = 0usize;
+            for (check, action, _) in rows_for_pattern.borrow().iter() {
+                let matched = matcher.matches(&action.name);
+                check.set_active(matched);
+                selected += usize::from(matched);
+            }
+            status_for_pattern.set_text(&format!("Selected {selected} update(s) matching \"{pattern}\""));
+        });
 
-            glib::idle_add_local(move || {
-                match rx.try_recv() {
-                    Ok(items) => {
-                        *all_updates.borrow_mut() = items;
-                        render_updates(
-                            &list,
-                            &rows,
-                            &all_updates.borrow(),
-                            &search.text(),
-                            source_filter.selected(),
-                            &status,
-                        );
-                        glib::ControlFlow::Break
-                    }
-                    Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
-                    Err(mpsc::TryRecvError::Disconnected) => glib::ControlFlow::Break,
-                }
-            });
+        let page = self.clone();
+        let ctx_for_check = ctx.clone();
+        self.check_button.connect_clicked(move |_| {
+            page.refresh(ctx_for_check.clone(), None);
         });
 
         let list = self.list.clone();
@@ -180,7 +188,7 @@ impl UpdatesPage {
                 &rows,
                 &items,
                 &query,
-                source_filter.selected(),
+                &selected_filter_label(&source_filter),
                 &status,
             );
         });
@@ -191,9 +199,12 @@ impl UpdatesPage {
         let all_updates = self.all_updates.clone();
         let search = self.search.clone();
         self.source_filter.connect_selected_notify(move |f| {
+            save_filter(&ctx, |settings| {
+                settings.updates_source_filter = source_filter_index(f, &SOURCE_FILTERS);
+            });
             let query = search.text().to_string();
             let items = all_updates.borrow();
-            render_updates(&list, &rows, &items, &query, f.selected(), &status);
+            render_updates(&list, &rows, &items, &query, &selected_filter_label(f), &status);
         });
     }
 
@@ -204,26 +215,45 @@ impl UpdatesPage {
         let all_updates = self.all_updates.clone();
         let search = self.search.clone();
         let source_filter = self.source_filter.clone();
+        let count_listeners = self.count_listeners.clone();
+        let update_listeners = self.update_listeners.clone();
+        let settings = ctx.settings.lock().unwrap().clone();
+        sync_source_filter(&self.source_filter, &SOURCE_FILTERS, &settings);
+        let offline = settings.offline && settings.enable_aur;
+        let generation = self.generation.clone();
+        let current = generation.start();
         let (tx, rx) = mpsc::channel();
         std::thread::spawn(move || {
-            let items = collect_updates(&ctx);
-            let _ = tx.send(items);
+            let _ = tx.send(collect_updates_checked(&ctx));
         });
 
         let notify = notify.clone();
         glib::idle_add_local(move || match rx.try_recv() {
-            Ok(items) => {
+            _ if !generation.is_current(current) => glib::ControlFlow::Break,
+            Ok((items, errors)) => {
                 *all_updates.borrow_mut() = items;
                 render_updates(
                     &list,
                     &rows,
                     &all_updates.borrow(),
                     &search.text(),
-                    source_filter.selected(),
+                    &selected_filter_label(&source_filter),
                     &status,
                 );
+                if offline {
+                    status.set_text(&format!("{} (AUR skipped in offline mode)", status.text()));
+                }
+                for note in errors.iter().filter_map(update_error_note) {
+                    status.set_text(&format!("{} ({note})", status.text()));
+                }
+                let count = all_updates.borrow().len();
+                for listener in count_listeners.borrow().iter() {
+                    listener(count);
+                }
+                for listener in update_listeners.borrow().iter() {
+                    listener(&all_updates.borrow());
+                }
                 if let Some(toasts) = notify.as_ref() {
-                    let count = all_updates.borrow().len();
                     if count > 0 {
                         toasts.add_toast(adw::Toast::new(&format!(
                             "{} updates available",
@@ -238,6 +268,16 @@ impl UpdatesPage {
         });
     }
 
+    pub fn connect_count_changed<F: Fn(usize) + 'static>(&self, f: F) {
+        self.count_listeners.borrow_mut().push(Box::new(f));
+    }
+
+    // Called with the full result list after every refresh, so other pages can reuse it
+    // instead of running their own update check.
+    pub fn connect_updates_changed<F: Fn(&[UpdateItem]) + 'static>(&self, f: F) {
+        self.update_listeners.borrow_mut().push(Box::new(f));
+    }
+
     pub fn connect_apply_all<F: Fn() + 'static>(&self, f: F) {
         self.apply_button.connect_clicked(move |_| f());
     }
@@ -256,20 +296,51 @@ impl UpdatesPage {
     }
 }
 
-fn collect_updates(ctx: &AppContext) -> Vec<(TransactionAction, String)> {
-    let mut items = Vec::new();
-    items.extend(collect_pacman_updates());
-    items.extend(collect_aur_updates(ctx));
-    items.extend(collect_flatpak_updates());
-    items
+enum NameMatcher {
+    Regex(String),
+    Substring(String),
+}
+
+impl NameMatcher {
+    // Globs such as `python-*` become anchored regexes; patterns that fail to compile are
+    // matched as a plain substring instead of being rejected.
+    fn new(pattern: &str) -> Self {
+        let is_glob = pattern.contains(['*', '?'])
+            && !pattern.contains(['^', '$', '(', ')', '[', ']', '{', '}', '+', '|', '\\', '.']);
+        let source = if is_glob {
+            format!("^{}$", pattern.replace('*', ".*").replace('?', "."))
+        } else {
+            pattern.to_string()
+        };
+        match glib::Regex::new(
+            &source,
+            glib::RegexCompileFlags::CASELESS,
+            glib::RegexMatchFlags::DEFAULT,
+        ) {
+            Ok(Some(_)) => NameMatcher::Regex(source),
+            _ => NameMatcher::Substring(pattern.to_lowercase()),
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            NameMatcher::Regex(source) => glib::Regex::match_simple(
+                source.as_str(),
+                name,
+                glib::RegexCompileFlags::CASELESS,
+                glib::RegexMatchFlags::DEFAULT,
+            ),
+            NameMatcher::Substring(needle) => name.to_lowercase().contains(needle),
+        }
+    }
 }
 
 fn render_updates(
     list: &gtk::ListBox,
     rows: &Rc<RefCell<Vec<(gtk::CheckButton, TransactionAction, String)>>>,
-    items: &[(TransactionAction, String)],
+    items: &[UpdateItem],
     query: &str,
-    source_filter_idx: u32,
+    source_filter: &str,
     status: &gtk::Label,
 ) {
     while let Some(child) = list.first_child() {
@@ -278,15 +349,16 @@ fn render_updates(
     rows.borrow_mut().clear();
 
     let q = query.trim().to_lowercase();
-    let filtered: Vec<(TransactionAction, String)> = items
+    let filtered: Vec<UpdateItem> = items
         .iter()
-        .filter(|(action, _)| match source_filter_idx {
-            1 => action.source == PackageSource::Repo,
-            2 => action.source == PackageSource::Aur,
-            3 => action.source == PackageSource::Flatpak,
+        .filter(|item| match source_filter {
+            "Pacman" => item.action.source == PackageSource::Repo,
+            "AUR" => item.action.source == PackageSource::Aur,
+            "Flatpak" => item.action.source == PackageSource::Flatpak,
+            "Rebuilds" => item.rebuild,
             _ => true,
         })
-        .filter(|(_, display)| q.is_empty() || display.to_lowercase().contains(&q))
+        .filter(|item| q.is_empty() || item.display.to_lowercase().contains(&q))
         .cloned()
         .collect();
 
@@ -304,7 +376,12 @@ fn render_updates(
         filtered.len(),
         items.len()
     ));
-    for (action, display) in filtered {
+    for UpdateItem {
+        action,
+        display,
+        rebuild,
+    } in filtered
+    {
         let check = gtk::CheckButton::new();
         check.set_active(true);
         check.set_margin_end(2);
@@ -334,6 +411,7 @@ fn render_updates(
             ActionKind::Install => "Install",
             ActionKind::Remove => "Remove",
             ActionKind::Upgrade => "Upgrade",
+            ActionKind::Reinstall => "Reinstall",
         }));
         mode_badge.add_css_class("pill-secondary");
         mode_badge.set_width_chars(9);
@@ -341,6 +419,14 @@ fn render_updates(
         name_col.append(&name);
         name_col.append(&detail);
 
+        let rebuild_badge = gtk::Label::new(Some("Rebuild"));
+        rebuild_badge.add_css_class("pill");
+        rebuild_badge.add_css_class("warning");
+        rebuild_badge.set_tooltip_text(Some(
+            "Same upstream version; likely rebuilt against updated libraries",
+        ));
+        rebuild_badge.set_visible(rebuild);
+
         let row_box = gtk::Box::new(gtk::Orientation::Horizontal, 8);
         row_box.add_css_class("update-row-inner");
         row_box.set_margin_top(6);
@@ -349,6 +435,7 @@ fn render_updates(
         row_box.set_margin_end(6);
         row_box.append(&check);
         row_box.append(&name_col);
+        row_box.append(&rebuild_badge);
         row_box.append(&source_badge);
         row_box.append(&mode_badge);
 
@@ -371,111 +458,3 @@ fn header_label(text: &str, expand: bool, width_chars: i32) -> gtk::Label {
     }
     label
 }
-
-fn collect_pacman_updates() -> Vec<(TransactionAction, String)> {
-    let output = Command::new("pacman")
-        .args(["-Qu"])
-        .env("LC_ALL", "C")
-        .output()
-        .ok()
-        .and_then(|o| String::from_utf8(o.stdout).ok())
-        .unwrap_or_default();
-
-    output
-        .lines()
-        .filter(|l| !l.trim().is_empty())
-        .map(|line| {
-            let name = line.split_whitespace().next().unwrap_or("").to_string();
-            (
-                TransactionAction {
-                    name,
-                    source: PackageSource::Repo,
-                    kind: ActionKind::Install,
-                    origin: None,
-                },
-                line.to_string(),
-            )
-        })
-        .collect()
-}
-
-fn collect_aur_updates(ctx: &AppContext) -> Vec<(TransactionAction, String)> {
-    let helper = match ctx.settings.lock() {
-        Ok(settings) => settings.aur_helper,
-        Err(_) => AurHelperKind::Yay,
-    };
-    let output = Command::new(helper.as_str())
-        .args(["-Qua"])
-        .env("LC_ALL", "C")
-        .output()
-        .ok()
-        .and_then(|o| String::from_utf8(o.stdout).ok())
-        .unwrap_or_default();
-
-    output
-        .lines()
-        .filter(|l| !l.trim().is_empty())
-        .map(|line| {
-            let name = line.split_whitespace().next().unwrap_or("").to_string();
-            (
-                TransactionAction {
-                    name,
-                    source: PackageSource::Aur,
-                    kind: ActionKind::Install,
-                    origin: None,
-                },
-                format!("{line} (AUR)"),
-            )
-        })
-        .collect()
-}
-
-fn collect_flatpak_updates() -> Vec<(TransactionAction, String)> {
-    let output = Command::new("flatpak")
-        .args([
-            "remote-ls",
-            "--updates",
-            "--columns=application,version,branch,remote",
-        ])
-        .env("LC_ALL", "C")
-        .output()
-        .ok()
-        .and_then(|o| String::from_utf8(o.stdout).ok())
-        .unwrap_or_default();
-
-    let mut items = Vec::new();
-    for line in output.lines() {
-        if line.trim().is_empty() {
-            continue;
-        }
-        let cols: Vec<&str> = line.split('\t').collect();
-        let app_id = cols.get(0).unwrap_or(&"").trim().to_string();
-        if app_id.is_empty() {
-            continue;
-        }
-        let version = cols.get(1).unwrap_or(&"").trim();
-        let branch = cols.get(2).unwrap_or(&"").trim();
-        let remote = cols.get(3).unwrap_or(&"").trim();
-        let mut display = app_id.clone();
-        if !version.is_empty() {
-            display.push_str(&format!(" {version}"));
-        } else if !branch.is_empty() {
-            display.push_str(&format!(" {branch}"));
-        }
-        if !remote.is_empty() {
-            display.push_str(&format!(" ({remote})"));
-        }
-        display.push_str(" [Flatpak]");
-
-        items.push((
-            TransactionAction {
-                name: app_id,
-                source: PackageSource::Flatpak,
-                kind: ActionKind::Upgrade,
-                origin: None,
-            },
-            display,
-        ));
-    }
-    items
-}
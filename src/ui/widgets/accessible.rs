@@ -0,0 +1,9 @@
+use gtk::prelude::*;
+
+// Icon-only controls have nothing for a screen reader to announce, so the tooltip text
+// doubles as their accessible label.
+pub fn set_icon_label(widget: &impl IsA<gtk::Widget>, label: &str) {
+    let widget = widget.upcast_ref::<gtk::Widget>();
+    widget.set_tooltip_text(Some(label));
+    widget.update_property(&[gtk::accessible::Property::Label(label)]);
+}
@@ -1,10 +1,49 @@
 use gtk::prelude::*;
-use gtk::pango;
+use gtk::{gdk, gio, pango};
+use std::cell::RefCell;
 use std::rc::Rc;
+use std::sync::mpsc;
 
-use crate::core::models::PackageSummary;
+use crate::core::cache::{set_favorite, FavoriteKeys};
+use crate::core::icons::{cached_package_icon, resolve_package_icons};
+use crate::core::models::{PackageSource, PackageSummary};
+use crate::ui::widgets::accessible::set_icon_label;
 
-pub fn build_card<F, G>(pkg: &PackageSummary, on_action: F, on_details: G) -> gtk::Box
+pub fn build_card<F, G>(
+    pkg: &PackageSummary,
+    favorites: &FavoriteKeys,
+    on_action: F,
+    on_details: G,
+) -> gtk::Box
+where
+    F: Fn() + 'static,
+    G: Fn() + 'static,
+{
+    build(pkg, favorites, on_action, on_details, None)
+}
+
+// In selection mode a click on the card toggles `check` instead of opening the details.
+pub fn build_selectable_card<F, G>(
+    pkg: &PackageSummary,
+    favorites: &FavoriteKeys,
+    on_action: F,
+    on_details: G,
+    check: gtk::CheckButton,
+) -> gtk::Box
+where
+    F: Fn() + 'static,
+    G: Fn() + 'static,
+{
+    build(pkg, favorites, on_action, on_details, Some(check))
+}
+
+fn build<F, G>(
+    pkg: &PackageSummary,
+    favorites: &FavoriteKeys,
+    on_action: F,
+    on_details: G,
+    check: Option<gtk::CheckButton>,
+) -> gtk::Box
 where
     F: Fn() + 'static,
     G: Fn() + 'static,
@@ -24,6 +63,7 @@ where
     let icon = gtk::Image::from_icon_name("application-x-executable");
     icon.set_pixel_size(64);
     icon.set_halign(gtk::Align::Center);
+    load_package_icon(&icon, pkg);
 
     let name = gtk::Label::new(Some(&pkg.name));
     name.add_css_class("title-4");
@@ -50,6 +90,14 @@ where
     }));
     badge.add_css_class("pill");
     badge.set_xalign(0.0);
+    let badges = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    badges.append(&badge);
+    if let Some(count) = pkg.group_size {
+        let group = gtk::Label::new(Some("Group"));
+        group.add_css_class("pill");
+        group.set_tooltip_text(Some(&format!("Installs {count} packages")));
+        badges.append(&group);
+    }
 
     let actions = gtk::Box::new(gtk::Orientation::Horizontal, 6);
     actions.set_halign(gtk::Align::End);
@@ -65,22 +113,193 @@ where
         move |_| (details_fn.as_ref())()
     });
 
+    if let Some(check) = &check {
+        check.set_halign(gtk::Align::Start);
+        check.set_tooltip_text(Some("Select for queueing"));
+        root.append(check);
+    }
     root.append(&icon);
     root.append(&name);
     root.append(&summary);
-    root.append(&badge);
+    root.append(&badges);
+    let starred = favorites.contains(&(pkg.name.clone(), pkg.source));
+    actions.append(&favorite_button(pkg, starred));
     actions.append(&details_btn);
     actions.append(&button);
     root.append(&actions);
 
     let gesture = gtk::GestureClick::new();
+    gesture.set_button(gdk::BUTTON_PRIMARY);
     gesture.connect_pressed({
         let details_fn = details_fn.clone();
-        move |_, _, _, _| {
-            (details_fn.as_ref())();
+        move |gesture, _, _, _| match &check {
+            Some(check) => {
+                // Claim the click so the FlowBox does not replace the selection with this card.
+                gesture.set_state(gtk::EventSequenceState::Claimed);
+                check.set_active(!check.is_active());
+            }
+            None => (details_fn.as_ref())(),
         }
     });
     root.add_controller(gesture);
 
+    root.insert_action_group("card", Some(&card_actions(pkg, details_fn)));
+    let context = gtk::GestureClick::new();
+    context.set_button(gdk::BUTTON_SECONDARY);
+    let root_for_menu = root.clone();
+    let source = pkg.source;
+    context.connect_pressed(move |gesture, _, x, y| {
+        gesture.set_state(gtk::EventSequenceState::Claimed);
+        show_context_menu(&root_for_menu, source, x, y);
+    });
+    root.add_controller(context);
+
     root
 }
+
+fn card_actions(pkg: &PackageSummary, details_fn: Rc<impl Fn() + 'static>) -> gio::SimpleActionGroup {
+    let group = gio::SimpleActionGroup::new();
+
+    let copy = gio::SimpleAction::new("copy-name", None);
+    let name = pkg.name.clone();
+    copy.connect_activate(move |_, _| {
+        if let Some(display) = gdk::Display::default() {
+            display.clipboard().set_text(&name);
+        }
+    });
+    group.add_action(&copy);
+
+    let open = gio::SimpleAction::new("open-page", None);
+    let url = package_page_url(pkg);
+    open.connect_activate(move |_, _| {
+        let _ = gio::AppInfo::launch_default_for_uri(&url, None::<&gio::AppLaunchContext>);
+    });
+    group.add_action(&open);
+
+    let details = gio::SimpleAction::new("details", None);
+    details.connect_activate(move |_, _| (details_fn.as_ref())());
+    group.add_action(&details);
+
+    group
+}
+
+fn show_context_menu(root: &gtk::Box, source: PackageSource, x: f64, y: f64) {
+    let menu = gio::Menu::new();
+    menu.append(Some("Copy Name"), Some("card.copy-name"));
+    let open_label = match source {
+        PackageSource::Repo => "Open on archlinux.org",
+        PackageSource::Aur => "Open AUR Page",
+        PackageSource::Flatpak => "Open on Flathub",
+    };
+    menu.append(Some(open_label), Some("card.open-page"));
+    menu.append(Some("Details"), Some("card.details"));
+
+    let popover = gtk::PopoverMenu::from_model(Some(&menu));
+    popover.set_parent(root);
+    popover.set_has_arrow(false);
+    popover.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+    // Unparent after the activated item has run, or the card keeps a stray child.
+    popover.connect_closed(|popover| {
+        let popover = popover.clone();
+        glib::idle_add_local_once(move || popover.unparent());
+    });
+    popover.popup();
+}
+
+fn package_page_url(pkg: &PackageSummary) -> String {
+    match pkg.source {
+        PackageSource::Aur => format!("https://aur.archlinux.org/packages/{}", pkg.name),
+        PackageSource::Repo => format!("https://archlinux.org/packages/?name={}", pkg.name),
+        PackageSource::Flatpak => format!("https://flathub.org/apps/{}", pkg.name),
+    }
+}
+
+pub fn favorite_button(pkg: &PackageSummary, starred: bool) -> gtk::ToggleButton {
+    let button = gtk::ToggleButton::new();
+    button.add_css_class("flat");
+    button.set_active(starred);
+    update_favorite_icon(&button, starred);
+    let name = pkg.name.clone();
+    let source = pkg.source;
+    button.connect_toggled(move |button| {
+        let starred = button.is_active();
+        update_favorite_icon(button, starred);
+        let _ = set_favorite(&name, source, starred);
+    });
+    button
+}
+
+fn update_favorite_icon(button: &gtk::ToggleButton, starred: bool) {
+    if starred {
+        button.set_icon_name("starred-symbolic");
+        set_icon_label(button, "Remove from favorites");
+    } else {
+        button.set_icon_name("non-starred-symbolic");
+        set_icon_label(button, "Add to favorites");
+    }
+}
+
+pub fn load_package_icon(image: &gtk::Image, pkg: &PackageSummary) {
+    if let Some(display) = gdk::Display::default() {
+        if gtk::IconTheme::for_display(&display).has_icon(&pkg.name) {
+            image.set_icon_name(Some(&pkg.name));
+            return;
+        }
+    }
+
+    // Only installed repo/AUR packages have a file list to inspect.
+    if pkg.source == PackageSource::Flatpak || !pkg.installed {
+        return;
+    }
+
+    if let Some(icon) = cached_package_icon(&pkg.name) {
+        if let Some(icon) = icon {
+            set_package_icon(image, &icon);
+        }
+        return;
+    }
+
+    let first = PENDING_ICONS.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        pending.push((pkg.name.clone(), image.clone()));
+        pending.len() == 1
+    });
+    if first {
+        glib::idle_add_local_once(resolve_pending_icons);
+    }
+}
+
+thread_local! {
+    // Cards built in the same main loop pass share one lookup.
+    static PENDING_ICONS: RefCell<Vec<(String, gtk::Image)>> = const { RefCell::new(Vec::new()) };
+}
+
+fn resolve_pending_icons() {
+    let pending = PENDING_ICONS.with(RefCell::take);
+    let names: Vec<String> = pending.iter().map(|(name, _)| name.clone()).collect();
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(resolve_package_icons(&names));
+    });
+
+    glib::idle_add_local(move || match rx.try_recv() {
+        Ok(icons) => {
+            for (name, image) in &pending {
+                if let Some(Some(icon)) = icons.get(name) {
+                    set_package_icon(image, icon);
+                }
+            }
+            glib::ControlFlow::Break
+        }
+        Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
+        Err(mpsc::TryRecvError::Disconnected) => glib::ControlFlow::Break,
+    });
+}
+
+fn set_package_icon(image: &gtk::Image, icon: &str) {
+    if icon.starts_with('/') {
+        image.set_from_file(Some(icon));
+    } else {
+        image.set_icon_name(Some(icon));
+    }
+}
@@ -1,16 +1,31 @@
-use std::cell::RefCell;
-use std::path::PathBuf;
+use std::cell::{Cell, RefCell};
 use std::process::Command;
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
 use gtk::prelude::*;
 use gtk::{gdk, gio};
 
-const DEFAULT_LOG_LIMIT: usize = 1000;
+use crate::core::ansi::{parse_ansi, strip_ansi, AnsiColor};
+use crate::core::cache::{append_log_file, helper_path, log_file_path};
+use crate::core::health::{format_age, pacman_lock_age};
+use crate::core::models::{EscalationMethod, Settings};
+use crate::core::transactions::noninteractive_escalation_args;
+use crate::ui::widgets::accessible::set_icon_label;
+
 const DEFAULT_LOG_HEIGHT: i32 = 320;
 const MIN_LOG_HEIGHT: i32 = 72;
 const MAX_LOG_HEIGHT: i32 = 900;
 const LOG_RESIZE_HANDLE_HEIGHT: i32 = 10;
+const MAX_SEARCH_MATCHES: usize = 2000;
+const SEARCH_REFRESH_DELAY_MS: u64 = 300;
+
+#[derive(Default)]
+struct LogSearch {
+    query: String,
+    matches: Vec<(i32, i32)>,
+    current: Option<usize>,
+}
 
 #[derive(Clone)]
 pub struct LogDrawer {
@@ -20,10 +35,11 @@ pub struct LogDrawer {
     text_view: gtk::TextView,
     lines: Rc<RefCell<Vec<String>>>,
     min_height: Rc<RefCell<i32>>,
+    settings: Arc<Mutex<Settings>>,
 }
 
 impl LogDrawer {
-    pub fn new() -> Self {
+    pub fn new(settings: Arc<Mutex<Settings>>) -> Self {
         let header = gtk::Box::new(gtk::Orientation::Horizontal, 8);
         header.add_css_class("log-drawer-header");
         header.set_margin_top(4);
@@ -36,14 +52,24 @@ impl LogDrawer {
         title.set_xalign(0.0);
 
         let minimize_btn = gtk::Button::from_icon_name("pan-down-symbolic");
+        set_icon_label(&minimize_btn, "Minimize logs");
         let close_btn = gtk::Button::from_icon_name("window-close-symbolic");
+        set_icon_label(&close_btn, "Hide logs");
         let copy_btn = gtk::Button::with_label("Copy");
         let save_btn = gtk::Button::with_label("Save");
         let clear_btn = gtk::Button::with_label("Clear");
         let clear_lock_btn = gtk::Button::with_label("Clear Lock");
+        let search_entry = gtk::SearchEntry::new();
+        search_entry.set_placeholder_text(Some("Find in logs"));
+        search_entry.set_search_delay(250);
+        search_entry.set_width_chars(18);
+        let search_status = gtk::Label::new(None);
+        search_status.add_css_class("dim-label");
 
         header.append(&title);
         header.append(&gtk::Separator::new(gtk::Orientation::Horizontal));
+        header.append(&search_entry);
+        header.append(&search_status);
         header.append(&minimize_btn);
         header.append(&close_btn);
         header.append(&copy_btn);
@@ -57,6 +83,31 @@ impl LogDrawer {
         text_view.set_monospace(true);
 
         let buffer = text_view.buffer();
+        let match_tag = gtk::TextTag::builder()
+            .name("search-match")
+            .background("rgba(246, 211, 45, 0.35)")
+            .build();
+        let current_tag = gtk::TextTag::builder()
+            .name("search-current")
+            .background("rgba(255, 120, 0, 0.65)")
+            .build();
+        buffer.tag_table().add(&match_tag);
+        buffer.tag_table().add(&current_tag);
+        for (color, rgb) in [
+            (AnsiColor::Black, "#77767b"),
+            (AnsiColor::Red, "#e01b24"),
+            (AnsiColor::Green, "#2ec27e"),
+            (AnsiColor::Yellow, "#e5a50a"),
+            (AnsiColor::Blue, "#3584e4"),
+            (AnsiColor::Magenta, "#c061cb"),
+            (AnsiColor::Cyan, "#1c9fa8"),
+            (AnsiColor::White, "#9a9996"),
+        ] {
+            let tag = gtk::TextTag::builder().name(color.tag_name()).foreground(rgb).build();
+            buffer.tag_table().add(&tag);
+        }
+        let bold_tag = gtk::TextTag::builder().name("ansi-bold").weight(700).build();
+        buffer.tag_table().add(&bold_tag);
 
         let scroller = gtk::ScrolledWindow::new();
         scroller.add_css_class("log-drawer-scroller");
@@ -92,13 +143,21 @@ impl LogDrawer {
         });
 
         let lines_save = lines.clone();
+        let settings_save = settings.clone();
         save_btn.connect_clicked(move |_| {
             let dialog = gtk::FileDialog::new();
             dialog.set_title("Save Logs");
             let text = lines_save.borrow().join("\n");
+            let full_log = settings_save.lock().unwrap().log_to_file && log_file_path().exists();
+            if full_log {
+                dialog.set_initial_name(Some("aurora.log"));
+            }
             dialog.save(None::<&gtk::Window>, gio::Cancellable::NONE, move |res| {
                 if let Ok(file) = res {
                     if let Some(path) = file.path() {
+                        if full_log && std::fs::copy(log_file_path(), &path).is_ok() {
+                            return;
+                        }
                         let _ = std::fs::write(path, text);
                     }
                 }
@@ -115,13 +174,14 @@ impl LogDrawer {
         let lines_lock = lines.clone();
         let buffer_lock = buffer.clone();
         let text_view_lock = text_view.clone();
+        let settings_lock = settings.clone();
         clear_lock_btn.connect_clicked(move |_| {
             Self::append_line_internal(
                 &lines_lock,
                 &buffer_lock,
                 &text_view_lock,
+                &settings_lock,
                 "Checking for active package managers before lock cleanup...",
-                DEFAULT_LOG_LIMIT,
             );
 
             let running = match Self::running_package_managers() {
@@ -131,8 +191,8 @@ impl LogDrawer {
                         &lines_lock,
                         &buffer_lock,
                         &text_view_lock,
+                        &settings_lock,
                         &format!("Safety check failed: {err}"),
-                        DEFAULT_LOG_LIMIT,
                     );
                     return;
                 }
@@ -143,31 +203,45 @@ impl LogDrawer {
                     &lines_lock,
                     &buffer_lock,
                     &text_view_lock,
+                    &settings_lock,
                     &format!(
                         "Refusing to clear pacman lock because these processes are active: {}",
                         running.join(", ")
                     ),
-                    DEFAULT_LOG_LIMIT,
                 );
                 return;
             }
 
+            let Some(age) = pacman_lock_age() else {
+                Self::append_line_internal(
+                    &lines_lock,
+                    &buffer_lock,
+                    &text_view_lock,
+                    &settings_lock,
+                    "No pacman lock file present.",
+                );
+                return;
+            };
             Self::append_line_internal(
                 &lines_lock,
                 &buffer_lock,
                 &text_view_lock,
-                "No active package manager found. Requesting authentication...",
-                DEFAULT_LOG_LIMIT,
+                &settings_lock,
+                &format!(
+                    "No active package manager found. The lock was created {} ago. Requesting authentication...",
+                    format_age(age)
+                ),
             );
 
-            match Self::clear_stale_pacman_lock() {
+            let escalation = settings_lock.lock().unwrap().escalation;
+            match Self::clear_stale_pacman_lock(escalation) {
                 Ok(message) => {
                     Self::append_line_internal(
                         &lines_lock,
                         &buffer_lock,
                         &text_view_lock,
+                        &settings_lock,
                         &message,
-                        DEFAULT_LOG_LIMIT,
                     );
                 }
                 Err(err) => {
@@ -175,8 +249,8 @@ impl LogDrawer {
                         &lines_lock,
                         &buffer_lock,
                         &text_view_lock,
+                        &settings_lock,
                         &format!("Failed to clear pacman lock: {err}"),
-                        DEFAULT_LOG_LIMIT,
                     );
                 }
             }
@@ -207,8 +281,10 @@ impl LogDrawer {
             *minimized_apply.borrow_mut() = is_minimized;
             if is_minimized {
                 minimize_btn_apply.set_icon_name("pan-up-symbolic");
+                set_icon_label(&minimize_btn_apply, "Expand logs");
             } else {
                 minimize_btn_apply.set_icon_name("pan-down-symbolic");
+                set_icon_label(&minimize_btn_apply, "Minimize logs");
             }
         });
 
@@ -246,6 +322,69 @@ impl LogDrawer {
         });
         resize_handle.add_controller(drag_resize);
 
+        let search = Rc::new(RefCell::new(LogSearch::default()));
+
+        let search_changed = search.clone();
+        let buffer_search = buffer.clone();
+        let text_view_search = text_view.clone();
+        let status_search = search_status.clone();
+        search_entry.connect_search_changed(move |entry| {
+            search_changed.borrow_mut().query = entry.text().to_string();
+            Self::refresh_search(&buffer_search, &search_changed, false);
+            Self::step_search(&buffer_search, &text_view_search, &search_changed, &status_search, true);
+        });
+
+        let search_next = search.clone();
+        let buffer_next = buffer.clone();
+        let text_view_next = text_view.clone();
+        let status_next = search_status.clone();
+        let step = Rc::new(move |forward: bool| {
+            Self::step_search(&buffer_next, &text_view_next, &search_next, &status_next, forward);
+        });
+        let step_activate = step.clone();
+        search_entry.connect_activate(move |_| step_activate(true));
+        let step_next = step.clone();
+        search_entry.connect_next_match(move |_| step_next(true));
+        let step_previous = step.clone();
+        search_entry.connect_previous_match(move |_| step_previous(false));
+        search_entry.connect_stop_search(|entry| entry.set_text(""));
+
+        let keys = gtk::EventControllerKey::new();
+        keys.set_propagation_phase(gtk::PropagationPhase::Capture);
+        keys.connect_key_pressed(move |_, key, _, state| {
+            let enter = matches!(key, gdk::Key::Return | gdk::Key::KP_Enter);
+            if enter && state.contains(gdk::ModifierType::SHIFT_MASK) {
+                step(false);
+                return glib::Propagation::Stop;
+            }
+            glib::Propagation::Proceed
+        });
+        search_entry.add_controller(keys);
+
+        // New lines are not highlighted and trimming old ones shifts the stored match offsets,
+        // so re-search at most once per delay instead of after every streamed line.
+        let refresh_pending = Rc::new(Cell::new(false));
+        let search_refresh = search.clone();
+        let status_refresh = search_status.clone();
+        buffer.connect_changed(move |buffer| {
+            if search_refresh.borrow().query.is_empty() || refresh_pending.get() {
+                return;
+            }
+            refresh_pending.set(true);
+            let pending = refresh_pending.clone();
+            let search = search_refresh.clone();
+            let status = status_refresh.clone();
+            let buffer = buffer.clone();
+            glib::timeout_add_local_once(
+                std::time::Duration::from_millis(SEARCH_REFRESH_DELAY_MS),
+                move || {
+                    pending.set(false);
+                    Self::refresh_search(&buffer, &search, true);
+                    Self::update_search_status(&search, &status);
+                },
+            );
+        });
+
         let root_hide = root.clone();
         close_btn.connect_clicked(move |_| {
             root_hide.set_visible(false);
@@ -258,6 +397,7 @@ impl LogDrawer {
             text_view,
             lines,
             min_height,
+            settings,
         }
     }
 
@@ -265,8 +405,14 @@ impl LogDrawer {
         &self.root
     }
 
-    pub fn append_line(&self, line: &str, limit: usize) {
-        Self::append_line_internal(&self.lines, &self.buffer, &self.text_view, line, limit);
+    pub fn append_line(&self, line: &str) {
+        Self::append_line_internal(
+            &self.lines,
+            &self.buffer,
+            &self.text_view,
+            &self.settings,
+            line,
+        );
     }
 
     pub fn clear(&self) {
@@ -297,15 +443,43 @@ impl LogDrawer {
         lines: &Rc<RefCell<Vec<String>>>,
         buffer: &gtk::TextBuffer,
         text_view: &gtk::TextView,
+        settings: &Arc<Mutex<Settings>>,
         line: &str,
-        limit: usize,
     ) {
+        let (limit, log_to_file, colors) = {
+            let settings = settings.lock().unwrap();
+            (settings.log_limit.max(1), settings.log_to_file, settings.log_colors)
+        };
+        let plain = strip_ansi(line);
+        if log_to_file {
+            let _ = append_log_file(&plain);
+        }
+
         let mut lines = lines.borrow_mut();
-        lines.push(line.to_string());
+        if !lines.is_empty() {
+            buffer.insert(&mut buffer.end_iter(), "\n");
+        }
+        if colors && plain.len() != line.len() {
+            for span in parse_ansi(line) {
+                let mut tags = Vec::new();
+                if let Some(color) = span.color {
+                    tags.push(color.tag_name());
+                }
+                if span.bold {
+                    tags.push("ansi-bold");
+                }
+                buffer.insert_with_tags_by_name(&mut buffer.end_iter(), &span.text, &tags);
+            }
+        } else {
+            buffer.insert(&mut buffer.end_iter(), &plain);
+        }
+        lines.push(plain);
         while lines.len() > limit {
             lines.remove(0);
+            if let Some(mut second) = buffer.iter_at_line(1) {
+                buffer.delete(&mut buffer.start_iter(), &mut second);
+            }
         }
-        buffer.set_text(&lines.join("\n"));
         Self::scroll_to_bottom_internal(buffer, text_view);
     }
 
@@ -315,6 +489,91 @@ impl LogDrawer {
         text_view.scroll_to_iter(&mut end, 0.0, false, 0.0, 1.0);
     }
 
+    fn refresh_search(buffer: &gtk::TextBuffer, search: &Rc<RefCell<LogSearch>>, keep_position: bool) {
+        let (start, end) = buffer.bounds();
+        buffer.remove_tag_by_name("search-match", &start, &end);
+        buffer.remove_tag_by_name("search-current", &start, &end);
+
+        let mut search = search.borrow_mut();
+        search.matches.clear();
+        if !keep_position {
+            search.current = None;
+        }
+        if search.query.is_empty() {
+            search.current = None;
+            return;
+        }
+
+        let flags = gtk::TextSearchFlags::CASE_INSENSITIVE | gtk::TextSearchFlags::TEXT_ONLY;
+        let mut from = buffer.start_iter();
+        while search.matches.len() < MAX_SEARCH_MATCHES {
+            let Some((match_start, match_end)) = from.forward_search(&search.query, flags, None) else {
+                break;
+            };
+            buffer.apply_tag_by_name("search-match", &match_start, &match_end);
+            search.matches.push((match_start.offset(), match_end.offset()));
+            from = match_end;
+        }
+
+        let count = search.matches.len();
+        search.current = search.current.filter(|_| count > 0).map(|idx| idx.min(count - 1));
+        if let Some(idx) = search.current {
+            let (start, end) = search.matches[idx];
+            buffer.apply_tag_by_name(
+                "search-current",
+                &buffer.iter_at_offset(start),
+                &buffer.iter_at_offset(end),
+            );
+        }
+    }
+
+    fn step_search(
+        buffer: &gtk::TextBuffer,
+        text_view: &gtk::TextView,
+        search: &Rc<RefCell<LogSearch>>,
+        status: &gtk::Label,
+        forward: bool,
+    ) {
+        {
+            let mut state = search.borrow_mut();
+            let count = state.matches.len();
+            if count > 0 {
+                let next = match (state.current, forward) {
+                    (None, true) => 0,
+                    (None, false) => count - 1,
+                    (Some(idx), true) => (idx + 1) % count,
+                    (Some(idx), false) => (idx + count - 1) % count,
+                };
+                state.current = Some(next);
+
+                let (start, end) = buffer.bounds();
+                buffer.remove_tag_by_name("search-current", &start, &end);
+                let (from, to) = state.matches[next];
+                let mut match_start = buffer.iter_at_offset(from);
+                buffer.apply_tag_by_name("search-current", &match_start, &buffer.iter_at_offset(to));
+                text_view.scroll_to_iter(&mut match_start, 0.1, true, 0.0, 0.5);
+            }
+        }
+        Self::update_search_status(search, status);
+    }
+
+    fn update_search_status(search: &Rc<RefCell<LogSearch>>, status: &gtk::Label) {
+        let search = search.borrow();
+        let text = if search.query.is_empty() {
+            String::new()
+        } else if search.matches.is_empty() {
+            String::from("No matches")
+        } else {
+            let capped = if search.matches.len() >= MAX_SEARCH_MATCHES { "+" } else { "" };
+            format!(
+                "{}/{}{capped}",
+                search.current.map_or(0, |idx| idx + 1),
+                search.matches.len()
+            )
+        };
+        status.set_text(&text);
+    }
+
     fn set_scroller_height(scroller: &gtk::ScrolledWindow, height: i32) {
         // Clear min/max constraints before applying a new fixed height to avoid
         // GTK assertion failures when toggling between small/large values.
@@ -341,13 +600,15 @@ impl LogDrawer {
         Ok(running)
     }
 
-    fn clear_stale_pacman_lock() -> Result<String, String> {
-        let helper = Self::helper_path();
-        let output = Command::new("pkexec")
+    fn clear_stale_pacman_lock(escalation: EscalationMethod) -> Result<String, String> {
+        let helper = helper_path();
+        let program = escalation.as_str();
+        let output = Command::new(program)
+            .args(noninteractive_escalation_args(escalation))
             .arg(&helper)
             .arg("clear-pacman-lock")
             .output()
-            .map_err(|err| format!("failed to run pkexec: {err}"))?;
+            .map_err(|err| format!("failed to run {program}: {err}"))?;
 
         let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
         let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
@@ -366,17 +627,4 @@ impl LogDrawer {
             Err(format!("command failed with status {}", output.status))
         }
     }
-
-    fn helper_path() -> String {
-        if let Ok(exe) = std::env::current_exe() {
-            if let Some(dir) = exe.parent() {
-                let mut candidate = PathBuf::from(dir);
-                candidate.push("aurora-helper");
-                if candidate.exists() {
-                    return candidate.to_string_lossy().to_string();
-                }
-            }
-        }
-        "/usr/bin/aurora-helper".to_string()
-    }
 }
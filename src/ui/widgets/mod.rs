@@ -1,3 +1,6 @@
+pub mod accessible;
 pub mod card;
 pub mod log_drawer;
+pub mod plan_tree;
+pub mod queue_panel;
 pub mod screenshot_carousel;
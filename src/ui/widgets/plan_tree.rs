@@ -0,0 +1,188 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::{mpsc, Arc};
+
+use glib::{BoxedAnyObject, ControlFlow};
+use gtk::gio;
+use gtk::prelude::*;
+
+use crate::core::models::{ActionKind, PackageSource, TransactionAction, TransactionQueue};
+use crate::core::providers::PacmanProvider;
+use crate::ui::AppContext;
+
+enum PlanNode {
+    Action {
+        action: TransactionAction,
+        resolving: Cell<bool>,
+    },
+    Package {
+        name: String,
+        removed: bool,
+    },
+    Note(String),
+}
+
+type WatchedRow = Rc<RefCell<Option<(gtk::TreeListRow, glib::SignalHandlerId)>>>;
+
+// One top-level row per queued action. Installs expand into the packages they pull in and
+// removals into what goes with them, resolved the first time the row is opened.
+pub fn build(queue: &TransactionQueue, ctx: &AppContext) -> gtk::ListView {
+    let root = gio::ListStore::new::<BoxedAnyObject>();
+    for action in &queue.actions {
+        root.append(&BoxedAnyObject::new(PlanNode::Action {
+            action: action.clone(),
+            resolving: Cell::new(false),
+        }));
+    }
+    let model = gtk::TreeListModel::new(root, false, false, |item| {
+        action_of(item).filter(expands).map(|_| {
+            let children = gio::ListStore::new::<BoxedAnyObject>();
+            children.append(&BoxedAnyObject::new(PlanNode::Note(
+                "Resolving\u{2026}".to_string(),
+            )));
+            children.upcast()
+        })
+    });
+
+    let factory = gtk::SignalListItemFactory::new();
+    let pacman = ctx.pacman.clone();
+    factory.connect_setup(move |_, item| {
+        let Some(item) = item.downcast_ref::<gtk::ListItem>() else {
+            return;
+        };
+        let label = gtk::Label::new(None);
+        label.set_xalign(0.0);
+        let expander = gtk::TreeExpander::new();
+        expander.set_child(Some(&label));
+        item.set_child(Some(&expander));
+
+        // Rows are recycled, so the expansion watch follows whichever row is bound.
+        let watched: WatchedRow = Rc::new(RefCell::new(None));
+        let pacman = pacman.clone();
+        expander.connect_list_row_notify(move |expander| {
+            if let Some((row, handler)) = watched.borrow_mut().take() {
+                row.disconnect(handler);
+            }
+            let Some(row) = expander.list_row() else {
+                return;
+            };
+            let pacman = pacman.clone();
+            let handler = row.connect_expanded_notify(move |row| {
+                if row.is_expanded() {
+                    resolve_children(row, pacman.clone());
+                }
+            });
+            *watched.borrow_mut() = Some((row, handler));
+        });
+    });
+    factory.connect_bind(|_, item| {
+        let Some(item) = item.downcast_ref::<gtk::ListItem>() else {
+            return;
+        };
+        let Some(row) = item.item().and_downcast::<gtk::TreeListRow>() else {
+            return;
+        };
+        let Some(expander) = item.child().and_downcast::<gtk::TreeExpander>() else {
+            return;
+        };
+        expander.set_list_row(Some(&row));
+        let Some(label) = expander.child().and_downcast::<gtk::Label>() else {
+            return;
+        };
+        let Some(node) = row.item().and_downcast::<BoxedAnyObject>() else {
+            return;
+        };
+        let node = node.borrow::<PlanNode>();
+        let (text, class) = match &*node {
+            PlanNode::Action { action, .. } => (
+                format!("{:?} {}", action.kind, action.name),
+                (action.kind == ActionKind::Remove).then_some("error"),
+            ),
+            PlanNode::Package { name, removed } => (name.clone(), removed.then_some("error")),
+            PlanNode::Note(note) => (note.clone(), Some("dim-label")),
+        };
+        label.set_text(&text);
+        label.set_css_classes(class.as_slice());
+    });
+    factory.connect_unbind(|_, item| {
+        if let Some(expander) = item
+            .downcast_ref::<gtk::ListItem>()
+            .and_then(|item| item.child())
+            .and_downcast::<gtk::TreeExpander>()
+        {
+            expander.set_list_row(None);
+        }
+    });
+
+    gtk::ListView::new(Some(gtk::NoSelection::new(Some(model))), Some(factory))
+}
+
+fn action_of(item: &glib::Object) -> Option<TransactionAction> {
+    let node = item.downcast_ref::<BoxedAnyObject>()?.borrow::<PlanNode>();
+    match &*node {
+        PlanNode::Action { action, .. } => Some(action.clone()),
+        _ => None,
+    }
+}
+
+// Flatpak and AUR installs are resolved by flatpak and the AUR helper, which have no
+// equivalent of pacman's --print.
+fn expands(action: &TransactionAction) -> bool {
+    matches!(
+        (action.source, action.kind),
+        (PackageSource::Repo, ActionKind::Install | ActionKind::Reinstall)
+            | (PackageSource::Repo | PackageSource::Aur, ActionKind::Remove)
+    )
+}
+
+fn resolve_children(row: &gtk::TreeListRow, pacman: Arc<dyn PacmanProvider>) {
+    let Some(node) = row.item().and_downcast::<BoxedAnyObject>() else {
+        return;
+    };
+    let action = match &*node.borrow::<PlanNode>() {
+        PlanNode::Action { action, resolving } if !resolving.replace(true) => action.clone(),
+        _ => return,
+    };
+    let Some(children) = row.children().and_downcast::<gio::ListStore>() else {
+        return;
+    };
+
+    let removed = action.kind == ActionKind::Remove;
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let names = [action.name.clone()];
+        let found = if removed {
+            pacman.removal_preview(&names)
+        } else {
+            pacman.install_preview(&names)
+        };
+        let _ = tx.send(found.map(|mut packages| {
+            packages.retain(|name| *name != action.name);
+            packages.sort();
+            packages
+        }));
+    });
+
+    glib::idle_add_local(move || match rx.try_recv() {
+        Ok(found) => {
+            let nodes: Vec<BoxedAnyObject> = match found {
+                Ok(packages) if packages.is_empty() => {
+                    vec![BoxedAnyObject::new(PlanNode::Note(
+                        "No other packages".to_string(),
+                    ))]
+                }
+                Ok(packages) => packages
+                    .into_iter()
+                    .map(|name| BoxedAnyObject::new(PlanNode::Package { name, removed }))
+                    .collect(),
+                Err(err) => vec![BoxedAnyObject::new(PlanNode::Note(format!(
+                    "Could not resolve: {err}"
+                )))],
+            };
+            children.splice(0, children.n_items(), &nodes);
+            ControlFlow::Break
+        }
+        Err(mpsc::TryRecvError::Empty) => ControlFlow::Continue,
+        Err(mpsc::TryRecvError::Disconnected) => ControlFlow::Break,
+    });
+}
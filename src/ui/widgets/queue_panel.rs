@@ -0,0 +1,140 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk::prelude::*;
+use libadwaita as adw;
+use adw::prelude::*;
+
+use crate::core::models::{ActionKind, PackageSource, TransactionAction, TransactionQueue};
+use crate::ui::widgets::accessible::set_icon_label;
+
+type RemoveCallback = Rc<dyn Fn(TransactionAction)>;
+
+#[derive(Clone)]
+pub struct QueuePanel {
+    root: gtk::Revealer,
+    list: gtk::ListBox,
+    summary: gtk::Label,
+    execute_button: gtk::Button,
+    clear_button: gtk::Button,
+    on_remove: Rc<RefCell<Option<RemoveCallback>>>,
+}
+
+impl QueuePanel {
+    pub fn new() -> Self {
+        let title = gtk::Label::new(Some("Queue"));
+        title.add_css_class("title-4");
+        title.set_xalign(0.0);
+
+        let summary = gtk::Label::new(Some("Queue is empty"));
+        summary.add_css_class("dim-label");
+        summary.set_xalign(0.0);
+        summary.set_wrap(true);
+
+        let list = gtk::ListBox::new();
+        list.add_css_class("boxed-list");
+        list.set_selection_mode(gtk::SelectionMode::None);
+        list.set_valign(gtk::Align::Start);
+
+        let scroller = gtk::ScrolledWindow::new();
+        scroller.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scroller.set_vexpand(true);
+        scroller.set_child(Some(&list));
+
+        let clear_button = gtk::Button::with_label("Clear");
+        clear_button.set_hexpand(true);
+        let execute_button = gtk::Button::with_label("Execute");
+        execute_button.add_css_class("suggested-action");
+        execute_button.set_hexpand(true);
+        let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+        buttons.append(&clear_button);
+        buttons.append(&execute_button);
+
+        let content = gtk::Box::new(gtk::Orientation::Vertical, 8);
+        content.set_width_request(280);
+        content.set_margin_top(12);
+        content.set_margin_bottom(12);
+        content.set_margin_start(12);
+        content.set_margin_end(12);
+        content.append(&title);
+        content.append(&summary);
+        content.append(&scroller);
+        content.append(&buttons);
+
+        let panel = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        panel.append(&gtk::Separator::new(gtk::Orientation::Vertical));
+        panel.append(&content);
+
+        let root = gtk::Revealer::new();
+        root.set_transition_type(gtk::RevealerTransitionType::SlideLeft);
+        root.set_child(Some(&panel));
+
+        let this = Self {
+            root,
+            list,
+            summary,
+            execute_button,
+            clear_button,
+            on_remove: Rc::new(RefCell::new(None)),
+        };
+        this.set_queue(&TransactionQueue::default(), "Queue is empty");
+        this
+    }
+
+    pub fn widget(&self) -> &gtk::Revealer {
+        &self.root
+    }
+
+    pub fn set_docked(&self, docked: bool) {
+        self.root.set_reveal_child(docked);
+    }
+
+    pub fn set_queue(&self, queue: &TransactionQueue, summary: &str) {
+        self.summary.set_label(summary);
+        self.execute_button.set_sensitive(!queue.is_empty());
+        self.clear_button.set_sensitive(!queue.is_empty());
+        while let Some(child) = self.list.first_child() {
+            self.list.remove(&child);
+        }
+        self.list.set_visible(!queue.is_empty());
+
+        for action in &queue.actions {
+            let row = adw::ActionRow::new();
+            row.set_title(&format!("{:?} {}", action.kind, action.name));
+            row.set_subtitle(match action.source {
+                PackageSource::Repo => "Pacman",
+                PackageSource::Aur => "AUR",
+                PackageSource::Flatpak if action.delete_data => "Flatpak, deletes app data",
+                PackageSource::Flatpak => "Flatpak",
+            });
+            if action.kind == ActionKind::Remove {
+                row.add_css_class("error");
+            }
+            let remove = gtk::Button::from_icon_name("list-remove-symbolic");
+            set_icon_label(&remove, &format!("Remove {} from queue", action.name));
+            remove.set_valign(gtk::Align::Center);
+            remove.add_css_class("flat");
+            let on_remove = self.on_remove.clone();
+            let action = action.clone();
+            remove.connect_clicked(move |_| {
+                if let Some(callback) = on_remove.borrow().clone() {
+                    callback(action.clone());
+                }
+            });
+            row.add_suffix(&remove);
+            self.list.append(&row);
+        }
+    }
+
+    pub fn connect_execute<F: Fn() + 'static>(&self, f: F) {
+        self.execute_button.connect_clicked(move |_| f());
+    }
+
+    pub fn connect_clear<F: Fn() + 'static>(&self, f: F) {
+        self.clear_button.connect_clicked(move |_| f());
+    }
+
+    pub fn connect_remove<F: Fn(TransactionAction) + 'static>(&self, f: F) {
+        *self.on_remove.borrow_mut() = Some(Rc::new(f));
+    }
+}
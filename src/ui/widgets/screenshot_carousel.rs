@@ -1,14 +1,19 @@
 use std::cell::RefCell;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::mpsc;
 
+use gtk::gdk_pixbuf::Pixbuf;
 use gtk::prelude::*;
 use gio;
 use libadwaita as adw;
+use adw::prelude::*;
 
 use crate::core::appstream::AppStreamClient;
 
+// Twice the carousel width, so screenshots stay sharp on HiDPI displays and in fullscreen.
+const MAX_SCREENSHOT_SIZE: i32 = 1280;
+
 #[derive(Clone)]
 pub struct ScreenshotCarousel {
     root: gtk::Box,
@@ -42,25 +47,42 @@ impl ScreenshotCarousel {
         &self.root
     }
 
-    pub fn set_screenshots(&self, urls: Vec<String>) {
+    pub fn set_screenshots(&self, urls: Vec<String>, offline: bool) {
         for child in self.children.borrow_mut().drain(..) {
             self.carousel.remove(&child);
         }
         self.pictures.borrow_mut().clear();
 
-        if urls.is_empty() {
-            let label = gtk::Label::new(Some("No screenshots available"));
+        // Offline, only screenshots that were downloaded earlier can be shown.
+        let cached: Vec<PathBuf> = if offline {
+            urls.iter()
+                .filter_map(|url| AppStreamClient::cached_path_for_url(url))
+                .filter(|path| path.exists())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let count = if offline { cached.len() } else { urls.len() };
+
+        if count == 0 {
+            let text = if offline && !urls.is_empty() {
+                "Screenshots are not downloaded in offline mode"
+            } else {
+                "No screenshots available"
+            };
+            let label = gtk::Label::new(Some(text));
             label.add_css_class("dim-label");
             self.carousel.append(&label);
             self.children.borrow_mut().push(label.upcast());
             return;
         }
 
-        for _ in &urls {
+        for _ in 0..count {
             let picture = gtk::Picture::new();
-            picture.set_content_fit(gtk::ContentFit::Cover);
+            picture.set_content_fit(gtk::ContentFit::Contain);
             picture.set_can_shrink(true);
-            picture.set_size_request(640, 360);
+            picture.set_hexpand(true);
+            picture.set_size_request(-1, 360);
 
             let spinner = gtk::Spinner::new();
             spinner.set_halign(gtk::Align::Center);
@@ -72,6 +94,15 @@ impl ScreenshotCarousel {
             overlay.add_overlay(&spinner);
             overlay.set_size_request(640, 360);
 
+            let click = gtk::GestureClick::new();
+            let picture_for_click = picture.clone();
+            click.connect_released(move |_, _, _, _| {
+                if let Some(file) = picture_for_click.file() {
+                    show_fullscreen(&picture_for_click, &file);
+                }
+            });
+            overlay.add_controller(click);
+
             self.carousel.append(&overlay);
             self.children.borrow_mut().push(overlay.upcast());
             self.pictures.borrow_mut().push((picture, spinner));
@@ -80,9 +111,15 @@ impl ScreenshotCarousel {
         let pictures = self.pictures.clone();
         let (tx, rx) = mpsc::channel();
         std::thread::spawn(move || {
+            if offline {
+                for (idx, path) in cached.into_iter().enumerate() {
+                    let _ = tx.send((idx, scaled_screenshot(path)));
+                }
+                return;
+            }
             for (idx, url) in urls.iter().enumerate() {
                 if let Some(path) = AppStreamClient::ensure_cached(url) {
-                    let _ = tx.send((idx, path));
+                    let _ = tx.send((idx, scaled_screenshot(path)));
                 }
             }
         });
@@ -98,6 +135,38 @@ impl ScreenshotCarousel {
     }
 }
 
+// AppStream screenshots are often full-resolution PNGs. A downscaled copy is kept next to the
+// download so the carousel never has to decode the original; the original is returned when
+// it is already small enough or cannot be scaled.
+fn scaled_screenshot(original: PathBuf) -> PathBuf {
+    let scaled = scaled_path(&original);
+    if scaled.exists() {
+        return scaled;
+    }
+    let Some((_, width, height)) = Pixbuf::file_info(&original) else {
+        return original;
+    };
+    if width <= MAX_SCREENSHOT_SIZE && height <= MAX_SCREENSHOT_SIZE {
+        return original;
+    }
+    let Ok(pixbuf) =
+        Pixbuf::from_file_at_scale(&original, MAX_SCREENSHOT_SIZE, MAX_SCREENSHOT_SIZE, true)
+    else {
+        return original;
+    };
+    let partial = scaled.with_extension("part");
+    if pixbuf.savev(&partial, "png", &[]).is_err() || std::fs::rename(&partial, &scaled).is_err() {
+        let _ = std::fs::remove_file(&partial);
+        return original;
+    }
+    scaled
+}
+
+fn scaled_path(original: &Path) -> PathBuf {
+    let name = original.file_name().unwrap_or_default().to_string_lossy();
+    original.with_file_name(format!("{name}.scaled.png"))
+}
+
 fn update_picture(pictures: &Rc<RefCell<Vec<(gtk::Picture, gtk::Spinner)>>>, idx: usize, path: PathBuf) {
     if let Some((picture, spinner)) = pictures.borrow().get(idx) {
         let file = gio::File::for_path(path);
@@ -106,3 +175,37 @@ fn update_picture(pictures: &Rc<RefCell<Vec<(gtk::Picture, gtk::Spinner)>>>, idx
         spinner.set_visible(false);
     }
 }
+
+fn show_fullscreen(source: &gtk::Picture, file: &gio::File) {
+    let picture = gtk::Picture::for_file(file);
+    picture.set_content_fit(gtk::ContentFit::Contain);
+    picture.set_can_shrink(true);
+    picture.set_hexpand(true);
+    picture.set_vexpand(true);
+
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    content.append(&adw::HeaderBar::new());
+    content.append(&picture);
+
+    let window = adw::Window::new();
+    window.set_title(Some("Screenshot"));
+    window.set_modal(true);
+    window.set_default_size(1200, 800);
+    window.set_content(Some(&content));
+    if let Some(parent) = source.root().and_downcast::<gtk::Window>() {
+        window.set_transient_for(Some(&parent));
+    }
+
+    let keys = gtk::EventControllerKey::new();
+    let window_for_keys = window.clone();
+    keys.connect_key_pressed(move |_, key, _, _| {
+        if key == gtk::gdk::Key::Escape {
+            window_for_keys.close();
+            return glib::Propagation::Stop;
+        }
+        glib::Propagation::Proceed
+    });
+    window.add_controller(keys);
+    window.maximize();
+    window.present();
+}